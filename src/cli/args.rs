@@ -0,0 +1,570 @@
+use crate::config::{parse_duration, SettingOverride};
+use crate::interface::InterfaceSelector;
+use crate::packet::reader::{BackendKind, ImpairmentOptions, Pacing, SenderThreadOptions};
+use crate::packet::transform::{AnonymizationKey, Decapsulation, Encapsulation, PayloadPolicy, Sanitizer, TransformError, TransformPipeline, VlanOperation};
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "TimescaleDBに保存したパケットをネットワークインターフェースへ再生します",
+    args_conflicts_with_subcommands = true,
+    args_override_self = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// 起動時の事前チェック (権限・インターフェース・データベースのスキーマ) を省略する
+    #[arg(long, global = true)]
+    pub skip_preflight: bool,
+
+    /// 設定ファイル (TOML)。省略時はカレントディレクトリに packet-flow.toml があれば読み込む
+    #[arg(long, global = true, env = "PACKET_FLOW_CONFIG", value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// 設定を上書きする (例: --set database.host=10.0.0.5)。環境変数や設定ファイルより優先される
+    #[arg(long = "set", global = true, value_name = "KEY=VALUE")]
+    pub settings: Vec<SettingOverride>,
+
+    /// サブコマンドを省略した場合は再生を行う
+    #[command(flatten)]
+    pub replay: ReplayArgs,
+}
+
+impl Cli {
+    /// 属性では表せない引数の組み合わせを確認する
+    pub fn validate(self) -> Result<Self, clap::Error> {
+        // 送信時刻を指定するバックエンドでは、全て同じ時刻になる最高速の送信は意味をなさない
+        if self.replay.backend == BackendKind::TxTime && self.replay.top_speed {
+            return Err(Self::command().error(ErrorKind::ArgumentConflict, "--backend txtime は --top-speed と同時に指定できません"));
+        }
+        Ok(self)
+    }
+
+    /// プロファイルの引数の後ろに実際のコマンドライン引数を続けて解析し直す
+    ///
+    /// 値を1つ取るオプションは後ろの指定が優先されるため、コマンドラインでプロファイルの設定を上書きできる。
+    /// 複数回指定できるオプション (`--vlan`等) はプロファイルの分に追加される。
+    pub fn parse_with_profile(profile_arguments: &[String]) -> Result<Self, clap::Error> {
+        let mut arguments = std::env::args_os();
+        let program = arguments.next().unwrap_or_else(|| OsString::from(env!("CARGO_PKG_NAME")));
+        Self::try_parse_from(std::iter::once(program).chain(profile_arguments.iter().map(OsString::from)).chain(arguments)).and_then(Self::validate)
+    }
+
+    /// プロファイルとして保存する引数が再生の引数として解析できるかを確認する
+    pub fn validate_profile_arguments(profile_arguments: &[String]) -> Result<(), String> {
+        let matches =
+            Self::command().try_get_matches_from(std::iter::once(env!("CARGO_PKG_NAME")).chain(profile_arguments.iter().map(String::as_str))).map_err(|e| e.to_string())?;
+        // 設定は再生の前に読み込むため、プロファイルからは変更できない
+        if ["config", "settings"].iter().any(|id| matches.value_source(id) == Some(ValueSource::CommandLine)) {
+            return Err("プロファイルには設定の指定 (--config / --set) を含められません".to_string());
+        }
+        let cli = Self::from_arg_matches(&matches).and_then(Self::validate).map_err(|e| e.to_string())?;
+        if cli.command.is_some() {
+            return Err("プロファイルにはサブコマンドを含められません".to_string());
+        }
+        if cli.replay.profile.is_some() {
+            return Err("プロファイルから別のプロファイルは指定できません".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// 期間内のパケットをpcapファイルへ書き出す
+    Export(ExportArgs),
+
+    /// 期間内のパケットの分布 (ヒストグラム・欠損区間・送信元の上位) を表示する
+    Availability(AvailabilityArgs),
+
+    /// 過去の再生の一覧、またはIDを指定してその詳細を表示する
+    History(HistoryArgs),
+
+    /// 名前を付けて保存した再生の設定 (プロファイル) を管理する
+    Profile(ProfileArgs),
+
+    /// 既定値・設定ファイル・環境変数・--set を重ねた設定を確認する
+    Config(ConfigArgs),
+
+    /// packets テーブルに、同じタイムスタンプのパケットの順序を決める capture_seq カラムを追加する
+    Migrate,
+}
+
+// 再生ごとの変換設定
+// 変換は `--decap` → サニタイズ → `--vlan` → `--encap` の順に、それぞれ指定順で適用される。
+#[derive(Args, Debug, Clone, Default)]
+pub struct ReplayArgs {
+    /// 保存したプロファイルの設定で再生する (コマンドラインの指定が優先される)
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// インターフェースと期間をTUIで選び、再生中は進捗をダッシュボードに表示する
+    #[arg(long)]
+    pub tui: bool,
+
+    /// 送信先のインターフェース (名前 / 番号 / MACアドレス)。省略時は対話的に選ぶ
+    #[arg(long, value_name = "NAME|INDEX|MAC")]
+    pub interface: Option<InterfaceSelector>,
+
+    /// 停止中・ループバック・アドレスのないインターフェースも一覧に表示する
+    #[arg(long)]
+    pub all_interfaces: bool,
+
+    /// 送信に使うバックエンド (pnet / af_packet / tx_ring / sendmmsg / txtime / tap / pcap)。tapでは --interface の名前のTAPデバイスへ、pcapでは --output のファイルへ書き込む
+    #[arg(long, value_name = "KIND", default_value = "pnet")]
+    pub backend: BackendKind,
+
+    /// --backend pcap で送信したフレームを書き出すファイル
+    #[arg(long, value_name = "FILE", required_if_eq("backend", "pcap"))]
+    pub output: Option<PathBuf>,
+
+    /// 実際には送信せず、変換やタイミングを含む再生の処理だけを行う (インターフェースは不要)
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// ドライランで送信するはずだったフレームを書き出すpcap (省略時は破棄する)
+    #[arg(long, value_name = "FILE", requires = "dry_run")]
+    pub dry_run_output: Option<PathBuf>,
+
+    /// 再生速度の倍率 (例: 2 で2倍速、0.5 で半分の速さ)
+    #[arg(long, value_name = "X", value_parser = parse_speed, overrides_with = "top_speed")]
+    pub speed: Option<f64>,
+
+    /// 間隔を無視してできるだけ速く送る (--speedと両方指定した場合は後の指定が優先される)
+    #[arg(long, overrides_with = "speed")]
+    pub top_speed: bool,
+
+    /// 送信を始める前に全てのフレームを準備してメモリに置く (tcpreplayの--preload-pcap相当)
+    #[arg(long)]
+    pub preload: bool,
+
+    /// パケットをチャンクごとに並列に取得する際の同時接続数
+    #[arg(long, value_name = "N", default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..=30))]
+    pub fetch_concurrency: u32,
+
+    /// 送信スレッドへ渡すフレームのキューの大きさ (満杯の間は準備を待たせる)
+    #[arg(long, value_name = "N", default_value_t = 4096, value_parser = clap::value_parser!(u32).range(1..))]
+    pub queue_capacity: u32,
+
+    /// 送信スレッドを固定するCPUの番号
+    #[arg(long, value_name = "CPU", value_parser = parse_sender_cpu)]
+    pub sender_cpu: Option<usize>,
+
+    /// 送信スレッドをSCHED_FIFOのこの優先度 (1〜99) で動かす (CAP_SYS_NICEが必要)
+    #[arg(long, value_name = "PRIORITY", value_parser = clap::value_parser!(i32).range(1..=99))]
+    pub sender_rt_priority: Option<i32>,
+
+    /// 外すトンネル (vxlan / gre / mpls)
+    #[arg(long = "decap", value_name = "KIND")]
+    pub decapsulations: Vec<Decapsulation>,
+
+    /// VLANタグの操作 (pop / push:vid=100[,pcp=3][,tpid=0x88a8] / rewrite:vid=200[,pcp=1])
+    #[arg(long = "vlan", value_name = "OP")]
+    pub vlan_operations: Vec<VlanOperation>,
+
+    /// 追加するトンネル (vxlan:vni=..,src=..,dst=.. / gre:src=..,dst=..[,key=..] / mpls:label=..)
+    #[arg(long = "encap", value_name = "SPEC")]
+    pub encapsulations: Vec<Encapsulation>,
+
+    #[command(flatten)]
+    pub time_range: TimeRangeArgs,
+
+    #[command(flatten)]
+    pub sanitize: SanitizeArgs,
+
+    #[command(flatten)]
+    pub impairment: ImpairmentArgs,
+}
+
+// 対象期間 (省略した場合は対話的に入力する)
+#[derive(Args, Debug, Clone, Default)]
+pub struct TimeRangeArgs {
+    /// 開始日時 (例: 2024-05-01 09:00 / 2024-05-01T09:00:00+09:00 / -2h)
+    #[arg(long, value_name = "TIME", requires = "end")]
+    pub start: Option<String>,
+
+    /// 終了日時 (例: 2024-05-01 10:00 / now / 開始日時からの +30m)
+    #[arg(long, value_name = "TIME", requires = "start")]
+    pub end: Option<String>,
+
+    /// 現在までの直近の期間 (例: 15m / 2h)
+    #[arg(long, value_name = "DURATION", conflicts_with_all = ["start", "end"])]
+    pub last: Option<String>,
+}
+
+impl TimeRangeArgs {
+    /// `DateTimeInput`へ渡す開始/終了日時の式
+    pub fn expressions(&self) -> (Option<String>, Option<String>) {
+        match &self.last {
+            Some(last) => (Some(format!("last {}", last)), None),
+            None => (self.start.clone(), self.end.clone()),
+        }
+    }
+}
+
+// netem相当の回線劣化の設定 (確率は0.0〜1.0)
+#[derive(Args, Debug, Clone, Default)]
+pub struct ImpairmentArgs {
+    /// パケットを破棄する確率
+    #[arg(long, value_name = "P", default_value_t = 0.0, value_parser = parse_probability)]
+    pub drop: f64,
+
+    /// パケットを複製して2回送る確率
+    #[arg(long, value_name = "P", default_value_t = 0.0, value_parser = parse_probability)]
+    pub duplicate: f64,
+
+    /// 後続のパケットと送信順を入れ替える確率
+    #[arg(long, value_name = "P", default_value_t = 0.0, value_parser = parse_probability)]
+    pub reorder: f64,
+
+    /// 順序を入れ替える相手を選ぶ範囲 (後続のパケット数)
+    #[arg(long, value_name = "N", default_value_t = 4, value_parser = parse_reorder_window)]
+    pub reorder_window: usize,
+
+    /// 1ビットを反転させる確率
+    #[arg(long, value_name = "P", default_value_t = 0.0, value_parser = parse_probability)]
+    pub corrupt: f64,
+
+    /// 全パケットに加える遅延 (ミリ秒)
+    #[arg(long, value_name = "MS", default_value_t = 0)]
+    pub latency: u64,
+
+    /// 遅延に加える±のゆらぎ (ミリ秒)
+    #[arg(long, value_name = "MS", default_value_t = 0)]
+    pub jitter: u64,
+
+    /// 乱数のシード (同じシードで同じ劣化を再現する)
+    #[arg(long, value_name = "SEED")]
+    pub seed: Option<u64>,
+}
+
+impl ImpairmentArgs {
+    pub fn impairment_options(&self) -> ImpairmentOptions {
+        ImpairmentOptions {
+            drop: self.drop,
+            duplicate: self.duplicate,
+            reorder: self.reorder,
+            reorder_window: self.reorder_window,
+            corrupt: self.corrupt,
+            latency: Duration::from_millis(self.latency),
+            jitter: Duration::from_millis(self.jitter),
+            seed: self.seed,
+        }
+    }
+}
+
+fn parse_speed(value: &str) -> Result<f64, String> {
+    let speed: f64 = value.parse().map_err(|e| format!("数値ではありません: {}", e))?;
+    if speed.is_finite() && speed > 0.0 {
+        Ok(speed)
+    } else {
+        Err(format!("速度の倍率は0より大きい値で指定してください: {}", speed))
+    }
+}
+
+fn parse_probability(value: &str) -> Result<f64, String> {
+    let probability: f64 = value.parse().map_err(|e| format!("数値ではありません: {}", e))?;
+    if (0.0..=1.0).contains(&probability) {
+        Ok(probability)
+    } else {
+        Err(format!("確率は0.0〜1.0で指定してください: {}", probability))
+    }
+}
+
+fn parse_reorder_window(value: &str) -> Result<usize, String> {
+    let window: usize = value.parse().map_err(|e| format!("数値ではありません: {}", e))?;
+    if window == 0 {
+        return Err("入れ替える範囲は1以上で指定してください".to_string());
+    }
+    Ok(window)
+}
+
+fn parse_sender_cpu(value: &str) -> Result<usize, String> {
+    let cpu: usize = value.parse().map_err(|e| format!("数値ではありません: {}", e))?;
+    #[cfg(target_os = "linux")]
+    if cpu >= libc::CPU_SETSIZE as usize {
+        return Err(format!("CPUの番号は{}未満で指定してください", libc::CPU_SETSIZE));
+    }
+    Ok(cpu)
+}
+
+#[derive(Args, Debug)]
+pub struct AvailabilityArgs {
+    // 期間を省略した場合はデータベース内の全期間を集計する
+    #[command(flatten)]
+    pub time_range: TimeRangeArgs,
+
+    /// 集計するバケットの幅 (例: 1m / 15m / 1h)。省略時は期間の長さから決める
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub bucket: Option<chrono::Duration>,
+
+    /// 表示する送信元アドレスの数
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    pub top: usize,
+}
+
+#[derive(Args, Debug)]
+pub struct HistoryArgs {
+    /// 詳細を表示する再生のID (省略時は一覧を表示する)
+    pub id: Option<i64>,
+
+    /// 一覧に表示する件数
+    #[arg(long, value_name = "N", default_value_t = 20, conflicts_with = "id")]
+    pub limit: usize,
+}
+
+#[derive(Args, Debug)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// 適用される設定とその設定元を表示する (パスワードは伏せる)
+    Show,
+}
+
+#[derive(Args, Debug)]
+pub struct ProfileArgs {
+    #[command(subcommand)]
+    pub command: ProfileCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ProfileCommand {
+    /// 保存済みのプロファイルを一覧表示する
+    List,
+
+    /// プロファイルの内容を表示する
+    Show { name: String },
+
+    /// 再生の引数をプロファイルとして保存する (例: profile save attack --description "..." -- --start "2024-11-03 10:00" --end "2024-11-03 12:00" --speed 5)
+    Save(ProfileSaveArgs),
+
+    /// プロファイルを削除する
+    Delete { name: String },
+}
+
+#[derive(Args, Debug)]
+pub struct ProfileSaveArgs {
+    pub name: String,
+
+    /// 一覧に表示する説明
+    #[arg(long, value_name = "TEXT")]
+    pub description: Option<String>,
+
+    /// 同じ名前のプロファイルがあれば置き換える
+    #[arg(long)]
+    pub replace: bool,
+
+    /// 保存する再生の引数 (`--` の後に続ける)
+    #[arg(last = true, required = true, value_name = "ARGS")]
+    pub arguments: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// 書き出し先のpcapファイル
+    #[arg(long, short, value_name = "FILE")]
+    pub output: PathBuf,
+
+    #[command(flatten)]
+    pub time_range: TimeRangeArgs,
+
+    #[command(flatten)]
+    pub sanitize: SanitizeArgs,
+}
+
+// 共有用のサニタイズ設定 (再生とエクスポートで共通)
+#[derive(Args, Debug, Clone, Default)]
+pub struct SanitizeArgs {
+    /// IPアドレスをプレフィックス保存で匿名化する鍵 (Crypto-PAn, 64桁の16進数)
+    #[arg(long, env = "PACKET_FLOW_ANONYMIZE_KEY", hide_env_values = true, value_name = "HEX")]
+    pub anonymize_key: Option<AnonymizationKey>,
+
+    /// MACアドレスを鍵に基づいてスクランブルする (--anonymize-keyが必要)
+    #[arg(long, requires = "anonymize_key")]
+    pub scramble_mac: bool,
+
+    /// L4ヘッダ以降のペイロードの扱い (keep / truncate / zero)
+    #[arg(long, value_name = "MODE", default_value = "keep")]
+    pub payload: PayloadPolicy,
+
+    /// DNSの名前/TXTとHTTPのクエリ文字列・機微なヘッダの値を伏せる
+    #[arg(long)]
+    pub scrub_application: bool,
+}
+
+impl SanitizeArgs {
+    /// 何も書き換えない設定であれば`None`
+    pub fn sanitizer(&self) -> Result<Option<Sanitizer>, TransformError> {
+        let sanitizer = Sanitizer::new(self.anonymize_key.as_ref(), self.scramble_mac, self.payload, self.scrub_application)?;
+        Ok((!sanitizer.is_noop()).then_some(sanitizer))
+    }
+
+    pub fn transform_pipeline(&self) -> Result<TransformPipeline, TransformError> {
+        let mut pipeline = TransformPipeline::new();
+        if let Some(sanitizer) = self.sanitizer()? {
+            pipeline.push(sanitizer);
+        }
+        Ok(pipeline)
+    }
+}
+
+impl ReplayArgs {
+    pub fn transform_pipeline(&self) -> Result<TransformPipeline, TransformError> {
+        let mut pipeline = TransformPipeline::new();
+        for decapsulation in &self.decapsulations {
+            pipeline.push(*decapsulation);
+        }
+        if let Some(sanitizer) = self.sanitize.sanitizer()? {
+            pipeline.push(sanitizer);
+        }
+        for operation in &self.vlan_operations {
+            pipeline.push(*operation);
+        }
+        for encapsulation in &self.encapsulations {
+            pipeline.push(encapsulation.clone());
+        }
+        Ok(pipeline)
+    }
+
+    pub fn pacing(&self) -> Pacing {
+        match (self.top_speed, self.speed) {
+            (true, _) => Pacing::TopSpeed,
+            (false, Some(speed)) => Pacing::Speed(speed),
+            (false, None) => Pacing::default(),
+        }
+    }
+
+    /// 履歴に残す変換と回線劣化の設定 (匿名化の鍵の値は含めない)
+    pub fn filter_summary(&self) -> String {
+        let mut filters: Vec<String> = Vec::new();
+        filters.extend(self.decapsulations.iter().map(|decapsulation| format!("decap={:?}", decapsulation)));
+        if self.sanitize.anonymize_key.is_some() {
+            filters.push("anonymize".to_string());
+        }
+        if self.sanitize.scramble_mac {
+            filters.push("scramble-mac".to_string());
+        }
+        if self.sanitize.payload != PayloadPolicy::Keep {
+            filters.push(format!("payload={:?}", self.sanitize.payload));
+        }
+        if self.sanitize.scrub_application {
+            filters.push("scrub-application".to_string());
+        }
+        filters.extend(self.vlan_operations.iter().map(|operation| format!("vlan={:?}", operation)));
+        filters.extend(self.encapsulations.iter().map(|encapsulation| format!("encap={:?}", encapsulation)));
+        let impairment = self.impairment.impairment_options();
+        if !impairment.is_noop() {
+            filters.push(format!("impairment={:?}", impairment));
+        }
+
+        if filters.is_empty() {
+            "なし".to_string()
+        } else {
+            filters.join(", ")
+        }
+    }
+
+    pub fn sender_thread_options(&self) -> SenderThreadOptions {
+        SenderThreadOptions {
+            queue_capacity: self.queue_capacity as usize,
+            cpu: self.sender_cpu,
+            realtime_priority: self.sender_rt_priority,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_impairment_options() {
+        let cli = Cli::try_parse_from([
+            "packet-flow-cli",
+            "--drop",
+            "0.1",
+            "--reorder",
+            "0.5",
+            "--reorder-window",
+            "2",
+            "--seed",
+            "42",
+        ])
+        .unwrap();
+        let options = cli.replay.impairment.impairment_options();
+        assert_eq!((options.drop, options.reorder, options.reorder_window, options.seed), (0.1, 0.5, 2, Some(42)));
+    }
+
+    #[test]
+    fn rejects_invalid_impairment_options() {
+        for args in [["--reorder-window", "0"], ["--drop", "1.5"], ["--corrupt", "-0.1"]] {
+            assert!(Cli::try_parse_from(std::iter::once("packet-flow-cli").chain(args)).is_err(), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn pcap_backend_requires_output() {
+        let cli = Cli::try_parse_from(["packet-flow-cli", "--backend", "pcap", "--output", "replay.pcap"]).unwrap();
+        assert_eq!((cli.replay.backend, cli.replay.output), (BackendKind::Pcap, Some(PathBuf::from("replay.pcap"))));
+
+        assert!(Cli::try_parse_from(["packet-flow-cli", "--backend", "pcap"]).is_err());
+        assert!(Cli::try_parse_from(["packet-flow-cli", "--backend", "netmap"]).is_err());
+    }
+
+    #[test]
+    fn parses_sender_thread_options() {
+        let cli = Cli::try_parse_from([
+            "packet-flow-cli",
+            "--queue-capacity",
+            "128",
+            "--sender-cpu",
+            "3",
+            "--sender-rt-priority",
+            "50",
+        ])
+        .unwrap();
+        let options = cli.replay.sender_thread_options();
+        assert_eq!((options.queue_capacity, options.cpu, options.realtime_priority), (128, Some(3), Some(50)));
+
+        for args in [
+            ["--queue-capacity", "0"],
+            ["--sender-cpu", "1024"],
+            ["--sender-rt-priority", "100"],
+        ] {
+            assert!(Cli::try_parse_from(std::iter::once("packet-flow-cli").chain(args)).is_err(), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn txtime_backend_conflicts_with_top_speed() {
+        let cli = Cli::try_parse_from(["packet-flow-cli", "--backend", "txtime", "--top-speed"]).unwrap();
+        assert_eq!(cli.validate().unwrap_err().kind(), ErrorKind::ArgumentConflict);
+
+        assert!(Cli::try_parse_from(["packet-flow-cli", "--backend", "txtime", "--speed", "2"]).unwrap().validate().is_ok());
+        assert!(Cli::try_parse_from(["packet-flow-cli", "--backend", "pnet", "--top-speed"]).unwrap().validate().is_ok());
+    }
+
+    #[test]
+    fn validates_profile_arguments() {
+        let arguments = |arguments: &[&str]| arguments.iter().map(|argument| argument.to_string()).collect::<Vec<_>>();
+
+        assert!(Cli::validate_profile_arguments(&arguments(&["--speed", "2", "--vlan", "push:vid=100"])).is_ok());
+        assert!(Cli::validate_profile_arguments(&arguments(&["--speed", "fast"])).is_err());
+        assert!(Cli::validate_profile_arguments(&arguments(&["history"])).is_err());
+        assert!(Cli::validate_profile_arguments(&arguments(&["--profile", "other"])).is_err());
+        assert!(Cli::validate_profile_arguments(&arguments(&["--backend", "txtime", "--top-speed"])).is_err());
+        assert!(Cli::validate_profile_arguments(&arguments(&["--set", "database.host=db"])).is_err());
+        assert!(Cli::validate_profile_arguments(&arguments(&["--config", "other.toml"])).is_err());
+    }
+}
@@ -0,0 +1,3 @@
+mod args;
+
+pub use args::{AvailabilityArgs, Cli, Command, ConfigArgs, ConfigCommand, ExportArgs, HistoryArgs, ProfileArgs, ProfileCommand, ReplayArgs};
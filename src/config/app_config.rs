@@ -1,28 +1,53 @@
+use crate::config::config_layers::{ConfigLayers, SettingValue};
 use crate::config::error::ConfigError;
-use dotenv::dotenv;
+use crate::config::log_style::{IdpsLogMode, PathStyle};
+use crate::config::password::PasswordSource;
+use crate::config::timezone::Timezone;
+use crate::packet::reader::LinkOutput;
+use crate::utils::secret::SecretString;
+use pnet::util::MacAddr;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::str::FromStr;
 
 #[derive(Debug, Clone)]
 pub struct DatabaseConfig {
     pub host: String,
     pub port: u16,
     pub user: String,
-    pub password: String,
+    /// 接続時に`password()`で読み出す。見つからなければパスワードを使わない認証方式 (trust / peer 等) で接続する
+    pub password_source: PasswordSource,
     pub database: String,
+    /// `capture_seq`のないpacketsテーブルで、同じタイムスタンプのパケットを行の格納位置の順に並べる旧方式を許可する
+    pub legacy_row_order: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct NetworkConfig {
     pub docker_mode: bool,
     pub docker_interface_name: String,
+    /// 非EthernetのパケットにL2ヘッダを合成する際の送信元/宛先MAC
+    pub replay_src_mac: Option<MacAddr>,
+    pub replay_dst_mac: Option<MacAddr>,
+    /// 非Ethernetのパケットの送信方式 (ethernet / raw_ip)
+    pub link_output: LinkOutput,
+    /// 送信するL3パケットのサイズの上限
+    pub mtu: usize,
 }
 
 #[derive(Debug, Clone)]
 pub struct LoggerConfig {
     pub normal_logger_file: String,
     pub idps_logger_file: String,
-    pub idps_log_mode: String,
-    pub normal_path_style: String,
-    pub idps_path_style: String,
+    pub idps_log_mode: IdpsLogMode,
+    pub normal_path_style: PathStyle,
+    pub idps_path_style: PathStyle,
+}
+
+#[derive(Debug, Clone)]
+pub struct TimeConfig {
+    /// 日時の入力と表示に使うタイムゾーン (UTC / local / +09:00 / Asia/Tokyo)
+    pub timezone: Timezone,
 }
 
 #[derive(Debug, Clone)]
@@ -30,34 +55,234 @@ pub struct AppConfig {
     pub database: DatabaseConfig,
     pub network: NetworkConfig,
     pub logger_config: LoggerConfig,
+    pub time: TimeConfig,
 }
 
-impl AppConfig {
-    pub fn new() -> Result<Self, ConfigError> {
-        dotenv().map_err(|e| ConfigError::EnvFileReadError(e.to_string()))?;
+impl DatabaseConfig {
+    /// 設定された取得元からパスワードを読み出す (ファイルの読み込みやコマンドの実行はここで行う)
+    pub fn password(&self) -> Result<Option<SecretString>, ConfigError> {
+        self.password_source.resolve(self)
+    }
+}
 
-        let get_env_var =
-            |var_name: &str| -> Result<String, ConfigError> { dotenv::var(var_name).map_err(|e| ConfigError::EnvVarError(format!("{}: {}", var_name, e.to_string()))) };
+impl AppConfig {
+    /// 重ねた設定値を型に変換する。誤りは全項目分をまとめて返す
+    pub fn from_layers(layers: &ConfigLayers) -> Result<Self, ConfigError> {
+        let mut reader = SettingReader { layers, errors: Vec::new() };
 
-        Ok(Self {
+        let config = Self {
             database: DatabaseConfig {
-                host: get_env_var("TIMESCALE_DB_HOST")?,
-                port: get_env_var("TIMESCALE_DB_PORT")?.parse::<u16>().map_err(|e| ConfigError::EnvVarParseError(format!("TIMESCALE_DB_PORT: {}", e.to_string())))?,
-                user: get_env_var("TIMESCALE_DB_USER")?,
-                password: get_env_var("TIMESCALE_DB_PASSWORD")?,
-                database: get_env_var("TIMESCALE_DB_DATABASE")?,
+                host: reader.text("database.host"),
+                port: reader.value("database.port"),
+                user: reader.text("database.user"),
+                password_source: reader.password_source(),
+                database: reader.text("database.name"),
+                legacy_row_order: reader.flag("database.legacy_row_order"),
             },
             network: NetworkConfig {
-                docker_mode: dotenv::var("DOCKER_MODE").map(|v| v.to_lowercase() == "true").unwrap_or(false),
-                docker_interface_name: get_env_var("DOCKER_INTERFACE_NAME")?,
+                docker_mode: reader.flag("network.docker_mode"),
+                docker_interface_name: reader.text("network.docker_interface"),
+                replay_src_mac: reader.optional("network.replay_src_mac"),
+                replay_dst_mac: reader.optional("network.replay_dst_mac"),
+                link_output: reader.value("network.link_output"),
+                mtu: reader.positive("network.mtu"),
             },
             logger_config: LoggerConfig {
-                normal_logger_file: get_env_var("NORMAL_LOGGER_FILE")?,
-                idps_logger_file: get_env_var("IDPS_LOGGER_FILE")?,
-                idps_log_mode: get_env_var("IDPS_LOG_MODE")?,
-                normal_path_style: get_env_var("NORMAL_PATH_STYLE")?,
-                idps_path_style: get_env_var("IDPS_PATH_STYLE")?,
+                normal_logger_file: reader.text("logger.normal_file"),
+                idps_logger_file: reader.text("logger.idps_file"),
+                idps_log_mode: reader.value("logger.idps_mode"),
+                normal_path_style: reader.value("logger.normal_path_style"),
+                idps_path_style: reader.value("logger.idps_path_style"),
+            },
+            time: TimeConfig {
+                timezone: reader.value("time.timezone"),
+            },
+        };
+
+        if reader.errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError::InvalidSettings(reader.errors.join("\n")))
+        }
+    }
+}
+
+/// 設定値を読み取りながら誤りを集める
+///
+/// 誤りのある項目は既定の値で埋めて読み進め、最後にまとめて報告する。
+struct SettingReader<'a> {
+    layers: &'a ConfigLayers,
+    errors: Vec<String>,
+}
+
+impl SettingReader<'_> {
+    fn value<T>(&mut self, key: &str) -> T
+    where
+        T: FromStr + Default,
+        T::Err: Display,
+    {
+        match self.layers.get(key) {
+            Some(setting) if setting.value.trim().is_empty() => self.reject(key, setting, "空にはできません"),
+            Some(setting) => match setting.value.trim().parse() {
+                Ok(value) => value,
+                Err(e) => self.reject(key, setting, e),
+            },
+            None => {
+                self.errors.push(format!("{}: 設定されていません", key));
+                T::default()
+            },
+        }
+    }
+
+    fn text(&mut self, key: &str) -> String {
+        self.value(key)
+    }
+
+    /// 0より大きい整数
+    fn positive(&mut self, key: &str) -> usize {
+        match self.layers.get(key) {
+            Some(setting) if setting.value.trim().parse() == Ok(0usize) => self.reject(key, setting, "0より大きい値を指定してください"),
+            _ => self.value(key),
+        }
+    }
+
+    /// パスワードの取得元を値・ファイル・コマンドのいずれか1つに決める
+    ///
+    /// どれも指定されていなければパスワードファイル (PGPASSFILE / ~/.pgpass) から探す。
+    /// ファイルの読み込みやコマンドの実行は接続時まで行わない。
+    fn password_source(&mut self) -> PasswordSource {
+        const SOURCES: [&str; 3] = ["database.password", "database.password_file", "database.password_command"];
+
+        let layers = self.layers;
+        let configured: Vec<(&str, &SettingValue)> =
+            SOURCES.iter().filter_map(|key| layers.get(key).filter(|setting| !setting.value.is_empty()).map(|setting| (*key, setting))).collect();
+
+        match configured.as_slice() {
+            [] => PasswordSource::Passfile(self.optional("database.passfile")),
+            [("database.password", setting)] => PasswordSource::Value(SecretString::from(setting.value.to_string())),
+            [("database.password_file", setting)] => PasswordSource::File {
+                path: PathBuf::from(setting.value.trim()),
+                source: setting.source.clone(),
             },
-        })
+            [(_, setting)] => PasswordSource::Command {
+                command: SecretString::from(setting.value.to_string()),
+                source: setting.source.clone(),
+            },
+            _ => {
+                let sources: Vec<String> = configured.iter().map(|(key, setting)| format!("{} ({})", key, setting.source)).collect();
+                self.errors.push(format!("パスワードの取得元は1つだけ指定してください: {}", sources.join(", ")));
+                PasswordSource::default()
+            },
+        }
+    }
+
+    /// 未設定または空なら`None`
+    fn optional<T>(&mut self, key: &str) -> Option<T>
+    where
+        T: FromStr + Default,
+        T::Err: Display,
+    {
+        self.layers.get(key).filter(|setting| !setting.value.trim().is_empty()).map(|_| self.value(key))
+    }
+
+    fn flag(&mut self, key: &str) -> bool {
+        match self.layers.get(key) {
+            Some(setting) => match setting.value.trim().to_lowercase().as_str() {
+                "true" => true,
+                "false" => false,
+                _ => self.reject(key, setting, "true か false を指定してください"),
+            },
+            None => false,
+        }
+    }
+
+    fn reject<T: Default>(&mut self, key: &str, setting: &SettingValue, reason: impl Display) -> T {
+        self.errors.push(format!("{} = {:?} ({}): {}", key, setting.value.as_str(), setting.source, reason));
+        T::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::VarError;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 設定ファイルを空にして、環境変数だけで設定を組み立てる
+    fn config_from_env(vars: &[(&str, &str)]) -> Result<AppConfig, ConfigError> {
+        static FILES: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!("packet-flow-{}-app-config-{}.toml", std::process::id(), FILES.fetch_add(1, Ordering::Relaxed)));
+        std::fs::write(&path, "").unwrap();
+        let env = |name: &str| vars.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string()).ok_or(VarError::NotPresent);
+        let layers = ConfigLayers::from_sources(Some(&path), env, &[]);
+        let _ = std::fs::remove_file(&path);
+        AppConfig::from_layers(&layers?)
+    }
+
+    fn errors_of(result: Result<AppConfig, ConfigError>) -> Vec<String> {
+        match result {
+            Err(ConfigError::InvalidSettings(message)) => message.lines().map(str::to_string).collect(),
+            other => panic!("設定の誤りとして報告されること: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn reads_defaults_and_typed_values() {
+        let config = config_from_env(&[
+            ("TIMESCALE_DB_PASSWORD", ""),
+            ("TIMESCALE_DB_PORT", " 6543 "),
+            ("DOCKER_MODE", "TRUE"),
+            ("REPLAY_SRC_MAC", "02:00:00:00:00:01"),
+            ("REPLAY_LINK_OUTPUT", "raw_ip"),
+        ])
+        .unwrap();
+
+        assert_eq!(config.database.port, 6543);
+        assert!(matches!(config.database.password_source, PasswordSource::Passfile(None)));
+        assert!(!config.database.legacy_row_order);
+        assert!(config.network.docker_mode);
+        assert_eq!(config.network.replay_src_mac, Some(MacAddr::new(2, 0, 0, 0, 0, 1)));
+        assert_eq!(config.network.replay_dst_mac, None);
+        assert_eq!(config.network.link_output, LinkOutput::RawIp);
+        assert_eq!(config.network.mtu, 1500);
+    }
+
+    #[test]
+    fn reports_every_invalid_setting_with_its_source() {
+        let errors = errors_of(config_from_env(&[
+            ("TIMESCALE_DB_PASSWORD", "secret"),
+            ("TIMESCALE_DB_PORT", "70000"),
+            ("TIMESCALE_DB_LEGACY_ROW_ORDER", "yes"),
+            ("REPLAY_DST_MAC", "02:00:00"),
+            ("REPLAY_LINK_OUTPUT", "token_ring"),
+            ("REPLAY_MTU", "0"),
+            ("TIMEZONE", ""),
+        ]));
+
+        assert_eq!(errors.len(), 6, "{:?}", errors);
+        assert!(errors[0].starts_with("database.port = \"70000\" (環境変数 TIMESCALE_DB_PORT): "));
+        assert!(errors[1].starts_with("database.legacy_row_order = \"yes\""));
+        assert!(errors.iter().any(|error| error.starts_with("network.replay_dst_mac = \"02:00:00\"")));
+        assert!(errors.iter().any(|error| error.starts_with("network.link_output = \"token_ring\"")));
+        assert!(errors.iter().any(|error| error.ends_with("0より大きい値を指定してください")));
+        assert!(errors.iter().any(|error| error.ends_with("time.timezone = \"\" (環境変数 TIMEZONE): 空にはできません")));
+    }
+
+    #[test]
+    fn password_sources_are_exclusive() {
+        let errors = errors_of(config_from_env(&[
+            ("TIMESCALE_DB_PASSWORD", "secret"),
+            ("TIMESCALE_DB_PASSWORD_FILE", "/run/secrets/db"),
+        ]));
+        assert_eq!(
+            errors,
+            ["パスワードの取得元は1つだけ指定してください: database.password (環境変数 TIMESCALE_DB_PASSWORD), database.password_file (環境変数 TIMESCALE_DB_PASSWORD_FILE)"]
+        );
+    }
+
+    #[test]
+    fn password_command_is_not_run_while_loading() {
+        let config = config_from_env(&[("TIMESCALE_DB_PASSWORD_COMMAND", "exit 1")]).unwrap();
+        assert!(matches!(config.database.password_source, PasswordSource::Command { .. }));
     }
 }
@@ -0,0 +1,384 @@
+use crate::config::error::ConfigError;
+use crate::utils::secret::SecretString;
+use std::collections::HashMap;
+use std::env::VarError;
+use std::fmt;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use zeroize::Zeroizing;
+
+/// `--config` を省略した場合に、カレントディレクトリにあれば読み込む設定ファイル
+pub const DEFAULT_CONFIG_FILE: &str = "packet-flow.toml";
+
+/// 設定項目の定義
+struct Setting {
+    /// 設定ファイルと `--set` で使う名前 (セクション.項目)
+    key: &'static str,
+    env: &'static str,
+    default: Option<&'static str>,
+    kind: SettingKind,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SettingKind {
+    Text,
+    Integer,
+    Flag,
+    /// 表示時に伏せ、コマンドラインでは受け付けない
+    Secret,
+}
+
+const SETTINGS: [Setting; 21] = [
+    Setting::new("database.host", "TIMESCALE_DB_HOST", Some("localhost"), SettingKind::Text),
+    Setting::new("database.port", "TIMESCALE_DB_PORT", Some("5432"), SettingKind::Integer),
+    Setting::new("database.user", "TIMESCALE_DB_USER", Some("postgres"), SettingKind::Text),
+    Setting::new("database.name", "TIMESCALE_DB_DATABASE", Some("postgres"), SettingKind::Text),
+    Setting::new("database.legacy_row_order", "TIMESCALE_DB_LEGACY_ROW_ORDER", Some("false"), SettingKind::Flag),
+    Setting::new("database.password", "TIMESCALE_DB_PASSWORD", None, SettingKind::Secret),
+    Setting::new("database.password_file", "TIMESCALE_DB_PASSWORD_FILE", None, SettingKind::Text),
+    Setting::new("database.password_command", "TIMESCALE_DB_PASSWORD_COMMAND", None, SettingKind::Secret),
+    Setting::new("database.passfile", "PGPASSFILE", None, SettingKind::Text),
+    Setting::new("network.docker_mode", "DOCKER_MODE", Some("false"), SettingKind::Flag),
+    Setting::new("network.docker_interface", "DOCKER_INTERFACE_NAME", Some("eth0"), SettingKind::Text),
+    Setting::new("network.replay_src_mac", "REPLAY_SRC_MAC", None, SettingKind::Text),
+    Setting::new("network.replay_dst_mac", "REPLAY_DST_MAC", None, SettingKind::Text),
+    Setting::new("network.link_output", "REPLAY_LINK_OUTPUT", Some("ethernet"), SettingKind::Text),
+    Setting::new("network.mtu", "REPLAY_MTU", Some("1500"), SettingKind::Integer),
+    Setting::new("logger.normal_file", "NORMAL_LOGGER_FILE", Some("./logs/system.log"), SettingKind::Text),
+    Setting::new("logger.idps_file", "IDPS_LOGGER_FILE", Some("./logs/idps.log"), SettingKind::Text),
+    Setting::new("logger.idps_mode", "IDPS_LOG_MODE", Some("all"), SettingKind::Text),
+    Setting::new("logger.normal_path_style", "NORMAL_PATH_STYLE", Some("file_path"), SettingKind::Text),
+    Setting::new("logger.idps_path_style", "IDPS_PATH_STYLE", Some("file_path"), SettingKind::Text),
+    Setting::new("time.timezone", "TIMEZONE", Some("UTC"), SettingKind::Text),
+];
+
+impl Setting {
+    const fn new(key: &'static str, env: &'static str, default: Option<&'static str>, kind: SettingKind) -> Self {
+        Self { key, env, default, kind }
+    }
+
+    fn find(key: &str) -> Option<&'static Setting> {
+        SETTINGS.iter().find(|setting| setting.key == key)
+    }
+}
+
+/// 設定値の出どころ
+#[derive(Debug, Clone)]
+pub enum ConfigSource {
+    Default,
+    File(PathBuf),
+    Environment(&'static str),
+    CommandLine,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "既定値"),
+            Self::File(path) => write!(f, "設定ファイル {}", path.display()),
+            Self::Environment(name) => write!(f, "環境変数 {}", name),
+            Self::CommandLine => write!(f, "コマンドライン --set"),
+        }
+    }
+}
+
+/// 設定値。秘密の値も含むため、破棄時にメモリを0で上書きする
+pub struct SettingValue {
+    pub value: Zeroizing<String>,
+    pub source: ConfigSource,
+}
+
+/// `--set` で指定された上書き (KEY=VALUE)
+#[derive(Debug, Clone)]
+pub struct SettingOverride {
+    key: String,
+    value: String,
+}
+
+impl FromStr for SettingOverride {
+    type Err = ConfigError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => Ok(Self {
+                key: key.trim().to_string(),
+                value: value.to_string(),
+            }),
+            _ => Err(ConfigError::InvalidOverride(value.to_string())),
+        }
+    }
+}
+
+/// 既定値 → 設定ファイル → 環境変数 → コマンドラインの順に重ねた設定値
+///
+/// 後の層の値が優先される。`.env` があれば環境変数として読み込むが、既に設定されている環境変数は上書きしない。
+pub struct ConfigLayers {
+    values: HashMap<&'static str, SettingValue>,
+}
+
+impl ConfigLayers {
+    pub fn load(file: Option<&Path>, overrides: &[SettingOverride]) -> Result<Self, ConfigError> {
+        dotenv::dotenv().ok();
+        Self::from_sources(file, |name| std::env::var(name), overrides)
+    }
+
+    /// 環境変数を`env`で引いて重ねる
+    pub(super) fn from_sources(file: Option<&Path>, env: impl Fn(&str) -> Result<String, VarError>, overrides: &[SettingOverride]) -> Result<Self, ConfigError> {
+        let mut values = HashMap::new();
+        let mut errors = Vec::new();
+
+        for setting in &SETTINGS {
+            if let Some(default) = setting.default {
+                values.insert(
+                    setting.key,
+                    SettingValue {
+                        value: default.to_string().into(),
+                        source: ConfigSource::Default,
+                    },
+                );
+            }
+        }
+
+        // 明示された設定ファイルは必須、既定の設定ファイルはあれば読み込む
+        let file = match file {
+            Some(path) => Some(path.to_path_buf()),
+            None => Some(PathBuf::from(DEFAULT_CONFIG_FILE)).filter(|path| path.is_file()),
+        };
+        if let Some(path) = file {
+            for (key, value) in Self::read_file(&path)? {
+                match Setting::find(&key) {
+                    Some(setting) => {
+                        values.insert(
+                            setting.key,
+                            SettingValue {
+                                value: value.into(),
+                                source: ConfigSource::File(path.clone()),
+                            },
+                        );
+                    },
+                    None => errors.push(format!("{}: 不明な設定項目です ({})", key, path.display())),
+                }
+            }
+        }
+
+        for setting in &SETTINGS {
+            match env(setting.env) {
+                Ok(value) => {
+                    values.insert(
+                        setting.key,
+                        SettingValue {
+                            value: value.into(),
+                            source: ConfigSource::Environment(setting.env),
+                        },
+                    );
+                },
+                Err(VarError::NotUnicode(_)) => errors.push(format!("{}: 環境変数 {} がUTF-8ではありません", setting.key, setting.env)),
+                Err(VarError::NotPresent) => {},
+            }
+        }
+
+        for SettingOverride { key, value } in overrides {
+            match Setting::find(key) {
+                Some(setting) if setting.kind == SettingKind::Secret => errors.push(format!(
+                    "{}: 秘密の値はコマンドラインでは指定できません (環境変数 {} か設定ファイルを使ってください)",
+                    key, setting.env
+                )),
+                Some(setting) => {
+                    values.insert(
+                        setting.key,
+                        SettingValue {
+                            value: value.clone().into(),
+                            source: ConfigSource::CommandLine,
+                        },
+                    );
+                },
+                None => errors.push(format!("{}: 不明な設定項目です (--set)", key)),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(Self { values })
+        } else {
+            Err(ConfigError::InvalidSettings(errors.join("\n")))
+        }
+    }
+
+    /// TOMLの設定ファイルを `セクション.項目` と値の組に展開する
+    fn read_file(path: &Path) -> Result<Vec<(String, String)>, ConfigError> {
+        let content = Zeroizing::new(std::fs::read_to_string(path).map_err(|e| ConfigError::ConfigFileError(format!("{}: {}", path.display(), e)))?);
+        let table: toml::Table = content.parse().map_err(|e| ConfigError::ConfigFileError(format!("{}: {}", path.display(), e)))?;
+
+        let mut entries = Vec::new();
+        for (section, items) in table {
+            let toml::Value::Table(items) = items else {
+                return Err(ConfigError::ConfigFileError(format!(
+                    "{}: {} はセクション ([{}]) の中に書いてください",
+                    path.display(),
+                    section,
+                    section
+                )));
+            };
+            for (name, value) in items {
+                let key = format!("{}.{}", section, name);
+                let value = match value {
+                    toml::Value::String(value) => value,
+                    toml::Value::Integer(value) => value.to_string(),
+                    toml::Value::Boolean(value) => value.to_string(),
+                    other => {
+                        return Err(ConfigError::ConfigFileError(format!(
+                            "{}: {} に {} は指定できません",
+                            path.display(),
+                            key,
+                            other.type_str()
+                        )))
+                    },
+                };
+                entries.push((key, value));
+            }
+        }
+        Ok(entries)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&SettingValue> {
+        self.values.get(key)
+    }
+
+    /// 適用される設定をTOMLの形式で出力する。値の出どころをコメントに付け、秘密の値は伏せる
+    pub fn render(&self) -> String {
+        let lines: Vec<(&str, String, String)> = SETTINGS
+            .iter()
+            .map(|setting| {
+                let (section, name) = setting.key.split_once('.').unwrap_or(("", setting.key));
+                let line = match self.values.get(setting.key) {
+                    Some(SettingValue { value, .. }) => format!("{} = {}", name, Self::render_value(setting.kind, value)),
+                    None => format!("# {} =", name),
+                };
+                let note = match self.values.get(setting.key) {
+                    Some(SettingValue { source, .. }) => source.to_string(),
+                    None => format!("未設定 (環境変数 {})", setting.env),
+                };
+                (section, line, note)
+            })
+            .collect();
+        let width = lines.iter().map(|(_, line, _)| line.chars().count()).max().unwrap_or(0);
+
+        let mut out = String::new();
+        let mut current = None;
+        for (section, line, note) in lines {
+            if current != Some(section) {
+                if current.is_some() {
+                    out.push('\n');
+                }
+                let _ = writeln!(out, "[{}]", section);
+                current = Some(section);
+            }
+            let padding = width - line.chars().count();
+            let _ = writeln!(out, "{}{}  # {}", line, " ".repeat(padding), note);
+        }
+        out
+    }
+
+    fn render_value(kind: SettingKind, value: &str) -> String {
+        match kind {
+            SettingKind::Secret => toml::Value::String(SecretString::MASK.to_string()).to_string(),
+            SettingKind::Integer if value.parse::<i64>().is_ok() => value.to_string(),
+            SettingKind::Flag if value.trim().to_lowercase().parse::<bool>().is_ok() => value.trim().to_lowercase(),
+            _ => toml::Value::String(value.to_string()).to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 一時ディレクトリに設定ファイルを書き出す
+    fn write_config(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("packet-flow-{}-{}.toml", std::process::id(), name));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn env_of(vars: &[(&str, &str)]) -> impl Fn(&str) -> Result<String, VarError> {
+        let vars: HashMap<String, String> = vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        move |name| vars.get(name).cloned().ok_or(VarError::NotPresent)
+    }
+
+    fn overrides(values: &[&str]) -> Vec<SettingOverride> {
+        values.iter().map(|value| value.parse().unwrap()).collect()
+    }
+
+    fn value_of<'a>(layers: &'a ConfigLayers, key: &str) -> (&'a str, String) {
+        let setting = layers.get(key).unwrap();
+        (setting.value.as_str(), setting.source.to_string())
+    }
+
+    #[test]
+    fn later_layers_take_precedence() {
+        let path = write_config("precedence", "[database]\nhost = \"file-host\"\nport = 6543\nuser = \"file-user\"\n");
+        let env = env_of(&[("TIMESCALE_DB_PORT", "7654"), ("TIMESCALE_DB_USER", "env-user")]);
+        let layers = ConfigLayers::from_sources(Some(&path), env, &overrides(&["database.user=cli-user"])).unwrap();
+
+        assert_eq!(value_of(&layers, "database.name"), ("postgres", "既定値".to_string()));
+        assert_eq!(value_of(&layers, "database.host"), ("file-host", format!("設定ファイル {}", path.display())));
+        assert_eq!(value_of(&layers, "database.port"), ("7654", "環境変数 TIMESCALE_DB_PORT".to_string()));
+        assert_eq!(value_of(&layers, "database.user"), ("cli-user", "コマンドライン --set".to_string()));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reports_unknown_keys_and_secrets_on_command_line_together() {
+        let path = write_config("unknown", "[database]\nhots = \"db\"\n");
+        let result = ConfigLayers::from_sources(
+            Some(&path),
+            env_of(&[]),
+            &overrides(&[
+                "database.password=secret",
+                "database.password_command=pass show db",
+                "network.nope=1",
+            ]),
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        let Err(ConfigError::InvalidSettings(message)) = result else {
+            panic!("設定の誤りとして報告されること");
+        };
+        assert_eq!(message.lines().count(), 4);
+        assert!(message.contains("database.hots: 不明な設定項目です"));
+        assert!(message.contains("database.password: 秘密の値はコマンドラインでは指定できません"));
+        assert!(message.contains("database.password_command: 秘密の値はコマンドラインでは指定できません"));
+        assert!(message.contains("network.nope: 不明な設定項目です (--set)"));
+    }
+
+    #[test]
+    fn rejects_malformed_files_and_overrides() {
+        let path = write_config("malformed", "host = \"db\"\n");
+        assert!(matches!(ConfigLayers::from_sources(Some(&path), env_of(&[]), &[]), Err(ConfigError::ConfigFileError(_))));
+        std::fs::remove_file(path).unwrap();
+
+        assert!(matches!("database.host".parse::<SettingOverride>(), Err(ConfigError::InvalidOverride(_))));
+        assert!(matches!("=value".parse::<SettingOverride>(), Err(ConfigError::InvalidOverride(_))));
+    }
+
+    #[test]
+    fn render_masks_secrets_and_notes_sources() {
+        let env = env_of(&[
+            ("TIMESCALE_DB_PASSWORD", "hunter2"),
+            ("TIMESCALE_DB_PASSWORD_COMMAND", "echo hunter3"),
+            ("REPLAY_MTU", "9000"),
+        ]);
+        let path = write_config("render", "");
+        let layers = ConfigLayers::from_sources(Some(&path), env, &[]).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let rendered = layers.render();
+
+        assert!(!rendered.contains("hunter2"));
+        assert!(!rendered.contains("hunter3"));
+        assert!(rendered.contains(&format!("password = \"{}\"", SecretString::MASK)));
+        assert!(rendered.contains(&format!("password_command = \"{}\"", SecretString::MASK)));
+        assert!(rendered.contains("mtu = 9000"));
+        assert!(rendered.contains("# 環境変数 REPLAY_MTU"));
+        assert!(rendered.contains("# replay_src_mac ="));
+    }
+}
@@ -1,57 +1,109 @@
 use crate::config::error::ConfigError;
+use crate::config::time_expression::TimeExpressionParser;
+use crate::config::timezone::Timezone;
+use crate::packet::availability::AvailabilityReport;
 use crate::packet::repository::PacketRepository;
-use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use chrono::{DateTime, Utc};
 use std::io::{self, Write};
 
+const INPUT_HINT: &str = "例: 2024-05-01 09:00, 2024-05-01T09:00:00+09:00, -2h, last 15m";
+const END_INPUT_HINT: &str = "例: 2024-05-01 10:00, now, +30m";
+/// 入力前の概要に表示する送信元アドレスの数
+const OVERVIEW_TOP_TALKERS: usize = 5;
+
 pub struct DateTimeInput {
     start_datetime: DateTime<Utc>,
     end_datetime: DateTime<Utc>,
 }
 
 impl DateTimeInput {
-    pub async fn new() -> Result<Self, ConfigError> {
+    /// 開始日時が指定されていればそれを使い、指定されていなければ対話的に入力させる
+    ///
+    /// 開始日時に`last 15m`のような期間全体を指定した場合、終了日時は指定できない。
+    pub async fn new(timezone: Timezone, start: Option<&str>, end: Option<&str>) -> Result<Self, ConfigError> {
+        match start {
+            Some(start) => Self::from_expressions(timezone, start, end),
+            None => Self::prompt(timezone).await,
+        }
+    }
+
+    fn from_expressions(timezone: Timezone, start: &str, end: Option<&str>) -> Result<Self, ConfigError> {
+        let parser = TimeExpressionParser::new(timezone);
+
+        if let Some((start_datetime, end_datetime)) = parser.parse_window(start)? {
+            if end.is_some() {
+                return Err(ConfigError::InvalidTimeExpression("期間全体を指定した場合は終了日時を指定できません".to_string()));
+            }
+            return Ok(Self { start_datetime, end_datetime });
+        }
+
+        let start_datetime = parser.parse_start(start)?;
+        let end = end.ok_or_else(|| ConfigError::InvalidTimeExpression("終了日時が指定されていません".to_string()))?;
+        let end_datetime = parser.parse_end(end, start_datetime)?;
+        validate_order(timezone, start_datetime, end_datetime)?;
+
+        Ok(Self { start_datetime, end_datetime })
+    }
+
+    async fn prompt(timezone: Timezone) -> Result<Self, ConfigError> {
         // データベース内のパケットの時間範囲を取得
-        match PacketRepository::get_packet_time_range().await {
+        match PacketRepository::from_database().get_packet_time_range().await {
             Ok((min_time, max_time)) => {
                 println!("\nデータベース内のパケットの時間範囲:");
-                println!("最古のパケット: {}", min_time);
-                println!("最新のパケット: {}\n", max_time);
+                println!("最古のパケット: {}", timezone.localize(min_time));
+                println!("最新のパケット: {}\n", timezone.localize(max_time));
+
+                // 欠損やバーストを見てから期間を選べるよう、全体の分布を表示する
+                if min_time < max_time {
+                    match AvailabilityReport::collect(min_time, max_time, None, OVERVIEW_TOP_TALKERS).await {
+                        Ok(report) => println!("{}", report.render(timezone)),
+                        Err(e) => println!("警告: パケットの分布の取得に失敗しました: {}", e),
+                    }
+                }
             },
             Err(e) => {
                 println!("警告: パケットの時間範囲の取得に失敗しました: {}", e);
             },
         }
 
-        let start_datetime = loop {
-            match get_naive_datetime_input("開始日時を入力してください") {
-                Ok(naive_dt) => {
-                    break DateTime::from_naive_utc_and_offset(naive_dt, Utc);
-                },
+        println!("日時の入力形式 ({})", INPUT_HINT);
+
+        let (start_datetime, window_end) = loop {
+            let input = read_input("開始日時を入力してください")?;
+            // 相対指定は入力のたびに現在時刻を取り直す
+            let parser = TimeExpressionParser::new(timezone);
+            let parsed = match parser.parse_window(&input) {
+                Ok(Some((start, end))) => Ok((start, Some(end))),
+                Ok(None) => parser.parse_start(&input).map(|start| (start, None)),
+                Err(e) => Err(e),
+            };
+            match parsed {
+                Ok(parsed) => break parsed,
                 Err(e) => {
                     println!("エラー: {}. もう一度入力してください。", e);
                     continue;
                 },
             }
         };
-        println!("入力された開始日時: {}", start_datetime);
-
-        let end_datetime = loop {
-            match get_naive_datetime_input("終了日時を入力してください") {
-                Ok(naive_dt) => {
-                    let dt = DateTime::from_naive_utc_and_offset(naive_dt, Utc);
-                    if dt <= start_datetime {
-                        println!("エラー: 終了日時は開始日時より後である必要があります。もう一度入力してください。");
+        println!("入力された開始日時: {}", timezone.localize(start_datetime));
+
+        let end_datetime = match window_end {
+            Some(end_datetime) => end_datetime,
+            None => loop {
+                let input = read_input(&format!("終了日時を入力してください ({})", END_INPUT_HINT))?;
+                let parsed = TimeExpressionParser::new(timezone)
+                    .parse_end(&input, start_datetime)
+                    .and_then(|end_datetime| validate_order(timezone, start_datetime, end_datetime).map(|_| end_datetime));
+                match parsed {
+                    Ok(end_datetime) => break end_datetime,
+                    Err(e) => {
+                        println!("エラー: {}. もう一度入力してください。", e);
                         continue;
-                    }
-                    break dt;
-                },
-                Err(e) => {
-                    println!("エラー: {}. もう一度入力してください。", e);
-                    continue;
-                },
-            }
+                    },
+                }
+            },
         };
-        println!("入力された終了日時: {}", end_datetime);
+        println!("入力された終了日時: {}", timezone.localize(end_datetime));
 
         Ok(Self { start_datetime, end_datetime })
     }
@@ -65,28 +117,26 @@ impl DateTimeInput {
     }
 }
 
-fn get_naive_datetime_input(prompt: &str) -> Result<NaiveDateTime, ConfigError> {
-    print!("{} (形式: YYYYMMDDHHMMSS): ", prompt);
+fn validate_order(timezone: Timezone, start_datetime: DateTime<Utc>, end_datetime: DateTime<Utc>) -> Result<(), ConfigError> {
+    if end_datetime <= start_datetime {
+        return Err(ConfigError::InvalidDateOrder(
+            timezone.localize(start_datetime).to_string(),
+            timezone.localize(end_datetime).to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn read_input(prompt: &str) -> Result<String, ConfigError> {
+    print!("{}: ", prompt);
     io::stdout().flush().map_err(|e| ConfigError::IoError(e.to_string()))?;
 
     let mut input = String::new();
-    io::stdin().read_line(&mut input).map_err(|e| ConfigError::IoError(e.to_string()))?;
-    let input = input.trim();
-
-    // 入力された文字列をパースして日時に変換
-    if input.len() != 14 {
-        return Err(ConfigError::InvalidInputLength(input.len().to_string()));
+    let read = io::stdin().read_line(&mut input).map_err(|e| ConfigError::IoError(e.to_string()))?;
+    // 入力が閉じられた (Ctrl+D / パイプの終端) 場合は、聞き直しても入力は来ない
+    if read == 0 {
+        println!();
+        return Err(ConfigError::IoError("日時の入力が終了しました (EOF)".to_string()));
     }
-
-    let year = input[0..4].parse::<i32>().map_err(|e| ConfigError::ParseError(format!("無効な年: {}", e)))?;
-    let month = input[4..6].parse::<u32>().map_err(|e| ConfigError::ParseError(format!("無効な月: {}", e)))?;
-    let day = input[6..8].parse::<u32>().map_err(|e| ConfigError::ParseError(format!("無効な日: {}", e)))?;
-    let hour = input[8..10].parse::<u32>().map_err(|e| ConfigError::ParseError(format!("無効な時: {}", e)))?;
-    let minute = input[10..12].parse::<u32>().map_err(|e| ConfigError::ParseError(format!("無効な分: {}", e)))?;
-    let second = input[12..14].parse::<u32>().map_err(|e| ConfigError::ParseError(format!("無効な秒: {}", e)))?;
-
-    let date = NaiveDate::from_ymd_opt(year, month, day).ok_or_else(|| ConfigError::ParseError("無効な日付です".to_string()))?;
-    let time = NaiveTime::from_hms_opt(hour, minute, second).ok_or_else(|| ConfigError::ParseError("無効な時刻です".to_string()))?;
-
-    Ok(NaiveDateTime::new(date, time))
+    Ok(input.trim().to_string())
 }
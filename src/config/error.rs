@@ -1,26 +1,34 @@
-use chrono::NaiveDateTime;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum ConfigError {
-    #[error("環境変数ファイルの読み込みに失敗しました: {0}")]
-    EnvFileReadError(String),
+    #[error("設定ファイルを読み込めません: {0}")]
+    ConfigFileError(String),
 
-    #[error("環境変数の取得に失敗しました: {0}")]
-    EnvVarError(String),
+    #[error("設定に誤りがあります:\n{0}")]
+    InvalidSettings(String),
 
-    #[error("環境変数の解析に失敗しました: {0}")]
-    EnvVarParseError(String),
+    #[error("設定の上書きは KEY=VALUE の形式で指定してください: {0}")]
+    InvalidOverride(String),
+
+    #[error("不明な値です: {0}")]
+    InvalidValue(String),
 
     #[error("終了日時は開始日時より後である必要があります: {0} > {1}")]
-    InvalidDateOrder(NaiveDateTime, NaiveDateTime),
+    InvalidDateOrder(String, String),
 
-    #[error("入力は14桁である必要がありますが、受け取った長さは {0} 桁です。")]
-    InvalidInputLength(String),
+    #[error("パスワードを取得できません: {0}")]
+    PasswordError(String),
 
     #[error("入出力エラー: {0}")]
     IoError(String),
 
-    #[error("日時のパースエラー: {0}")]
-    ParseError(String),
+    #[error("日時を解釈できません: {0}")]
+    InvalidTimeExpression(String),
+
+    #[error("期間を解釈できません (例: 15m, 1h30m, 2d): {0}")]
+    InvalidDuration(String),
+
+    #[error("無効なタイムゾーンです (UTC / local / +09:00 / Asia/Tokyo): {0}")]
+    InvalidTimezone(String),
 }
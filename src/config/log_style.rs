@@ -0,0 +1,61 @@
+use crate::config::error::ConfigError;
+use std::str::FromStr;
+
+/// IDPSログの出力先
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IdpsLogMode {
+    /// ファイルとコンソールの両方
+    #[default]
+    All,
+    File,
+    Console,
+    None,
+}
+
+impl FromStr for IdpsLogMode {
+    type Err = ConfigError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "all" => Ok(Self::All),
+            "file" => Ok(Self::File),
+            "console" => Ok(Self::Console),
+            "none" => Ok(Self::None),
+            other => Err(ConfigError::InvalidValue(format!("{} (all / file / console / none)", other))),
+        }
+    }
+}
+
+/// ログの各行に付ける出力元の表記
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PathStyle {
+    /// ソースファイルのパス
+    #[default]
+    FilePath,
+    /// モジュールのパス
+    ModulePath,
+    None,
+}
+
+impl PathStyle {
+    pub fn select<'a>(&self, file_path: &'a str, module_path: &'a str) -> &'a str {
+        match self {
+            Self::FilePath => file_path,
+            Self::ModulePath => module_path,
+            Self::None => "",
+        }
+    }
+}
+
+impl FromStr for PathStyle {
+    type Err = ConfigError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "file_path" => Ok(Self::FilePath),
+            "module_path" => Ok(Self::ModulePath),
+            "none" => Ok(Self::None),
+            other => Err(ConfigError::InvalidValue(format!("{} (file_path / module_path / none)", other))),
+        }
+    }
+}
@@ -1,7 +1,16 @@
 mod app_config;
+mod config_layers;
 mod date_input;
 mod error;
+mod log_style;
+mod password;
+mod time_expression;
+mod timezone;
 
-pub use app_config::AppConfig;
 pub use app_config::LoggerConfig;
+pub use app_config::{AppConfig, DatabaseConfig};
+pub use config_layers::{ConfigLayers, SettingOverride};
 pub use date_input::DateTimeInput;
+pub use log_style::{IdpsLogMode, PathStyle};
+pub use time_expression::parse_duration;
+pub use timezone::Timezone;
@@ -0,0 +1,243 @@
+use crate::config::app_config::DatabaseConfig;
+use crate::config::config_layers::ConfigSource;
+use crate::config::error::ConfigError;
+use crate::utils::secret::SecretString;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use zeroize::Zeroize;
+
+/// パスワードの取得元
+///
+/// ファイルの読み込みやコマンドの実行は接続する時点まで行わない (`config show` 等では実行しない)。
+#[derive(Debug, Clone)]
+pub enum PasswordSource {
+    Value(SecretString),
+    File {
+        path: PathBuf,
+        source: ConfigSource,
+    },
+    /// コマンドには秘密の値が含まれ得るため、エラーやログに出さないよう`SecretString`で持つ
+    Command {
+        command: SecretString,
+        source: ConfigSource,
+    },
+    /// PGPASSFILE (省略時は ~/.pgpass) から接続先に合うものを探す
+    Passfile(Option<PathBuf>),
+}
+
+impl Default for PasswordSource {
+    fn default() -> Self {
+        Self::Passfile(None)
+    }
+}
+
+impl PasswordSource {
+    /// パスワードを取得する。見つからなければパスワードを使わない認証方式とみなして`None`を返す
+    ///
+    /// エラーには設定項目とその設定元だけを含め、ファイルのパスやコマンドは出さない。
+    pub fn resolve(&self, database: &DatabaseConfig) -> Result<Option<SecretString>, ConfigError> {
+        match self {
+            Self::Value(password) => Ok(Some(password.clone())),
+            Self::File { path, source } => {
+                read_password_file(path).map(Some).map_err(|reason| ConfigError::PasswordError(format!("database.password_file ({}): {}", source, reason)))
+            },
+            Self::Command { command, source } => {
+                run_password_command(command.expose()).map(Some).map_err(|reason| ConfigError::PasswordError(format!("database.password_command ({}): {}", source, reason)))
+            },
+            Self::Passfile(passfile) => lookup_passfile(passfile.as_deref(), database).map_err(|reason| ConfigError::PasswordError(format!("database.passfile: {}", reason))),
+        }
+    }
+}
+
+/// ファイルからパスワードを読み込む (Docker/Kubernetesのシークレット)
+///
+/// 末尾の改行は取り除く。
+fn read_password_file(path: &Path) -> Result<SecretString, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("読み込めません: {}", e))?;
+    non_empty(trim_newline(content)).ok_or_else(|| "ファイルが空です".to_string())
+}
+
+/// コマンドを `sh -c` で実行し、標準出力をパスワードとして使う
+///
+/// 標準エラー出力はそのまま端末へ流す。末尾の改行は取り除く。
+fn run_password_command(command: &str) -> Result<SecretString, String> {
+    let output = Command::new("sh").arg("-c").arg(command).stdin(Stdio::null()).stderr(Stdio::inherit()).output().map_err(|e| format!("実行できません: {}", e))?;
+
+    let stdout = match String::from_utf8(output.stdout) {
+        Ok(stdout) => stdout,
+        Err(e) => {
+            e.into_bytes().zeroize();
+            return Err("出力がUTF-8ではありません".to_string());
+        },
+    };
+    let password = non_empty(trim_newline(stdout));
+    if !output.status.success() {
+        return Err(format!("失敗しました ({})", output.status));
+    }
+    password.ok_or_else(|| "何も出力しませんでした".to_string())
+}
+
+/// パスワードファイル (PGPASSFILE、省略時は ~/.pgpass) から接続先に合うパスワードを探す
+///
+/// 書式はlibpqと同じ `host:port:database:user:password` で、`*` は任意の値に一致する。
+/// libpqと同様に、グループや他のユーザーが読めるファイルは使わない。
+fn lookup_passfile(passfile: Option<&Path>, database: &DatabaseConfig) -> Result<Option<SecretString>, String> {
+    let path = match passfile {
+        Some(path) => path.to_path_buf(),
+        None => match std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".pgpass")) {
+            Some(path) if path.is_file() => path,
+            _ => return Ok(None),
+        },
+    };
+
+    let metadata = std::fs::metadata(&path).map_err(|e| format!("{} を読み込めません: {}", path.display(), e))?;
+    if metadata.permissions().mode() & 0o077 != 0 {
+        return Err(format!("{} はグループや他のユーザーが読めるため使えません (chmod 0600 {})", path.display(), path.display()));
+    }
+    let mut content = std::fs::read_to_string(&path).map_err(|e| format!("{} を読み込めません: {}", path.display(), e))?;
+
+    let port = database.port.to_string();
+    let wanted = [
+        database.host.as_str(),
+        port.as_str(),
+        database.database.as_str(),
+        database.user.as_str(),
+    ];
+    let password = content.lines().filter(|line| !line.starts_with('#')).find_map(|line| {
+        let mut fields = split_fields(line);
+        if fields.len() != 5 {
+            fields.iter_mut().for_each(Zeroize::zeroize);
+            return None;
+        }
+        let password = fields.pop().map(SecretString::from);
+        let matched = fields.iter().zip(wanted).all(|(field, wanted)| field == "*" || field == wanted);
+        matched.then_some(password).flatten()
+    });
+    content.zeroize();
+
+    Ok(password)
+}
+
+/// `:` 区切りの行を分割する。`\:` と `\\` はそれぞれ `:` と `\` として扱う
+fn split_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    fields.last_mut().unwrap().push(escaped);
+                }
+            },
+            ':' => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+fn trim_newline(mut value: String) -> String {
+    let len = value.trim_end_matches(['\r', '\n']).len();
+    value.truncate(len);
+    value
+}
+
+fn non_empty(value: String) -> Option<SecretString> {
+    (!value.is_empty()).then(|| SecretString::from(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn database() -> DatabaseConfig {
+        DatabaseConfig {
+            host: "db.example".to_string(),
+            port: 5432,
+            user: "replay".to_string(),
+            password_source: PasswordSource::default(),
+            database: "packets".to_string(),
+            legacy_row_order: false,
+        }
+    }
+
+    /// 所有者のみ読める一時ファイルを書き出す
+    fn write_file(content: &str, mode: u32) -> PathBuf {
+        static FILES: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!("packet-flow-{}-password-{}", std::process::id(), FILES.fetch_add(1, Ordering::Relaxed)));
+        std::fs::write(&path, content).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+        path
+    }
+
+    fn lookup(content: &str) -> Option<String> {
+        let path = write_file(content, 0o600);
+        let password = lookup_passfile(Some(&path), &database()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        password.map(|password| password.expose().to_string())
+    }
+
+    #[test]
+    fn splits_fields_with_escapes() {
+        assert_eq!(split_fields("db:5432:packets:replay:secret"), ["db", "5432", "packets", "replay", "secret"]);
+        assert_eq!(split_fields(r"db\:1:*:*:*:pa\:ss\\word"), [r"db:1", "*", "*", "*", r"pa:ss\word"]);
+        assert_eq!(split_fields(r"a:b\"), ["a", "b"]);
+    }
+
+    #[test]
+    fn passfile_matches_fields_in_order_with_wildcards() {
+        assert_eq!(lookup("db.example:5432:packets:replay:exact\n"), Some("exact".to_string()));
+        assert_eq!(lookup("*:*:*:*:any\n"), Some("any".to_string()));
+        // host:port:database:user の順に照合する (databaseとuserを入れ替えた行は一致しない)
+        assert_eq!(lookup("db.example:5432:replay:packets:swapped\n"), None);
+        assert_eq!(lookup("db.example:5433:packets:replay:other_port\n"), None);
+        assert_eq!(lookup("db.example:5432:packets:replay\n"), None);
+    }
+
+    #[test]
+    fn passfile_uses_first_matching_line() {
+        let content = "# db.example:5432:packets:replay:comment\nother:*:*:*:other_host\ndb.example:*:*:replay:first\n*:*:*:*:fallback\n";
+        assert_eq!(lookup(content), Some("first".to_string()));
+    }
+
+    #[test]
+    fn passfile_readable_by_others_is_rejected() {
+        let path = write_file("*:*:*:*:secret\n", 0o644);
+        let result = lookup_passfile(Some(&path), &database());
+        std::fs::remove_file(path).unwrap();
+        assert!(result.unwrap_err().contains("chmod 0600"));
+    }
+
+    #[test]
+    fn trims_only_trailing_newlines_from_file() {
+        let path = write_file(" pass word \r\n\n", 0o600);
+        let password = read_password_file(&path);
+        let empty = write_file("\n", 0o600);
+        let empty_password = read_password_file(&empty);
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(empty).unwrap();
+
+        assert_eq!(password.unwrap().expose(), " pass word ");
+        assert_eq!(empty_password.unwrap_err(), "ファイルが空です");
+    }
+
+    #[test]
+    fn trims_only_trailing_newlines_from_command_output() {
+        assert_eq!(run_password_command(r"printf ' secret\n\n'").unwrap().expose(), " secret");
+        assert_eq!(run_password_command("printf '\\n'").unwrap_err(), "何も出力しませんでした");
+        assert!(run_password_command("echo secret; exit 3").unwrap_err().starts_with("失敗しました"));
+    }
+
+    #[test]
+    fn errors_do_not_reveal_the_command() {
+        let source = PasswordSource::Command {
+            command: SecretString::from("echo hunter2; exit 1".to_string()),
+            source: ConfigSource::Environment("TIMESCALE_DB_PASSWORD_COMMAND"),
+        };
+        let message = source.resolve(&database()).unwrap_err().to_string();
+        assert!(message.starts_with("パスワードを取得できません: database.password_command (環境変数 TIMESCALE_DB_PASSWORD_COMMAND): 失敗しました"));
+        assert!(!message.contains("hunter2"));
+    }
+}
@@ -0,0 +1,172 @@
+use crate::config::error::ConfigError;
+use crate::config::timezone::Timezone;
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+
+/// オフセットを含まない日時の形式 (設定されたタイムゾーンで解釈する)
+const NAIVE_DATETIME_FORMATS: [&str; 7] = [
+    "%Y%m%d%H%M%S",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M",
+    "%Y/%m/%d %H:%M:%S",
+    "%Y/%m/%d %H:%M",
+];
+
+/// 開始日時と終了日時の組
+pub type TimeWindow = (DateTime<Utc>, DateTime<Utc>);
+
+/// `1h30m` / `15m` / `2d` / `500ms` 形式の期間をパースする
+pub fn parse_duration(input: &str) -> Result<Duration, ConfigError> {
+    let invalid = || ConfigError::InvalidDuration(input.to_string());
+    let compact: String = input.chars().filter(|c| !c.is_whitespace()).collect();
+    if compact.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut total = Duration::zero();
+    let mut rest = compact.as_str();
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let unit_len = rest[digits..].find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len() - digits);
+        let value: i64 = rest[..digits].parse().map_err(|_| invalid())?;
+        let part = match &rest[digits..digits + unit_len] {
+            "ms" => Duration::try_milliseconds(value),
+            "s" | "sec" => Duration::try_seconds(value),
+            "m" | "min" => Duration::try_minutes(value),
+            "h" | "hour" => Duration::try_hours(value),
+            "d" | "day" => Duration::try_days(value),
+            "w" | "week" => Duration::try_weeks(value),
+            _ => None,
+        };
+        total = part.and_then(|part| total.checked_add(&part)).ok_or_else(invalid)?;
+        rest = &rest[digits + unit_len..];
+    }
+
+    Ok(total)
+}
+
+/// 日時の入力式を現在時刻とタイムゾーンに基づいて解釈する
+///
+/// 受け付ける形式:
+/// - RFC 3339 (`2024-05-01T09:00:00+09:00`)
+/// - オフセットなしの日時 (`2024-05-01 09:00`, `20240501090000`, `2024-05-01`)
+/// - 現在時刻からの相対指定 (`now`, `-2h`, `now-30m`, `2h ago`)
+/// - 日時と期間の加減算 (`2024-05-01 09:00 + 15m`)
+/// - 期間全体の指定 (`last 15m`)
+/// - 終了日時のみ、開始日時からの相対指定 (`+30m`, `start + 30m`)
+pub struct TimeExpressionParser {
+    timezone: Timezone,
+    now: DateTime<Utc>,
+}
+
+impl TimeExpressionParser {
+    pub fn new(timezone: Timezone) -> Self {
+        Self { timezone, now: Utc::now() }
+    }
+
+    /// `last 15m` のような期間全体の指定であれば開始/終了日時を返す
+    pub fn parse_window(&self, input: &str) -> Result<Option<TimeWindow>, ConfigError> {
+        match input.trim().strip_prefix("last ") {
+            Some(duration) => Ok(Some((subtract(self.now, duration)?, self.now))),
+            None => Ok(None),
+        }
+    }
+
+    pub fn parse_start(&self, input: &str) -> Result<DateTime<Utc>, ConfigError> {
+        self.parse_point(input.trim())
+    }
+
+    /// 先頭が`+`または`start`の場合は開始日時からの相対指定として扱う
+    pub fn parse_end(&self, input: &str, start: DateTime<Utc>) -> Result<DateTime<Utc>, ConfigError> {
+        let input = input.trim();
+        if let Some(duration) = input.strip_prefix("start").map(str::trim_start).and_then(|rest| rest.strip_prefix('+')).or_else(|| input.strip_prefix('+')) {
+            return add(start, duration);
+        }
+        self.parse_point(input)
+    }
+
+    fn parse_point(&self, input: &str) -> Result<DateTime<Utc>, ConfigError> {
+        if input.is_empty() {
+            return Err(ConfigError::InvalidTimeExpression("日時が入力されていません".to_string()));
+        }
+        if input == "now" {
+            return Ok(self.now);
+        }
+        if let Some(duration) = input.strip_suffix("ago") {
+            return subtract(self.now, duration);
+        }
+
+        let relative = input.strip_prefix("now").map(str::trim_start).unwrap_or(input);
+        if let Some(duration) = relative.strip_prefix('-') {
+            return subtract(self.now, duration);
+        }
+        if let Some(duration) = relative.strip_prefix('+') {
+            return add(self.now, duration);
+        }
+
+        // 日付やオフセットにも`+`/`-`が含まれるため、演算子は前後の空白で区別する
+        if let Some((base, duration)) = input.rsplit_once(" + ") {
+            return add(self.parse_absolute(base.trim())?, duration);
+        }
+        if let Some((base, duration)) = input.rsplit_once(" - ") {
+            return subtract(self.parse_absolute(base.trim())?, duration);
+        }
+
+        self.parse_absolute(input)
+    }
+
+    fn parse_absolute(&self, input: &str) -> Result<DateTime<Utc>, ConfigError> {
+        if let Ok(datetime) = DateTime::parse_from_rfc3339(input) {
+            return Ok(datetime.with_timezone(&Utc));
+        }
+
+        let naive = NAIVE_DATETIME_FORMATS
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+            .or_else(|| NaiveDate::parse_from_str(input, "%Y-%m-%d").ok().and_then(|date| date.and_hms_opt(0, 0, 0)))
+            .ok_or_else(|| ConfigError::InvalidTimeExpression(input.to_string()))?;
+
+        self.timezone.resolve(naive)
+    }
+}
+
+/// 日時に期間を足す。表せる日時の範囲を超える場合は期間の誤りとして扱う
+fn add(base: DateTime<Utc>, duration: &str) -> Result<DateTime<Utc>, ConfigError> {
+    base.checked_add_signed(parse_duration(duration)?).ok_or_else(|| ConfigError::InvalidDuration(duration.trim().to_string()))
+}
+
+/// 日時から期間を引く。表せる日時の範囲を超える場合は期間の誤りとして扱う
+fn subtract(base: DateTime<Utc>, duration: &str) -> Result<DateTime<Utc>, ConfigError> {
+    base.checked_sub_signed(parse_duration(duration)?).ok_or_else(|| ConfigError::InvalidDuration(duration.trim().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HUGE: &str = "99999999w";
+
+    #[test]
+    fn out_of_range_arithmetic_is_an_error() {
+        let parser = TimeExpressionParser::new(Timezone::default());
+        let start = parser.parse_start("2024-05-01T00:00:00Z").unwrap();
+
+        assert!(matches!(parser.parse_window(&format!("last {}", HUGE)), Err(ConfigError::InvalidDuration(_))));
+        assert!(matches!(parser.parse_start(&format!("-{}", HUGE)), Err(ConfigError::InvalidDuration(_))));
+        assert!(matches!(parser.parse_start(&format!("now+{}", HUGE)), Err(ConfigError::InvalidDuration(_))));
+        assert!(matches!(parser.parse_start(&format!("{} ago", HUGE)), Err(ConfigError::InvalidDuration(_))));
+        assert!(matches!(parser.parse_start(&format!("2024-05-01 + {}", HUGE)), Err(ConfigError::InvalidDuration(_))));
+        assert!(matches!(parser.parse_start(&format!("2024-05-01 - {}", HUGE)), Err(ConfigError::InvalidDuration(_))));
+        assert!(matches!(parser.parse_end(&format!("+{}", HUGE), start), Err(ConfigError::InvalidDuration(_))));
+    }
+
+    #[test]
+    fn relative_arithmetic_in_range() {
+        let parser = TimeExpressionParser::new(Timezone::default());
+        let start = parser.parse_start("2024-05-01T00:00:00Z").unwrap();
+
+        assert_eq!(parser.parse_end("start + 1h30m", start).unwrap(), start + Duration::minutes(90));
+        assert_eq!(parser.parse_start("2024-05-01 09:00 - 15m").unwrap(), parser.parse_start("2024-05-01T08:45:00Z").unwrap());
+    }
+}
@@ -0,0 +1,97 @@
+use crate::config::error::ConfigError;
+use chrono::{DateTime, FixedOffset, Local, LocalResult, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::str::FromStr;
+
+/// 日時の入力と表示に使うタイムゾーン
+///
+/// DBにはUTCで保存されているため、入力はこのタイムゾーンからUTCへ、表示はUTCからこのタイムゾーンへ変換する。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Timezone {
+    #[default]
+    Utc,
+    /// 実行環境のローカルタイムゾーン
+    Local,
+    Fixed(FixedOffset),
+    /// IANAのタイムゾーン名 (Asia/Tokyo 等)。夏時間も考慮される
+    Named(Tz),
+}
+
+impl FromStr for Timezone {
+    type Err = ConfigError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "UTC" | "utc" | "Z" => Ok(Self::Utc),
+            "local" | "Local" => Ok(Self::Local),
+            other => FixedOffset::from_str(other).map(Self::Fixed).or_else(|_| Tz::from_str(other).map(Self::Named)).map_err(|_| ConfigError::InvalidTimezone(other.to_string())),
+        }
+    }
+}
+
+impl Timezone {
+    /// 壁時計の時刻をUTCへ変換する
+    ///
+    /// 夏時間の切り替えで2通りに解釈できる時刻は早い方を採用し、存在しない時刻はエラーにする。
+    pub fn resolve(&self, naive: NaiveDateTime) -> Result<DateTime<Utc>, ConfigError> {
+        let resolved = match self {
+            Self::Utc => return Ok(Utc.from_utc_datetime(&naive)),
+            Self::Local => Local.from_local_datetime(&naive).map(|dt| dt.with_timezone(&Utc)),
+            Self::Fixed(offset) => offset.from_local_datetime(&naive).map(|dt| dt.with_timezone(&Utc)),
+            Self::Named(tz) => tz.from_local_datetime(&naive).map(|dt| dt.with_timezone(&Utc)),
+        };
+
+        match resolved {
+            LocalResult::Single(dt) | LocalResult::Ambiguous(dt, _) => Ok(dt),
+            LocalResult::None => Err(ConfigError::InvalidTimeExpression(format!("{} はこのタイムゾーンに存在しない時刻です", naive))),
+        }
+    }
+
+    /// 表示用にこのタイムゾーンの時刻へ変換する
+    pub fn localize(&self, datetime: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self {
+            Self::Utc => datetime.fixed_offset(),
+            Self::Local => datetime.with_timezone(&Local).fixed_offset(),
+            Self::Fixed(offset) => datetime.with_timezone(offset),
+            Self::Named(tz) => datetime.with_timezone(tz).fixed_offset(),
+        }
+    }
+
+    pub fn now(&self) -> DateTime<FixedOffset> {
+        self.localize(Utc::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn naive(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap().and_hms_opt(h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn parses_names_and_offsets() {
+        assert_eq!("utc".parse::<Timezone>().unwrap(), Timezone::Utc);
+        assert_eq!("+09:00".parse::<Timezone>().unwrap(), Timezone::Fixed(FixedOffset::east_opt(9 * 3600).unwrap()));
+        assert_eq!("Asia/Tokyo".parse::<Timezone>().unwrap(), Timezone::Named(chrono_tz::Asia::Tokyo));
+        assert!(matches!("Mars/Olympus".parse::<Timezone>(), Err(ConfigError::InvalidTimezone(_))));
+    }
+
+    #[test]
+    fn resolves_and_localizes_named_zone() {
+        let tokyo = Timezone::Named(chrono_tz::Asia::Tokyo);
+        let utc = tokyo.resolve(naive(2024, 5, 1, 9, 0)).unwrap();
+        assert_eq!(utc, Utc.from_utc_datetime(&naive(2024, 5, 1, 0, 0)));
+        assert_eq!(tokyo.localize(utc).to_rfc3339(), "2024-05-01T09:00:00+09:00");
+    }
+
+    #[test]
+    fn handles_daylight_saving_transitions() {
+        let new_york = Timezone::Named(chrono_tz::America::New_York);
+        // 2024-03-10 02:30 は存在せず、2024-11-03 01:30 は2回ある (早い方のEDTを採用する)
+        assert!(matches!(new_york.resolve(naive(2024, 3, 10, 2, 30)), Err(ConfigError::InvalidTimeExpression(_))));
+        assert_eq!(new_york.resolve(naive(2024, 11, 3, 1, 30)).unwrap(), Utc.from_utc_datetime(&naive(2024, 11, 3, 5, 30)));
+    }
+}
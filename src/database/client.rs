@@ -1,20 +1,72 @@
 use crate::database::error::DatabaseError;
-use crate::database::pool::DatabasePool;
+use crate::database::pool::{DatabasePool, PooledClient};
+use crate::database::row::Row;
+use crate::database::statement_cache::{CachedClient, StatementCacheStats};
+use crate::database::transaction::PooledTransaction;
+use crate::utils::secret::SecretString;
 use async_trait::async_trait;
-use std::collections::HashMap;
-use tokio_postgres::{Row, Statement};
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use futures::{pin_mut, SinkExt, Stream, StreamExt, TryStreamExt};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio_postgres::types::ToSql;
 
+pub type QueryParams<'a> = &'a [&'a (dyn ToSql + Sync)];
+
+/// 1行ずつ受け取るクエリ結果のストリーム
+pub type RowStream = BoxStream<'static, Result<Row, DatabaseError>>;
+
+/// `COPY`で送受信するデータのストリーム
+pub type CopyStream = BoxStream<'static, Result<Bytes, DatabaseError>>;
+
+/// リポジトリ層が利用するクエリ操作
+///
+/// 実データベースに依存しないよう、リポジトリはこのトレイト越しにのみクエリを実行する。
+/// リポジトリの拡張向けに、ツリー内に呼び出し元がまだない操作も`allow(dead_code)`を付けて提供している。
+#[async_trait]
+pub trait ExecuteQuery: Send + Sync {
+    async fn query(&self, query: &str, params: QueryParams<'_>) -> Result<Vec<Row>, DatabaseError>;
+
+    async fn query_one(&self, query: &str, params: QueryParams<'_>) -> Result<Row, DatabaseError>;
+
+    async fn query_opt(&self, query: &str, params: QueryParams<'_>) -> Result<Option<Row>, DatabaseError>;
+
+    async fn execute(&self, query: &str, params: QueryParams<'_>) -> Result<u64, DatabaseError>;
+
+    /// 結果をバッファリングせず、行ごとに返す
+    async fn query_raw(&self, query: &str, params: QueryParams<'_>) -> Result<RowStream, DatabaseError>;
+
+    /// `COPY ... FROM STDIN`を実行し、書き込んだ行数を返す
+    #[allow(dead_code)]
+    async fn copy_in(&self, query: &str, data: CopyStream) -> Result<u64, DatabaseError>;
+
+    /// `COPY ... TO STDOUT`を実行し、出力をストリームで返す
+    #[allow(dead_code)]
+    async fn copy_out(&self, query: &str) -> Result<CopyStream, DatabaseError>;
+}
+
+/// トランザクションを開始できるクエリ実行先
 #[async_trait]
-pub trait ExecuteQuery {
-    async fn query(&self, query: &str, params: &[&(dyn tokio_postgres::types::ToSql + Sync)]) -> Result<Vec<Row>, DatabaseError>;
+pub trait BeginTransaction: ExecuteQuery {
+    async fn transaction(&self) -> Result<Box<dyn QueryTransaction>, DatabaseError>;
 }
 
-pub struct Database {
-    prepared_statements: HashMap<String, Statement>,
+/// 開始済みのトランザクション
+///
+/// `commit`も`rollback`も呼ばれずに破棄された場合はロールバックされる。
+#[async_trait]
+pub trait QueryTransaction: ExecuteQuery {
+    async fn commit(self: Box<Self>) -> Result<(), DatabaseError>;
+
+    #[allow(dead_code)]
+    async fn rollback(self: Box<Self>) -> Result<(), DatabaseError>;
 }
 
+pub struct Database;
+
 impl Database {
-    pub async fn connect(host: &str, port: u16, user: &str, password: &str, database: &str) -> Result<(), DatabaseError> {
+    pub async fn connect(host: &str, port: u16, user: &str, password: Option<&SecretString>, database: &str) -> Result<(), DatabaseError> {
         DatabasePool::initialize(host, port, user, password, database).await
     }
 
@@ -22,27 +74,127 @@ impl Database {
         // DbPoolの存在を確認
         let _ = DatabasePool::get_pool();
         // Databaseはステートレスなので、staticなインスタンスを返す
-        static DATABASE: std::sync::OnceLock<Database> = std::sync::OnceLock::new();
-        DATABASE.get_or_init(|| Database {
-            prepared_statements: HashMap::new(),
-        })
+        static DATABASE: Database = Database;
+        &DATABASE
+    }
+
+    pub fn statement_cache_stats() -> StatementCacheStats {
+        StatementCacheStats::snapshot()
+    }
+
+    async fn client() -> Result<PooledClient, DatabaseError> {
+        let pool = DatabasePool::get_pool().map_err(|e| DatabaseError::PoolRetrievalError(e.to_string()))?;
+        pool.inner().get_owned().await.map_err(|e| DatabaseError::ConnectionError(e.to_string()))
     }
 }
 
 #[async_trait]
 impl ExecuteQuery for Database {
-    async fn query(&self, query: &str, params: &[&(dyn tokio_postgres::types::ToSql + Sync)]) -> Result<Vec<Row>, DatabaseError> {
-        let pool = DatabasePool::get_pool().map_err(|e| DatabaseError::PoolRetrievalError(e.to_string()))?;
-        let client = pool.inner().get().await.map_err(|e| DatabaseError::ConnectionError(e.to_string()))?;
+    async fn query(&self, query: &str, params: QueryParams<'_>) -> Result<Vec<Row>, DatabaseError> {
+        Self::client().await?.query(query, params).await
+    }
+
+    async fn query_one(&self, query: &str, params: QueryParams<'_>) -> Result<Row, DatabaseError> {
+        Self::client().await?.query_one(query, params).await
+    }
+
+    async fn query_opt(&self, query: &str, params: QueryParams<'_>) -> Result<Option<Row>, DatabaseError> {
+        Self::client().await?.query_opt(query, params).await
+    }
+
+    async fn execute(&self, query: &str, params: QueryParams<'_>) -> Result<u64, DatabaseError> {
+        Self::client().await?.execute(query, params).await
+    }
+
+    async fn query_raw(&self, query: &str, params: QueryParams<'_>) -> Result<RowStream, DatabaseError> {
+        let client = Self::client().await?;
+        let rows = client.query_raw(query, params).await?;
+        Ok(PinnedConnectionStream::new(client, rows).boxed())
+    }
+
+    async fn copy_in(&self, query: &str, data: CopyStream) -> Result<u64, DatabaseError> {
+        Self::client().await?.copy_in(query, data).await
+    }
+
+    async fn copy_out(&self, query: &str) -> Result<CopyStream, DatabaseError> {
+        let client = Self::client().await?;
+        let data = client.copy_out(query).await?;
+        Ok(PinnedConnectionStream::new(client, data).boxed())
+    }
+}
+
+#[async_trait]
+impl BeginTransaction for Database {
+    async fn transaction(&self) -> Result<Box<dyn QueryTransaction>, DatabaseError> {
+        let transaction = PooledTransaction::begin(Self::client().await?).await?;
+        Ok(Box::new(transaction))
+    }
+}
+
+#[async_trait]
+impl ExecuteQuery for CachedClient {
+    async fn query(&self, query: &str, params: QueryParams<'_>) -> Result<Vec<Row>, DatabaseError> {
+        // 接続ごとのキャッシュからプリペアドステートメントを取得し、なければ準備してキャッシュする
+        let stmt = self.prepare_cached(query).await.map_err(|e| DatabaseError::QueryPreparationError(e.to_string()))?;
+        let rows = (**self).query(&stmt, params).await.map_err(|e| DatabaseError::QueryExecutionError(e.to_string()))?;
+        Ok(rows.into_iter().map(Row::from).collect())
+    }
+
+    async fn query_one(&self, query: &str, params: QueryParams<'_>) -> Result<Row, DatabaseError> {
+        self.query_opt(query, params).await?.ok_or(DatabaseError::RowNotFound)
+    }
+
+    async fn query_opt(&self, query: &str, params: QueryParams<'_>) -> Result<Option<Row>, DatabaseError> {
+        let stmt = self.prepare_cached(query).await.map_err(|e| DatabaseError::QueryPreparationError(e.to_string()))?;
+        let row = (**self).query_opt(&stmt, params).await.map_err(|e| DatabaseError::QueryExecutionError(e.to_string()))?;
+        Ok(row.map(Row::from))
+    }
+
+    async fn execute(&self, query: &str, params: QueryParams<'_>) -> Result<u64, DatabaseError> {
+        let stmt = self.prepare_cached(query).await.map_err(|e| DatabaseError::QueryPreparationError(e.to_string()))?;
+        (**self).execute(&stmt, params).await.map_err(|e| DatabaseError::QueryExecutionError(e.to_string()))
+    }
+
+    async fn query_raw(&self, query: &str, params: QueryParams<'_>) -> Result<RowStream, DatabaseError> {
+        let stmt = self.prepare_cached(query).await.map_err(|e| DatabaseError::QueryPreparationError(e.to_string()))?;
+        let rows = (**self).query_raw(&stmt, params.iter().copied()).await.map_err(|e| DatabaseError::QueryExecutionError(e.to_string()))?;
+        Ok(rows.map_ok(Row::from).map_err(|e| DatabaseError::QueryExecutionError(e.to_string())).boxed())
+    }
+
+    async fn copy_in(&self, query: &str, data: CopyStream) -> Result<u64, DatabaseError> {
+        let sink = (**self).copy_in::<_, Bytes>(query).await.map_err(|e| DatabaseError::CopyError(e.to_string()))?;
+        pin_mut!(sink);
+
+        let mut data = data.map(|chunk| chunk.map_err(|e| DatabaseError::CopyError(e.to_string())));
+        while let Some(chunk) = data.next().await {
+            sink.send(chunk?).await.map_err(|e| DatabaseError::CopyError(e.to_string()))?;
+        }
+
+        sink.finish().await.map_err(|e| DatabaseError::CopyError(e.to_string()))
+    }
+
+    async fn copy_out(&self, query: &str) -> Result<CopyStream, DatabaseError> {
+        let data = (**self).copy_out(query).await.map_err(|e| DatabaseError::CopyError(e.to_string()))?;
+        Ok(data.map_err(|e| DatabaseError::CopyError(e.to_string())).boxed())
+    }
+}
+
+/// ストリームを読み切るまでプールの接続を保持するラッパー
+struct PinnedConnectionStream<T> {
+    _client: PooledClient,
+    inner: BoxStream<'static, Result<T, DatabaseError>>,
+}
+
+impl<T> PinnedConnectionStream<T> {
+    fn new(client: PooledClient, inner: BoxStream<'static, Result<T, DatabaseError>>) -> Self {
+        Self { _client: client, inner }
+    }
+}
 
-        // プリペアドステートメントのキャッシュを試みる
-        let stmt = if let Some(stmt) = self.prepared_statements.get(query) {
-            stmt.clone()
-        } else {
-            client.prepare(query).await.map_err(|e| DatabaseError::QueryPreparationError(e.to_string()))?
-        };
+impl<T> Stream for PinnedConnectionStream<T> {
+    type Item = Result<T, DatabaseError>;
 
-        let rows = client.query(&stmt, params).await.map_err(|e| DatabaseError::QueryExecutionError(e.to_string()))?;
-        Ok(rows)
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
     }
 }
@@ -1,11 +1,7 @@
-use log::error;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum DatabaseError {
-    #[error("接続マネージャの作成に失敗しました: {0}")]
-    ConnectionManagerError(#[from] tokio_postgres::Error),
-
     #[error("データベースプールの作成に失敗しました: {0}")]
     CreatePoolError(String),
 
@@ -29,4 +25,19 @@ pub enum DatabaseError {
 
     #[error("データベースプールの取得に失敗しました: {0}")]
     PoolRetrievalError(String),
+
+    #[error("クエリ結果の変換に失敗しました: {0}")]
+    RowMappingError(String),
+
+    #[error("クエリの結果が0行でした")]
+    RowNotFound,
+
+    #[error("テーブルの形式が対応していません: {0}")]
+    UnsupportedSchema(String),
+
+    #[error("トランザクションの処理に失敗しました: {0}")]
+    TransactionError(String),
+
+    #[error("COPYの実行に失敗しました: {0}")]
+    CopyError(String),
 }
@@ -0,0 +1,34 @@
+use crate::database::error::DatabaseError;
+use crate::database::row::Row;
+use tokio_postgres::types::{FromSqlOwned, Type};
+
+/// クエリ結果の1行から値を組み立てる
+pub trait FromRow: Sized {
+    fn from_row(row: &Row) -> Result<Self, DatabaseError>;
+}
+
+/// カラムが結果に含まれていなければ`None`、含まれていればNULL許容で取得する
+pub fn optional_column<T: FromSqlOwned>(row: &Row, name: &str) -> Result<Option<T>, DatabaseError> {
+    match row.column_type(name) {
+        Some(_) => row.try_get::<Option<T>>(name),
+        None => Ok(None),
+    }
+}
+
+/// 整数カラムを型幅に関係なく取得する (`smallint`/`integer`/`bigint`)
+pub fn optional_integer(row: &Row, name: &str) -> Result<Option<i64>, DatabaseError> {
+    let Some(type_) = row.column_type(name) else {
+        return Ok(None);
+    };
+
+    match *type_ {
+        Type::INT2 => Ok(optional_column::<i16>(row, name)?.map(i64::from)),
+        Type::INT4 => Ok(optional_column::<i32>(row, name)?.map(i64::from)),
+        Type::INT8 => optional_column::<i64>(row, name),
+        ref other => Err(DatabaseError::RowMappingError(format!("{}: 整数型ではありません ({})", name, other))),
+    }
+}
+
+pub fn required_column<T: FromSqlOwned>(row: &Row, name: &str) -> Result<T, DatabaseError> {
+    row.try_get::<T>(name)
+}
@@ -0,0 +1,177 @@
+use crate::database::client::{BeginTransaction, CopyStream, ExecuteQuery, QueryParams, QueryTransaction, RowStream};
+use crate::database::error::DatabaseError;
+use crate::database::row::Row;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{stream, StreamExt, TryStreamExt};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// 実行されたクエリと引数 (引数は`Debug`表現で記録する)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedQuery {
+    pub query: String,
+    pub params: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    results: Mutex<VecDeque<Vec<Row>>>,
+    recorded: Mutex<Vec<RecordedQuery>>,
+    affected_rows: AtomicU64,
+}
+
+/// テスト用にメモリ上で動くクエリ実行先
+///
+/// 実行されたクエリを記録し、行を返すクエリには`with_rows`で積んだ結果を積んだ順に1つずつ返す
+/// (積んだ結果がなくなれば0行)。`execute`は`with_affected_rows`で指定した行数 (既定は0) を返し、`COPY ... TO STDOUT`の出力は常に空になる。
+/// トランザクションも同じ記録先を共有し、`BEGIN`/`COMMIT`/`ROLLBACK`を記録する。
+#[derive(Debug, Default)]
+pub struct MemoryDatabase {
+    state: Arc<MemoryState>,
+}
+
+impl MemoryDatabase {
+    /// 次に実行される行を返すクエリの結果を積む
+    pub fn with_rows(self, rows: Vec<Row>) -> Self {
+        self.state.results.lock().unwrap().push_back(rows);
+        self
+    }
+
+    /// `execute`が返す、変更した行数
+    pub fn with_affected_rows(self, affected_rows: u64) -> Self {
+        self.state.affected_rows.store(affected_rows, Ordering::Relaxed);
+        self
+    }
+
+    pub fn recorded(&self) -> Vec<RecordedQuery> {
+        self.state.recorded.lock().unwrap().clone()
+    }
+
+    fn record(&self, query: &str, params: QueryParams<'_>) {
+        // インデントや改行の違いで比較しにくくならないよう、空白を詰めて記録する
+        let query = query.split_whitespace().collect::<Vec<_>>().join(" ");
+        let params = params.iter().map(|param| format!("{:?}", param)).collect();
+        self.state.recorded.lock().unwrap().push(RecordedQuery { query, params });
+    }
+
+    fn next_rows(&self) -> Vec<Row> {
+        self.state.results.lock().unwrap().pop_front().unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl ExecuteQuery for MemoryDatabase {
+    async fn query(&self, query: &str, params: QueryParams<'_>) -> Result<Vec<Row>, DatabaseError> {
+        self.record(query, params);
+        Ok(self.next_rows())
+    }
+
+    async fn query_one(&self, query: &str, params: QueryParams<'_>) -> Result<Row, DatabaseError> {
+        self.query_opt(query, params).await?.ok_or(DatabaseError::RowNotFound)
+    }
+
+    async fn query_opt(&self, query: &str, params: QueryParams<'_>) -> Result<Option<Row>, DatabaseError> {
+        self.record(query, params);
+        Ok(self.next_rows().into_iter().next())
+    }
+
+    async fn execute(&self, query: &str, params: QueryParams<'_>) -> Result<u64, DatabaseError> {
+        self.record(query, params);
+        Ok(self.state.affected_rows.load(Ordering::Relaxed))
+    }
+
+    async fn query_raw(&self, query: &str, params: QueryParams<'_>) -> Result<RowStream, DatabaseError> {
+        self.record(query, params);
+        Ok(stream::iter(self.next_rows().into_iter().map(Ok)).boxed())
+    }
+
+    async fn copy_in(&self, query: &str, data: CopyStream) -> Result<u64, DatabaseError> {
+        self.record(query, &[]);
+        let chunks: Vec<Bytes> = data.try_collect().await?;
+        // テキスト形式とみなし、改行の数を行数として返す
+        Ok(chunks.iter().flat_map(|chunk| chunk.iter()).filter(|&&byte| byte == b'\n').count() as u64)
+    }
+
+    async fn copy_out(&self, query: &str) -> Result<CopyStream, DatabaseError> {
+        self.record(query, &[]);
+        Ok(stream::empty().boxed())
+    }
+}
+
+#[async_trait]
+impl BeginTransaction for MemoryDatabase {
+    async fn transaction(&self) -> Result<Box<dyn QueryTransaction>, DatabaseError> {
+        self.record("BEGIN", &[]);
+        Ok(Box::new(MemoryTransaction {
+            db: MemoryDatabase { state: Arc::clone(&self.state) },
+            finished: false,
+        }))
+    }
+}
+
+/// `MemoryDatabase`のトランザクション。終了せずに破棄されると`ROLLBACK`を記録する
+struct MemoryTransaction {
+    db: MemoryDatabase,
+    finished: bool,
+}
+
+impl MemoryTransaction {
+    fn finish(&mut self, command: &str) {
+        self.finished = true;
+        self.db.record(command, &[]);
+    }
+}
+
+impl Drop for MemoryTransaction {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.db.record("ROLLBACK", &[]);
+        }
+    }
+}
+
+#[async_trait]
+impl ExecuteQuery for MemoryTransaction {
+    async fn query(&self, query: &str, params: QueryParams<'_>) -> Result<Vec<Row>, DatabaseError> {
+        self.db.query(query, params).await
+    }
+
+    async fn query_one(&self, query: &str, params: QueryParams<'_>) -> Result<Row, DatabaseError> {
+        self.db.query_one(query, params).await
+    }
+
+    async fn query_opt(&self, query: &str, params: QueryParams<'_>) -> Result<Option<Row>, DatabaseError> {
+        self.db.query_opt(query, params).await
+    }
+
+    async fn execute(&self, query: &str, params: QueryParams<'_>) -> Result<u64, DatabaseError> {
+        self.db.execute(query, params).await
+    }
+
+    async fn query_raw(&self, query: &str, params: QueryParams<'_>) -> Result<RowStream, DatabaseError> {
+        self.db.query_raw(query, params).await
+    }
+
+    async fn copy_in(&self, query: &str, data: CopyStream) -> Result<u64, DatabaseError> {
+        self.db.copy_in(query, data).await
+    }
+
+    async fn copy_out(&self, query: &str) -> Result<CopyStream, DatabaseError> {
+        self.db.copy_out(query).await
+    }
+}
+
+#[async_trait]
+impl QueryTransaction for MemoryTransaction {
+    async fn commit(mut self: Box<Self>) -> Result<(), DatabaseError> {
+        self.finish("COMMIT");
+        Ok(())
+    }
+
+    async fn rollback(mut self: Box<Self>) -> Result<(), DatabaseError> {
+        self.finish("ROLLBACK");
+        Ok(())
+    }
+}
@@ -1,8 +1,18 @@
 mod client;
 mod error;
+mod from_row;
+#[cfg(test)]
+mod memory;
 mod pool;
+mod row;
+mod statement_cache;
+mod transaction;
 
 pub use client::Database;
 pub use error::DatabaseError;
+pub(crate) use from_row::{optional_column, optional_integer, required_column, FromRow};
 
-pub(crate) use client::ExecuteQuery;
+pub(crate) use client::{BeginTransaction, ExecuteQuery, RowStream};
+#[cfg(test)]
+pub(crate) use memory::MemoryDatabase;
+pub(crate) use row::Row;
@@ -1,20 +1,25 @@
 use crate::database::error::DatabaseError;
-use bb8::Pool;
+use crate::database::statement_cache::CachingConnectionManager;
+use crate::utils::secret::SecretString;
+use bb8::{Pool, PooledConnection};
 use bb8_postgres::PostgresConnectionManager;
 use std::sync::OnceLock;
 use std::time::Duration;
-use tokio_postgres::NoTls;
+use tokio_postgres::{Config, NoTls};
+
+/// プールから所有権ごと借りた接続
+pub type PooledClient = PooledConnection<'static, CachingConnectionManager>;
 
 pub(crate) static DATABASE_POOL: OnceLock<DatabasePool> = OnceLock::new();
 
 #[derive(Debug)]
 pub struct DatabasePool {
-    pool: Pool<PostgresConnectionManager<NoTls>>,
+    pool: Pool<CachingConnectionManager>,
 }
 
 impl DatabasePool {
-    pub async fn new(connection_string: &str) -> Result<Self, DatabaseError> {
-        let manager = PostgresConnectionManager::new_from_stringlike(connection_string, NoTls).map_err(DatabaseError::ConnectionManagerError)?;
+    pub async fn new(config: Config) -> Result<Self, DatabaseError> {
+        let manager = CachingConnectionManager::new(PostgresConnectionManager::new(config, NoTls));
         let pool = Pool::builder()
             .max_size(30)
             .min_idle(Some(10))
@@ -28,15 +33,17 @@ impl DatabasePool {
         Ok(Self { pool })
     }
 
-    pub async fn initialize(host: &str, port: u16, user: &str, password: &str, database: &str) -> Result<(), DatabaseError> {
-        let connection_string = format!("postgres://{}:{}@{}:{}/{}", user, password, host, port, database);
-        let pool = Self::new(&connection_string).await?;
+    /// 接続文字列は組み立てずに接続設定を直接渡す (パスワードがエラーやログに出ないようにするため)
+    pub async fn initialize(host: &str, port: u16, user: &str, password: Option<&SecretString>, database: &str) -> Result<(), DatabaseError> {
+        let mut config = Config::new();
+        config.host(host).port(port).user(user).dbname(database);
+        if let Some(password) = password {
+            config.password(password.expose());
+        }
+        let pool = Self::new(config.clone()).await?;
 
         // 接続テスト
-        let (client, connection) = tokio_postgres::connect(&connection_string, NoTls).await.map_err(|e| {
-            eprintln!("接続エラー: {:?}", e);
-            DatabaseError::InitFailedConnectDatabase(e.to_string())
-        })?;
+        let (client, connection) = config.connect(NoTls).await.map_err(|e| DatabaseError::InitFailedConnectDatabase(e.to_string()))?;
 
         tokio::spawn(async move {
             if let Err(e) = connection.await {
@@ -54,7 +61,7 @@ impl DatabasePool {
         DATABASE_POOL.get().ok_or(DatabaseError::PoolNotInitialized)
     }
 
-    pub fn inner(&self) -> &Pool<PostgresConnectionManager<NoTls>> {
+    pub fn inner(&self) -> &Pool<CachingConnectionManager> {
         &self.pool
     }
 }
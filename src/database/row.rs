@@ -0,0 +1,89 @@
+use crate::database::error::DatabaseError;
+use tokio_postgres::types::{FromSql, Type};
+#[cfg(test)]
+use tokio_postgres::types::{IsNull, ToSql};
+
+/// クエリ結果の1行
+///
+/// 実データベースの行と、テスト用にメモリ上で組み立てた行を同じ方法で読み取る。
+#[derive(Debug)]
+pub struct Row {
+    inner: RowInner,
+}
+
+#[derive(Debug)]
+enum RowInner {
+    Postgres(tokio_postgres::Row),
+    #[cfg(test)]
+    Memory(Vec<MemoryColumn>),
+}
+
+/// メモリ上の行の1カラム (値は実データベースと同じバイナリ形式で保持する)
+#[cfg(test)]
+#[derive(Debug)]
+struct MemoryColumn {
+    name: String,
+    type_: Type,
+    raw: Option<Vec<u8>>,
+}
+
+impl Row {
+    /// カラムの値を取得する。カラムが存在しないか型が合わない場合はパニックする
+    pub fn get<'a, T: FromSql<'a>>(&'a self, name: &str) -> T {
+        self.try_get(name).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// カラムの値を取得する。カラムが存在しないか型が合わない場合は`RowMappingError`を返す
+    pub fn try_get<'a, T: FromSql<'a>>(&'a self, name: &str) -> Result<T, DatabaseError> {
+        match &self.inner {
+            RowInner::Postgres(row) => row.try_get(name).map_err(|e| DatabaseError::RowMappingError(format!("{}: {}", name, e))),
+            #[cfg(test)]
+            RowInner::Memory(columns) => {
+                let column = columns.iter().find(|column| column.name == name).ok_or_else(|| DatabaseError::RowMappingError(format!("{}: カラムが存在しません", name)))?;
+                if !T::accepts(&column.type_) {
+                    return Err(DatabaseError::RowMappingError(format!("{}: {}型の値は読み取れません", name, column.type_)));
+                }
+                T::from_sql_nullable(&column.type_, column.raw.as_deref()).map_err(|e| DatabaseError::RowMappingError(format!("{}: {}", name, e)))
+            },
+        }
+    }
+
+    /// カラムの型。カラムが結果に含まれていなければ`None`
+    pub fn column_type(&self, name: &str) -> Option<&Type> {
+        match &self.inner {
+            RowInner::Postgres(row) => row.columns().iter().find(|column| column.name() == name).map(|column| column.type_()),
+            #[cfg(test)]
+            RowInner::Memory(columns) => columns.iter().find(|column| column.name == name).map(|column| &column.type_),
+        }
+    }
+}
+
+impl From<tokio_postgres::Row> for Row {
+    fn from(row: tokio_postgres::Row) -> Self {
+        Self { inner: RowInner::Postgres(row) }
+    }
+}
+
+#[cfg(test)]
+impl Row {
+    /// メモリ上で行を組み立てる。値は`ToSql`で指定した型のバイナリ形式にエンコードする
+    pub fn from_values(values: &[(&str, Type, &(dyn ToSql + Sync))]) -> Self {
+        let columns = values
+            .iter()
+            .map(|(name, type_, value)| {
+                let mut buf = bytes::BytesMut::new();
+                let raw = match value.to_sql_checked(type_, &mut buf).unwrap_or_else(|e| panic!("{}: {}", name, e)) {
+                    IsNull::Yes => None,
+                    IsNull::No => Some(buf.to_vec()),
+                };
+                MemoryColumn {
+                    name: name.to_string(),
+                    type_: type_.clone(),
+                    raw,
+                }
+            })
+            .collect();
+
+        Self { inner: RowInner::Memory(columns) }
+    }
+}
@@ -0,0 +1,167 @@
+use bb8::ManageConnection;
+use bb8_postgres::PostgresConnectionManager;
+use std::collections::HashMap;
+use std::future::Future;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tokio_postgres::{Client, Error, NoTls, Statement};
+
+static CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+static CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// プール全体でのステートメントキャッシュのヒット/ミス回数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatementCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl StatementCacheStats {
+    pub fn snapshot() -> Self {
+        Self {
+            hits: CACHE_HITS.load(Ordering::Relaxed),
+            misses: CACHE_MISSES.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// クエリ文字列をキーにしたステートメントのキャッシュ
+#[derive(Debug)]
+struct StatementCache<S> {
+    statements: Mutex<HashMap<String, S>>,
+}
+
+impl<S: Clone> StatementCache<S> {
+    fn new() -> Self {
+        Self {
+            statements: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// キャッシュにあればそれを返し、なければ`prepare`で準備してキャッシュする
+    async fn get_or_prepare<F, Fut, E>(&self, query: &str, prepare: F) -> Result<S, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<S, E>>,
+    {
+        // ロックはawaitをまたいで保持しない
+        if let Some(stmt) = self.cached(query) {
+            CACHE_HITS.fetch_add(1, Ordering::Relaxed);
+            return Ok(stmt);
+        }
+
+        CACHE_MISSES.fetch_add(1, Ordering::Relaxed);
+        let stmt = prepare().await?;
+        if let Ok(mut statements) = self.statements.lock() {
+            statements.insert(query.to_string(), stmt.clone());
+        }
+        Ok(stmt)
+    }
+
+    fn cached(&self, query: &str) -> Option<S> {
+        self.statements.lock().ok().and_then(|statements| statements.get(query).cloned())
+    }
+}
+
+/// プリペアドステートメントを接続単位でキャッシュするクライアント
+///
+/// `Statement`は準備した接続でしか使えないため、キャッシュは接続と同じ寿命で保持する。
+pub struct CachedClient {
+    client: Client,
+    statements: StatementCache<Statement>,
+    /// プールへ戻さずに破棄する (状態の分からない接続を再利用させない)
+    discarded: bool,
+}
+
+impl CachedClient {
+    fn new(client: Client) -> Self {
+        Self {
+            client,
+            statements: StatementCache::new(),
+            discarded: false,
+        }
+    }
+
+    /// 返却時にプールへ戻さず、接続を閉じる
+    pub fn discard(&mut self) {
+        self.discarded = true;
+    }
+
+    pub async fn prepare_cached(&self, query: &str) -> Result<Statement, Error> {
+        self.statements.get_or_prepare(query, || self.client.prepare(query)).await
+    }
+}
+
+impl Deref for CachedClient {
+    type Target = Client;
+
+    fn deref(&self) -> &Self::Target {
+        &self.client
+    }
+}
+
+/// `PostgresConnectionManager`をラップし、接続ごとに`CachedClient`を生成するマネージャ
+#[derive(Debug, Clone)]
+pub struct CachingConnectionManager {
+    inner: PostgresConnectionManager<NoTls>,
+}
+
+impl CachingConnectionManager {
+    pub fn new(inner: PostgresConnectionManager<NoTls>) -> Self {
+        Self { inner }
+    }
+}
+
+impl ManageConnection for CachingConnectionManager {
+    type Connection = CachedClient;
+    type Error = Error;
+
+    async fn connect(&self) -> Result<Self::Connection, Self::Error> {
+        self.inner.connect().await.map(CachedClient::new)
+    }
+
+    async fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
+        self.inner.is_valid(&mut conn.client).await
+    }
+
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        conn.discarded || self.inner.has_broken(&mut conn.client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+
+    #[tokio::test]
+    async fn prepares_each_query_once() {
+        let cache = StatementCache::new();
+        let mut prepared = Vec::new();
+
+        for query in ["SELECT 1", "SELECT 2", "SELECT 1"] {
+            let stmt = cache
+                .get_or_prepare(query, || {
+                    prepared.push(query);
+                    async move { Ok::<_, Infallible>(format!("stmt: {}", query)) }
+                })
+                .await
+                .unwrap();
+            assert_eq!(stmt, format!("stmt: {}", query));
+        }
+
+        assert_eq!(prepared, ["SELECT 1", "SELECT 2"]);
+    }
+
+    #[tokio::test]
+    async fn failed_preparation_is_not_cached() {
+        let cache = StatementCache::<String>::new();
+
+        let failed = cache.get_or_prepare("SELECT broken", || async { Err("syntax error") }).await;
+        assert_eq!(failed, Err("syntax error"));
+
+        let retried = cache.get_or_prepare("SELECT broken", || async { Ok::<_, &str>("stmt".to_string()) }).await;
+        assert_eq!(retried, Ok("stmt".to_string()));
+    }
+}
@@ -0,0 +1,95 @@
+use crate::database::client::{CopyStream, ExecuteQuery, QueryParams, QueryTransaction, RowStream};
+use crate::database::error::DatabaseError;
+use crate::database::pool::PooledClient;
+use crate::database::row::Row;
+use crate::database::statement_cache::CachedClient;
+use async_trait::async_trait;
+use log::warn;
+
+/// プールから借りた1接続を専有するトランザクション
+pub struct PooledTransaction {
+    client: Option<PooledClient>,
+}
+
+impl PooledTransaction {
+    pub async fn begin(client: PooledClient) -> Result<Self, DatabaseError> {
+        client.batch_execute("BEGIN").await.map_err(|e| DatabaseError::TransactionError(e.to_string()))?;
+        Ok(Self { client: Some(client) })
+    }
+
+    fn client(&self) -> Result<&CachedClient, DatabaseError> {
+        self.client.as_deref().ok_or_else(|| DatabaseError::TransactionError("トランザクションは既に終了しています".to_string()))
+    }
+
+    async fn finish(mut self, command: &str) -> Result<(), DatabaseError> {
+        // finishに入った時点でDropでのロールバックは不要になる
+        let client = self.client.take().ok_or_else(|| DatabaseError::TransactionError("トランザクションは既に終了しています".to_string()))?;
+        client.batch_execute(command).await.map_err(|e| DatabaseError::TransactionError(e.to_string()))
+    }
+}
+
+impl Drop for PooledTransaction {
+    fn drop(&mut self) {
+        let Some(mut client) = self.client.take() else {
+            return;
+        };
+        match tokio::runtime::Handle::try_current() {
+            // 非同期でロールバックしてから接続をプールへ返却する
+            Ok(handle) => {
+                handle.spawn(async move {
+                    if let Err(e) = client.batch_execute("ROLLBACK").await {
+                        warn!("破棄されたトランザクションのロールバックに失敗しました: {}", e);
+                        client.discard();
+                    }
+                });
+            },
+            // ランタイムの外ではロールバックを送れないため、接続ごと閉じてサーバー側でロールバックさせる
+            Err(_) => {
+                warn!("ランタイムの外で破棄されたトランザクションは、接続を閉じてロールバックします");
+                client.discard();
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl ExecuteQuery for PooledTransaction {
+    async fn query(&self, query: &str, params: QueryParams<'_>) -> Result<Vec<Row>, DatabaseError> {
+        self.client()?.query(query, params).await
+    }
+
+    async fn query_one(&self, query: &str, params: QueryParams<'_>) -> Result<Row, DatabaseError> {
+        self.client()?.query_one(query, params).await
+    }
+
+    async fn query_opt(&self, query: &str, params: QueryParams<'_>) -> Result<Option<Row>, DatabaseError> {
+        self.client()?.query_opt(query, params).await
+    }
+
+    async fn execute(&self, query: &str, params: QueryParams<'_>) -> Result<u64, DatabaseError> {
+        self.client()?.execute(query, params).await
+    }
+
+    async fn query_raw(&self, query: &str, params: QueryParams<'_>) -> Result<RowStream, DatabaseError> {
+        self.client()?.query_raw(query, params).await
+    }
+
+    async fn copy_in(&self, query: &str, data: CopyStream) -> Result<u64, DatabaseError> {
+        self.client()?.copy_in(query, data).await
+    }
+
+    async fn copy_out(&self, query: &str) -> Result<CopyStream, DatabaseError> {
+        self.client()?.copy_out(query).await
+    }
+}
+
+#[async_trait]
+impl QueryTransaction for PooledTransaction {
+    async fn commit(self: Box<Self>) -> Result<(), DatabaseError> {
+        self.finish("COMMIT").await
+    }
+
+    async fn rollback(self: Box<Self>) -> Result<(), DatabaseError> {
+        self.finish("ROLLBACK").await
+    }
+}
@@ -11,6 +11,9 @@ pub enum InitProcessError {
     #[error("インターフェイスの選択に失敗しました: {0}")]
     InterfaceSelectionError(String),
 
+    #[error("事前チェックで {0} 件のエラーが見つかりました")]
+    PreflightFailed(usize),
+
     #[error("データベース接続エラー: {0}")]
     DatabaseConnectionError(String),
 
@@ -19,4 +22,7 @@ pub enum InitProcessError {
 
     #[error("時間範囲の入力に失敗しました: {0}")]
     InvalidDateInput(String),
+
+    #[error("TUIの実行に失敗しました: {0}")]
+    TuiError(String),
 }
@@ -4,20 +4,20 @@ use thiserror::Error;
 pub enum InterfaceError {
     // select_device
     #[error("利用可能なネットワークインターフェースがありません")]
-    NoAvailableNetworkInterfaceError,
+    NoAvailableNetworkInterface,
 
-    #[error("指定されたDocker使用時のインターフェースが見つかりません: {0}")]
-    DockerInterfaceNotFound(String),
+    #[error("指定されたインターフェースが見つかりません: {0}")]
+    InterfaceNotFound(String),
+
+    #[error("インターフェース {0} は停止しています (ip link set {0} up で起動してください)")]
+    InterfaceDown(String),
+
+    #[error("インターフェース {0} へ送信するためのCAP_NET_RAWがありません")]
+    MissingCapNetRaw(String),
 
     #[error("標準出力のフラッシュに失敗しました: {0}")]
     StdoutFlushError(String),
 
     #[error("標準入力の行読み取りに失敗しました: {0}")]
     ReadLineError(String),
-
-    #[error("入力された値は無効なインターフェイス番号です")]
-    InvalidInterfaceNumberError(String),
-
-    #[error("入力された値は指定範囲外のインターフェイス番号です")]
-    OutOfRangeInterfaceNumberError,
 }
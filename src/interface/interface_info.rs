@@ -0,0 +1,177 @@
+use crate::interface::error::InterfaceError;
+use log::warn;
+use pnet::datalink::NetworkInterface;
+use pnet::util::MacAddr;
+use std::convert::Infallible;
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+
+/// capabilities(7)でのCAP_NET_RAWのビット番号
+const CAP_NET_RAW: u32 = 13;
+
+/// 非対話モードでのインターフェースの指定方法
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterfaceSelector {
+    Name(String),
+    /// OSのインターフェース番号 (ifindex)
+    Index(u32),
+    Mac(MacAddr),
+}
+
+impl FromStr for InterfaceSelector {
+    type Err = Infallible;
+
+    /// MACアドレス、数値 (ifindex)、名前の順に解釈する
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if let Ok(mac) = MacAddr::from_str(value) {
+            return Ok(Self::Mac(mac));
+        }
+        if let Ok(index) = value.parse() {
+            return Ok(Self::Index(index));
+        }
+        Ok(Self::Name(value.to_string()))
+    }
+}
+
+impl fmt::Display for InterfaceSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Name(name) => write!(f, "名前 {}", name),
+            Self::Index(index) => write!(f, "番号 {}", index),
+            Self::Mac(mac) => write!(f, "MACアドレス {}", mac),
+        }
+    }
+}
+
+impl InterfaceSelector {
+    pub fn matches(&self, interface: &NetworkInterface) -> bool {
+        match self {
+            Self::Name(name) => interface.name == *name,
+            Self::Index(index) => interface.index == *index,
+            Self::Mac(mac) => interface.mac == Some(*mac),
+        }
+    }
+}
+
+/// sysfsからインターフェースのMTUを読み取る (Linux以外や読み取れない場合は`None`)
+pub fn interface_mtu(name: &str) -> Option<u32> {
+    fs::read_to_string(format!("/sys/class/net/{}/mtu", name)).ok()?.trim().parse().ok()
+}
+
+/// 再生先として使えるか (起動中かつリンクが有効で、ループバックではなくアドレスを持つ)
+pub fn is_usable(interface: &NetworkInterface) -> bool {
+    interface.is_up() && interface.is_running() && !interface.is_loopback() && !interface.ips.is_empty()
+}
+
+/// 一覧表示用の1行の説明
+pub fn describe_interface(interface: &NetworkInterface) -> String {
+    let mut flags = vec![if interface.is_up() { "UP" } else { "DOWN" }];
+    if interface.is_running() {
+        flags.push("RUNNING");
+    }
+    if interface.is_loopback() {
+        flags.push("LOOPBACK");
+    }
+
+    let addresses = if interface.ips.is_empty() {
+        "アドレスなし".to_string()
+    } else {
+        interface.ips.iter().map(|ip| ip.to_string()).collect::<Vec<_>>().join(", ")
+    };
+
+    format!(
+        "{} <{}> MAC {} MTU {} [{}]",
+        interface.name,
+        flags.join(","),
+        interface.mac.map(|mac| mac.to_string()).unwrap_or_else(|| "-".to_string()),
+        interface_mtu(&interface.name).map(|mtu| mtu.to_string()).unwrap_or_else(|| "-".to_string()),
+        addresses
+    )
+}
+
+/// 実効ケーパビリティにCAP_NET_RAWが含まれるか (判定できない環境では`None`)
+pub fn has_cap_net_raw() -> Option<bool> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let effective = status.lines().find_map(|line| line.strip_prefix("CapEff:"))?;
+    let effective = u64::from_str_radix(effective.trim(), 16).ok()?;
+    Some(effective & (1 << CAP_NET_RAW) != 0)
+}
+
+/// 送信を始める前に、選ばれたインターフェースで送信できるかを確認する
+pub fn validate_interface(interface: &NetworkInterface) -> Result<(), InterfaceError> {
+    if !interface.is_up() {
+        return Err(InterfaceError::InterfaceDown(interface.name.clone()));
+    }
+    if has_cap_net_raw() == Some(false) {
+        return Err(InterfaceError::MissingCapNetRaw(interface.name.clone()));
+    }
+    if !interface.is_running() {
+        warn!("{} はリンクが確立していません", interface.name);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Linuxのif.hでの値
+    const IFF_UP: u32 = 0x1;
+    const IFF_LOOPBACK: u32 = 0x8;
+    const IFF_RUNNING: u32 = 0x40;
+
+    fn interface(name: &str, flags: u32, ips: &[&str]) -> NetworkInterface {
+        NetworkInterface {
+            name: name.to_string(),
+            description: String::new(),
+            index: 2,
+            mac: Some(MacAddr::new(0x02, 0, 0, 0, 0, 0x01)),
+            ips: ips.iter().map(|ip| ip.parse().unwrap()).collect(),
+            flags,
+        }
+    }
+
+    #[test]
+    fn parses_selector() {
+        assert_eq!(
+            "02:00:00:00:00:01".parse::<InterfaceSelector>(),
+            Ok(InterfaceSelector::Mac(MacAddr::new(0x02, 0, 0, 0, 0, 0x01)))
+        );
+        assert_eq!(" 2\n".parse::<InterfaceSelector>(), Ok(InterfaceSelector::Index(2)));
+        assert_eq!("eth0".parse::<InterfaceSelector>(), Ok(InterfaceSelector::Name("eth0".to_string())));
+    }
+
+    #[test]
+    fn selector_matches_by_each_key() {
+        let eth0 = interface("eth0", IFF_UP | IFF_RUNNING, &["192.0.2.1/24"]);
+        assert!(InterfaceSelector::Name("eth0".to_string()).matches(&eth0));
+        assert!(InterfaceSelector::Index(2).matches(&eth0));
+        assert!(InterfaceSelector::Mac(MacAddr::new(0x02, 0, 0, 0, 0, 0x01)).matches(&eth0));
+        assert!(!InterfaceSelector::Name("eth1".to_string()).matches(&eth0));
+        assert!(!InterfaceSelector::Index(3).matches(&eth0));
+    }
+
+    #[test]
+    fn filters_unusable_interfaces() {
+        assert!(is_usable(&interface("eth0", IFF_UP | IFF_RUNNING, &["192.0.2.1/24"])));
+        assert!(!is_usable(&interface("eth0", IFF_UP, &["192.0.2.1/24"])));
+        assert!(!is_usable(&interface("eth0", IFF_UP | IFF_RUNNING, &[])));
+        assert!(!is_usable(&interface("lo", IFF_UP | IFF_RUNNING | IFF_LOOPBACK, &["127.0.0.1/8"])));
+    }
+
+    #[test]
+    fn describes_flags_and_addresses() {
+        let description = describe_interface(&interface("pf-test0", IFF_UP | IFF_LOOPBACK, &["192.0.2.1/24", "2001:db8::1/64"]));
+        assert!(description.starts_with("pf-test0 <UP,LOOPBACK> MAC 02:00:00:00:00:01 MTU - ["));
+        assert!(description.ends_with("[192.0.2.1/24, 2001:db8::1/64]"));
+        assert!(describe_interface(&interface("pf-test0", 0, &[])).contains("<DOWN>"));
+    }
+
+    #[test]
+    fn rejects_interface_that_is_down() {
+        let result = validate_interface(&interface("eth0", 0, &["192.0.2.1/24"]));
+        assert!(matches!(result, Err(InterfaceError::InterfaceDown(name)) if name == "eth0"));
+    }
+}
@@ -1,4 +1,6 @@
 mod error;
+mod interface_info;
 mod select_interface;
 
+pub use interface_info::{describe_interface, has_cap_net_raw, interface_mtu, is_usable, validate_interface, InterfaceSelector};
 pub use select_interface::select_interface;
@@ -1,42 +1,52 @@
 use crate::interface::error::InterfaceError;
+use crate::interface::interface_info::{describe_interface, is_usable, validate_interface, InterfaceSelector};
 use log::info;
 use pnet::datalink::{self, NetworkInterface};
 use std::io::{self, Write};
 
-pub fn select_interface(docker_mode: bool, docker_interface_name: &str) -> Result<NetworkInterface, InterfaceError> {
+/// `selector`が指定されていればそのインターフェースを、なければ対話的に選ばせる
+///
+/// 一覧には既定で再生先として使えるものだけを表示し、`show_all`で全てを表示する。
+/// どちらの場合も送信できない状態のインターフェースはエラーにする。
+pub fn select_interface(selector: Option<&InterfaceSelector>, show_all: bool) -> Result<NetworkInterface, InterfaceError> {
     let interfaces = datalink::interfaces();
 
     if interfaces.is_empty() {
-        return Err(InterfaceError::NoAvailableNetworkInterfaceError);
+        return Err(InterfaceError::NoAvailableNetworkInterface);
     }
 
-    // Dockerモードの場合はインターフェイスの自動選択
-    if docker_mode {
-        info!("Docker Modeが有効な為、{}インターフェイスで自動実行されます。", docker_interface_name);
-        return if let Some(interface) = interfaces.iter().find(|interface| interface.name == docker_interface_name) {
-            Ok(interface.clone())
-        } else {
-            Err(InterfaceError::DockerInterfaceNotFound(docker_interface_name.to_string()))?
-        };
+    // 非対話モードでは一覧の絞り込みに関係なく指定されたものを探す
+    if let Some(selector) = selector {
+        let interface = interfaces.iter().find(|interface| selector.matches(interface)).ok_or_else(|| InterfaceError::InterfaceNotFound(selector.to_string()))?;
+        info!("指定されたインターフェースを使用します: {}", describe_interface(interface));
+        validate_interface(interface)?;
+        return Ok(interface.clone());
+    }
+
+    let candidates: Vec<&NetworkInterface> = interfaces.iter().filter(|interface| show_all || is_usable(interface)).collect();
+    if candidates.is_empty() {
+        return Err(InterfaceError::NoAvailableNetworkInterface);
     }
 
     // 通常モードの場合は対話的に選択
     println!("\n利用可能なネットワークインターフェース:");
-    for (idx, interface) in interfaces.iter().enumerate() {
-        println!("{}. {} ({})", idx + 1, interface.name, interface.description);
+    for interface in &candidates {
+        println!("{:>3}. {}", interface.index, describe_interface(interface));
+    }
+    if !show_all && candidates.len() < interfaces.len() {
+        println!("(停止中・ループバック・アドレスのないインターフェース {} 個は非表示)", interfaces.len() - candidates.len());
     }
 
-    print!("\nインターフェースを選択してください [1-{}]: ", interfaces.len());
+    print!("\nインターフェースを番号・名前・MACアドレスで選択してください: ");
     io::stdout().flush().map_err(|e| InterfaceError::StdoutFlushError(e.to_string()))?;
 
     let mut input = String::new();
     io::stdin().read_line(&mut input).map_err(|e| InterfaceError::ReadLineError(e.to_string()))?;
 
-    let selection = input.trim().parse::<usize>().map_err(|e| InterfaceError::InvalidInterfaceNumberError(e.to_string()))?;
+    let Ok(selector) = input.parse::<InterfaceSelector>();
+    let interface = candidates.into_iter().find(|interface| selector.matches(interface)).ok_or_else(|| InterfaceError::InterfaceNotFound(selector.to_string()))?;
 
-    if selection < 1 || selection > interfaces.len() {
-        return Err(InterfaceError::OutOfRangeInterfaceNumberError);
-    }
+    validate_interface(interface)?;
 
-    Ok(interfaces[selection - 1].clone())
+    Ok(interface.clone())
 }
@@ -1,5 +1,5 @@
+use crate::config::{PathStyle, Timezone};
 use crate::logger::error::LoggerError;
-use chrono::Local;
 use once_cell::sync::Lazy;
 use std::fs;
 use std::fs::{File, OpenOptions};
@@ -19,7 +19,8 @@ pub struct LogConfig {
     file: Option<Mutex<File>>,
     mode: OutputMode,
     file_path: Option<String>,
-    path_style: Option<String>,
+    path_style: PathStyle,
+    timezone: Timezone,
 }
 
 static LOGGER: Lazy<Mutex<LogConfig>> = Lazy::new(|| {
@@ -27,7 +28,8 @@ static LOGGER: Lazy<Mutex<LogConfig>> = Lazy::new(|| {
         file: None,
         mode: OutputMode::All,
         file_path: None,
-        path_style: None,
+        path_style: PathStyle::FilePath,
+        timezone: Timezone::Utc,
     })
 });
 
@@ -54,11 +56,12 @@ fn create_log_file(file_path: &str) -> Result<File, LoggerError> {
     }
 }
 
-pub fn set_idps_settings(mode: OutputMode, file_path: &str, path_style: &str) -> Result<(), LoggerError> {
+pub fn set_idps_settings(mode: OutputMode, file_path: &str, path_style: PathStyle, timezone: Timezone) -> Result<(), LoggerError> {
     if let Ok(mut logger) = LOGGER.lock() {
         logger.mode = mode;
         logger.file_path = Some(file_path.to_string());
-        logger.path_style = Some(path_style.to_string());
+        logger.path_style = path_style;
+        logger.timezone = timezone;
 
         if mode == OutputMode::FileOnly || mode == OutputMode::All {
             logger.file = create_log_file(file_path).ok().map(Mutex::new);
@@ -71,16 +74,10 @@ pub fn set_idps_settings(mode: OutputMode, file_path: &str, path_style: &str) ->
 }
 
 pub fn write_log(message: &str, log_file_path: &str, module_path: &str, line: u32) {
-    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S%.3f");
-
     if let Ok(logger) = LOGGER.lock() {
+        let timestamp = logger.timezone.now().format("%Y-%m-%d %H:%M:%S%.3f");
         if logger.file_path.is_some() {
-            let path_info = match logger.path_style.as_deref() {
-                Some("file_path") => log_file_path,
-                Some("module_path") => module_path,
-                Some("none") => "",
-                _ => log_file_path,
-            };
+            let path_info = logger.path_style.select(log_file_path, module_path);
 
             let final_log_message = format!("{} [IDPS] {}:{} - {}\n", timestamp, path_info, line, message);
 
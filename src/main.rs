@@ -1,57 +1,415 @@
+mod cli;
 mod config;
 mod database;
 mod error;
 mod interface;
 mod logger;
 mod packet;
+mod preflight;
+mod tui;
 mod utils;
 
-use crate::config::{AppConfig, DateTimeInput};
+use crate::cli::{AvailabilityArgs, Cli, Command, ConfigArgs, ConfigCommand, ExportArgs, HistoryArgs, ProfileArgs, ProfileCommand, ReplayArgs};
+use crate::config::{AppConfig, ConfigLayers, DateTimeInput};
 use crate::database::Database;
 use crate::error::InitProcessError;
-use crate::interface::select_interface;
+use crate::interface::{select_interface, InterfaceSelector};
 use crate::logger::setup_logger::setup_logger;
-use crate::packet::reader::PacketReader;
-use log::info;
+use crate::packet::availability::AvailabilityReport;
+use crate::packet::export::PacketExporter;
+use crate::packet::history::{ReplayHistory, ReplayRecorder};
+use crate::packet::model::ReplayRunParameters;
+use crate::packet::profile::ReplayProfiles;
+use crate::packet::reader::{BackendKind, DryRunOptions, LinkLayerOptions, PacketReader, PacketReaderError, ReplayMonitor, ReplayOptions, ReplayTarget};
+use crate::packet::repository::PacketRepository;
+use crate::preflight::{NetworkRequirement, PreflightReport};
+use crate::tui::{pick_interface, pick_range, run_dashboard, TerminalSession, TuiError};
+use chrono::{DateTime, Utc};
+use clap::Parser;
+use log::{error, info, warn};
+use pnet::datalink::NetworkInterface;
+use std::io::IsTerminal;
+use std::sync::Arc;
 
 #[tokio::main]
 async fn main() -> Result<(), InitProcessError> {
-    // 設定の読み込み
-    let config: AppConfig = AppConfig::new().map_err(|e| InitProcessError::ConfigurationError(e.to_string()))?;
+    let cli = Cli::parse().validate().unwrap_or_else(|e| e.exit());
+
+    // 設定の読み込み (既定値 → 設定ファイル → 環境変数 → --set の順に重ねる)
+    let layers = ConfigLayers::load(cli.config.as_deref(), &cli.settings).map_err(|e| InitProcessError::ConfigurationError(e.to_string()))?;
+
+    // 設定の確認はロガーとデータベースを使わずに行う
+    if let Some(Command::Config(args)) = &cli.command {
+        return run_config(&layers, args);
+    }
+
+    let config = AppConfig::from_layers(&layers).map_err(|e| InitProcessError::ConfigurationError(e.to_string()))?;
 
     // ロガーのセットアップ
-    setup_logger(config.logger_config).map_err(|e| InitProcessError::LoggerError(e.to_string()))?;
+    setup_logger(config.logger_config.clone(), config.time.timezone).map_err(|e| InitProcessError::LoggerError(e.to_string()))?;
 
     info!("loggerが正常にセットアップされました");
 
     // データベース接続
-    Database::connect(
-        &config.database.host,
-        config.database.port,
-        &config.database.user,
-        &config.database.password,
-        &config.database.database,
-    )
-    .await
-    .map_err(|e| InitProcessError::DatabaseConnectionError(e.to_string()))?;
+    // 事前チェックを行う場合は、その中で接続してスキーマまで確認する
+    // migrateはスキーマを直すためのコマンドなので、スキーマの確認では止めない
+    if cli.skip_preflight || matches!(cli.command, Some(Command::Migrate)) {
+        let password = config.database.password().map_err(|e| InitProcessError::DatabaseConnectionError(e.to_string()))?;
+        Database::connect(
+            &config.database.host,
+            config.database.port,
+            &config.database.user,
+            password.as_ref(),
+            &config.database.database,
+        )
+        .await
+        .map_err(|e| InitProcessError::DatabaseConnectionError(e.to_string()))?;
+    } else {
+        // 既存のインターフェースへ送信するのは再生のみ (ドライラン・TAPデバイス・pcapを除く)
+        // プロファイルの送信先はデータベースに接続するまで分からないため、その場合は送信時の確認に任せる
+        let network = (cli.command.is_none() && cli.replay.profile.is_none() && !cli.replay.dry_run && cli.replay.backend.uses_interface())
+            .then(|| network_requirement(&config, &cli.replay));
+        run_preflight(&config, network.as_ref()).await?;
+    }
 
     info!("データベースに接続できました: address:{}, port:{}", config.database.host, config.database.port);
 
-    // ネットワークインターフェースの選択
-    let interface = select_interface(config.network.docker_mode, &config.network.docker_interface_name).map_err(|e| InitProcessError::InterfaceSelectionError(e.to_string()))?;
+    match cli.command {
+        Some(Command::Export(args)) => run_export(&config, args).await?,
+        Some(Command::Availability(args)) => run_availability(&config, args).await?,
+        Some(Command::History(args)) => run_history(&config, args).await?,
+        Some(Command::Profile(args)) => run_profile(&config, args).await?,
+        Some(Command::Config(_)) => unreachable!("設定の確認はデータベースへの接続前に行う"),
+        Some(Command::Migrate) => run_migrate().await?,
+        None => run_replay(&config, apply_profile(cli.replay).await?).await?,
+    }
+
+    let cache_stats = Database::statement_cache_stats();
+    info!("ステートメントキャッシュ: ヒット {} 回, ミス {} 回", cache_stats.hits, cache_stats.misses);
+
+    Ok(())
+}
+
+async fn run_replay(config: &AppConfig, args: ReplayArgs) -> Result<(), InitProcessError> {
+    if args.tui {
+        return run_replay_tui(config, args).await;
+    }
 
-    info!("選択されたインターフェース: {}", interface.name);
+    // ネットワークインターフェースの選択 (ドライラン・TAPデバイス・pcapでは不要)
+    let target = match fixed_target(&args)? {
+        Some(target) => target,
+        None => {
+            let NetworkRequirement { selector, show_all } = network_requirement(config, &args);
+            if args.interface.is_none() && config.network.docker_mode {
+                info!("Docker Modeが有効な為、{}インターフェイスで自動実行されます。", config.network.docker_interface_name);
+            }
+            let interface = select_interface(selector.as_ref(), show_all).map_err(|e| InitProcessError::InterfaceSelectionError(e.to_string()))?;
+            info!("選択されたインターフェース: {}", interface.name);
+            interface_target(&args, interface)
+        },
+    };
 
     // 時間範囲の入力
-    let datetime_input = DateTimeInput::new().await.map_err(|e| InitProcessError::InvalidDateInput(e.to_string()))?;
+    let (start, end) = args.time_range.expressions();
+    let datetime_input = DateTimeInput::new(config.time.timezone, start.as_deref(), end.as_deref()).await.map_err(|e| InitProcessError::InvalidDateInput(e.to_string()))?;
+
+    info!("開始時刻: {}", config.time.timezone.localize(datetime_input.start_datetime()));
+    info!("終了時刻: {}", config.time.timezone.localize(datetime_input.end_datetime()));
 
-    info!("開始時刻: {}", datetime_input.start_datetime());
-    info!("終了時刻: {}", datetime_input.end_datetime());
+    let options = replay_options(config, &args)?;
 
     // パケット再生の実行
-    PacketReader::replay_packets(interface, datetime_input.start_datetime(), datetime_input.end_datetime())
+    replay_with_history(target, datetime_input.start_datetime(), datetime_input.end_datetime(), options, args.filter_summary())
         .await
         .map_err(|e| InitProcessError::TaskExecutionProcessError(e.to_string()))?;
 
     Ok(())
 }
+
+/// インターフェースと期間をTUIで選び、再生の進捗をダッシュボードに表示する
+async fn run_replay_tui(config: &AppConfig, args: ReplayArgs) -> Result<(), InitProcessError> {
+    let mut options = replay_options(config, &args)?;
+    let monitor = Arc::new(ReplayMonitor::default());
+    options.monitor = Some(monitor.clone());
+
+    // 期間がコマンドラインで指定されていればタイムラインでの選択は省略する
+    let (start, end) = args.time_range.expressions();
+    let range = match start {
+        Some(start) => {
+            let datetime_input = DateTimeInput::new(config.time.timezone, Some(&start), end.as_deref()).await.map_err(|e| InitProcessError::InvalidDateInput(e.to_string()))?;
+            Some((datetime_input.start_datetime(), datetime_input.end_datetime()))
+        },
+        None => None,
+    };
+
+    let target = fixed_target(&args)?;
+
+    let mut session = TerminalSession::start().map_err(|e| InitProcessError::TuiError(e.to_string()))?;
+    let result = async {
+        let target = match target {
+            Some(target) => target,
+            None => interface_target(&args, pick_interface(&mut session)?),
+        };
+        let (start_datetime, end_datetime) = match range {
+            Some(range) => range,
+            None => pick_range(&mut session, config.time.timezone).await?,
+        };
+
+        let replay = tokio::spawn(replay_with_history(target, start_datetime, end_datetime, options, args.filter_summary()));
+        run_dashboard(&mut session, monitor, replay).await
+    }
+    .await;
+    drop(session);
+
+    match result {
+        Err(TuiError::Cancelled) => {
+            info!("TUIでの操作がキャンセルされました");
+            Ok(())
+        },
+        result => result.map_err(|e| InitProcessError::TuiError(e.to_string())),
+    }
+}
+
+/// `--profile`で指定された、または対話的に選ばれたプロファイルの設定を再生の引数に反映する
+///
+/// 期間をコマンドラインで指定せずに端末から実行した場合は、保存済みのプロファイルから選ばせる。
+async fn apply_profile(args: ReplayArgs) -> Result<ReplayArgs, InitProcessError> {
+    let profile = match &args.profile {
+        Some(name) => ReplayProfiles::get(name).await.map_err(|e| InitProcessError::ConfigurationError(e.to_string()))?,
+        None if !args.tui && args.time_range.expressions().0.is_none() && std::io::stdin().is_terminal() => {
+            // プロファイルを読めなくても通常の対話的な入力で再生できるようにする
+            let profiles = match ReplayProfiles::load().await {
+                Ok(profiles) if !profiles.is_empty() => profiles,
+                Ok(_) => return Ok(args),
+                Err(e) => {
+                    warn!("{}", e);
+                    return Ok(args);
+                },
+            };
+            match profiles.prompt().map_err(|e| InitProcessError::ConfigurationError(e.to_string()))? {
+                Some(profile) => profile,
+                None => return Ok(args),
+            }
+        },
+        None => return Ok(args),
+    };
+
+    info!("プロファイル {} を使用します", profile.name);
+    let cli =
+        Cli::parse_with_profile(&profile.arguments).map_err(|e| InitProcessError::ConfigurationError(format!("プロファイル {} の引数を解析できません: {}", profile.name, e)))?;
+    Ok(cli.replay)
+}
+
+/// 再生を実行し、設定と結果を`replay_runs`へ記録する (記録に失敗しても再生は行う)
+async fn replay_with_history(target: ReplayTarget, start: DateTime<Utc>, end: DateTime<Utc>, mut options: ReplayOptions, filters: String) -> Result<(), PacketReaderError> {
+    let monitor = options.monitor.get_or_insert_with(Default::default).clone();
+    let parameters = ReplayRunParameters {
+        range_start: start,
+        range_end: end,
+        backend: target.backend_name().to_string(),
+        destination: target.destination(),
+        filters,
+        speed: options.pacing.to_string(),
+    };
+    let recorder = match ReplayRecorder::begin(&parameters).await {
+        Ok(recorder) => Some(recorder),
+        Err(e) => {
+            warn!("{}", e);
+            None
+        },
+    };
+
+    let result = PacketReader::replay_packets(target, start, end, options).await;
+
+    if let Some(recorder) = recorder {
+        let error = result.as_ref().err().map(|e| e.to_string());
+        if let Err(e) = recorder.finish(&monitor.snapshot(), error.as_deref()).await {
+            warn!("{}", e);
+        }
+    }
+    result
+}
+
+/// 時間のかかる処理を始める前に、実行に必要な条件をまとめて確認する
+async fn run_preflight(config: &AppConfig, network: Option<&NetworkRequirement>) -> Result<(), InitProcessError> {
+    let report = PreflightReport::run(config, network).await;
+
+    if report.has_errors() {
+        error!("{}", report.render());
+        return Err(InitProcessError::PreflightFailed(report.error_count()));
+    }
+    if report.is_clean() {
+        info!("事前チェックに問題はありませんでした");
+    } else {
+        warn!("{}", report.render());
+    }
+
+    Ok(())
+}
+
+/// 再生の送信先の決め方
+///
+/// コマンドラインでの指定 > Docker Modeでの自動選択 > 対話的な選択 の順。
+/// TUIではインターフェースを画面上で選び、非表示の候補も切り替えて表示できる。
+fn network_requirement(config: &AppConfig, args: &ReplayArgs) -> NetworkRequirement {
+    if args.tui {
+        return NetworkRequirement { selector: None, show_all: true };
+    }
+
+    let selector = match args.interface.clone() {
+        Some(selector) => Some(selector),
+        None if config.network.docker_mode => Some(InterfaceSelector::Name(config.network.docker_interface_name.clone())),
+        None => None,
+    };
+    NetworkRequirement {
+        selector,
+        show_all: args.all_interfaces,
+    }
+}
+
+/// インターフェースを選ばずに決まる送信先 (ドライラン・TAPデバイス・pcap)
+fn fixed_target(args: &ReplayArgs) -> Result<Option<ReplayTarget>, InitProcessError> {
+    if args.dry_run {
+        return Ok(Some(ReplayTarget::DryRun(DryRunOptions {
+            output: args.dry_run_output.clone(),
+        })));
+    }
+    match (args.backend, &args.interface, &args.output) {
+        (BackendKind::Tap, Some(InterfaceSelector::Name(name)), _) => Ok(Some(ReplayTarget::Tap(name.clone()))),
+        (BackendKind::Tap, _, _) => Err(InitProcessError::ConfigurationError("TAPデバイスは --interface で名前を指定してください".to_string())),
+        (BackendKind::Pcap, _, Some(path)) => Ok(Some(ReplayTarget::PcapFile(path.clone()))),
+        (BackendKind::Pcap, _, None) => Err(InitProcessError::ConfigurationError("pcapの書き出し先を --output で指定してください".to_string())),
+        (BackendKind::Pnet | BackendKind::AfPacket | BackendKind::TxRing | BackendKind::Sendmmsg | BackendKind::TxTime, _, _) => Ok(None),
+    }
+}
+
+fn interface_target(args: &ReplayArgs, interface: NetworkInterface) -> ReplayTarget {
+    match args.backend {
+        BackendKind::AfPacket => ReplayTarget::AfPacket(interface),
+        BackendKind::TxRing => ReplayTarget::TxRing(interface),
+        BackendKind::Sendmmsg => ReplayTarget::Sendmmsg(interface),
+        BackendKind::TxTime => ReplayTarget::TxTime(interface),
+        BackendKind::Pnet | BackendKind::Tap | BackendKind::Pcap => ReplayTarget::Pnet(interface),
+    }
+}
+
+fn replay_options(config: &AppConfig, args: &ReplayArgs) -> Result<ReplayOptions, InitProcessError> {
+    // 非Ethernetのパケットを送信する際のL2設定
+    let link_options = LinkLayerOptions {
+        src_mac: config.network.replay_src_mac,
+        dst_mac: config.network.replay_dst_mac,
+        output: config.network.link_output,
+        mtu: config.network.mtu,
+    };
+
+    Ok(ReplayOptions {
+        fetch_concurrency: args.fetch_concurrency as usize,
+        link: link_options,
+        transforms: args.transform_pipeline().map_err(|e| InitProcessError::ConfigurationError(e.to_string()))?,
+        impairment: args.impairment.impairment_options(),
+        timezone: config.time.timezone,
+        pacing: args.pacing(),
+        preload: args.preload,
+        sender: args.sender_thread_options(),
+        monitor: None,
+        legacy_row_order: config.database.legacy_row_order,
+    })
+}
+
+async fn run_export(config: &AppConfig, args: ExportArgs) -> Result<(), InitProcessError> {
+    // 時間範囲の入力
+    let (start, end) = args.time_range.expressions();
+    let datetime_input = DateTimeInput::new(config.time.timezone, start.as_deref(), end.as_deref()).await.map_err(|e| InitProcessError::InvalidDateInput(e.to_string()))?;
+
+    info!("開始時刻: {}", config.time.timezone.localize(datetime_input.start_datetime()));
+    info!("終了時刻: {}", config.time.timezone.localize(datetime_input.end_datetime()));
+
+    let transforms = args.sanitize.transform_pipeline().map_err(|e| InitProcessError::ConfigurationError(e.to_string()))?;
+
+    PacketExporter::export_to_pcap(
+        &args.output,
+        datetime_input.start_datetime(),
+        datetime_input.end_datetime(),
+        &transforms,
+        config.database.legacy_row_order,
+    )
+    .await
+    .map_err(|e| InitProcessError::TaskExecutionProcessError(e.to_string()))?;
+
+    Ok(())
+}
+
+async fn run_migrate() -> Result<(), InitProcessError> {
+    let added = PacketRepository::from_database().add_capture_sequence().await.map_err(|e| InitProcessError::TaskExecutionProcessError(e.to_string()))?;
+    if added {
+        info!("packets テーブルに capture_seq カラムを追加しました (既存の行には格納位置の順に採番しました)");
+    } else {
+        info!("packets テーブルには既に capture_seq カラムがあります");
+    }
+    Ok(())
+}
+
+async fn run_availability(config: &AppConfig, args: AvailabilityArgs) -> Result<(), InitProcessError> {
+    let (start, end) = args.time_range.expressions();
+    let (start_datetime, end_datetime) = match start {
+        Some(start) => {
+            let datetime_input = DateTimeInput::new(config.time.timezone, Some(&start), end.as_deref()).await.map_err(|e| InitProcessError::InvalidDateInput(e.to_string()))?;
+            (datetime_input.start_datetime(), datetime_input.end_datetime())
+        },
+        None => PacketRepository::from_database().get_packet_time_range().await.map_err(|e| InitProcessError::TaskExecutionProcessError(e.to_string()))?,
+    };
+
+    let report = AvailabilityReport::collect(start_datetime, end_datetime, args.bucket, args.top).await.map_err(|e| InitProcessError::TaskExecutionProcessError(e.to_string()))?;
+    println!("{}", report.render(config.time.timezone));
+
+    Ok(())
+}
+
+async fn run_history(config: &AppConfig, args: HistoryArgs) -> Result<(), InitProcessError> {
+    let output = match args.id {
+        Some(id) => ReplayHistory::single(id).await.map(|history| history.render_detail(config.time.timezone)),
+        None => ReplayHistory::recent(args.limit).await.map(|history| history.render_list(config.time.timezone)),
+    }
+    .map_err(|e| InitProcessError::TaskExecutionProcessError(e.to_string()))?;
+    print!("{}", output);
+
+    Ok(())
+}
+
+fn run_config(layers: &ConfigLayers, args: &ConfigArgs) -> Result<(), InitProcessError> {
+    match args.command {
+        ConfigCommand::Show => {
+            print!("{}", layers.render());
+            // 表示した設定に誤りがあれば併せて報告する
+            AppConfig::from_layers(layers).map_err(|e| InitProcessError::ConfigurationError(e.to_string()))?;
+        },
+    }
+
+    Ok(())
+}
+
+async fn run_profile(config: &AppConfig, args: ProfileArgs) -> Result<(), InitProcessError> {
+    match args.command {
+        ProfileCommand::List => {
+            let profiles = ReplayProfiles::load().await.map_err(|e| InitProcessError::TaskExecutionProcessError(e.to_string()))?;
+            print!("{}", profiles.render_list());
+        },
+        ProfileCommand::Show { name } => {
+            let profile = ReplayProfiles::get(&name).await.map_err(|e| InitProcessError::TaskExecutionProcessError(e.to_string()))?;
+            print!("{}", ReplayProfiles::render_detail(&profile, config.time.timezone));
+        },
+        ProfileCommand::Save(save) => {
+            Cli::validate_profile_arguments(&save.arguments).map_err(|e| InitProcessError::ConfigurationError(format!("プロファイルの引数が不正です: {}", e)))?;
+            ReplayProfiles::save(&save.name, save.description.as_deref(), &save.arguments, save.replace)
+                .await
+                .map_err(|e| InitProcessError::TaskExecutionProcessError(e.to_string()))?;
+            println!("プロファイル {} を保存しました", save.name);
+        },
+        ProfileCommand::Delete { name } => {
+            ReplayProfiles::delete(&name).await.map_err(|e| InitProcessError::TaskExecutionProcessError(e.to_string()))?;
+            println!("プロファイル {} を削除しました", name);
+        },
+    }
+
+    Ok(())
+}
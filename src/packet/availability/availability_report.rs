@@ -0,0 +1,270 @@
+use crate::config::Timezone;
+use crate::packet::availability::error::AvailabilityError;
+use crate::packet::model::{TopTalker, TrafficBucket};
+use crate::packet::repository::PacketRepository;
+use crate::utils::format::{format_bytes, format_duration};
+use chrono::{DateTime, Duration, Utc};
+use log::warn;
+use std::fmt::Write;
+
+/// ヒストグラムのバーの最大幅 (文字数)
+const BAR_WIDTH: usize = 40;
+/// バケット幅を自動で決める際の目安のバケット数
+const TARGET_BUCKETS: i64 = 48;
+/// 1/8刻みのバーの端
+const PARTIAL_BLOCKS: [&str; 8] = ["", "▏", "▎", "▍", "▌", "▋", "▊", "▉"];
+
+/// パケットが1つも存在しない区間
+#[derive(Debug, Clone)]
+pub struct TrafficGap {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+/// 期間を選ぶ前に確認するためのデータの分布
+pub struct AvailabilityReport {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub bucket_width: Duration,
+    pub buckets: Vec<TrafficBucket>,
+    pub gaps: Vec<TrafficGap>,
+    pub top_talkers: Vec<TopTalker>,
+}
+
+impl AvailabilityReport {
+    /// `bucket_width`を省略した場合は期間の長さから決める
+    pub async fn collect(start: DateTime<Utc>, end: DateTime<Utc>, bucket_width: Option<Duration>, top: usize) -> Result<Self, AvailabilityError> {
+        if end <= start {
+            return Err(AvailabilityError::InvalidRange(format!("{} - {}", start, end)));
+        }
+        let bucket_width = bucket_width.unwrap_or_else(|| Self::auto_bucket_width(end - start));
+        if bucket_width <= Duration::zero() {
+            return Err(AvailabilityError::InvalidRange("バケット幅は正の値である必要があります".to_string()));
+        }
+
+        let repository = PacketRepository::from_database();
+        let buckets = repository.get_traffic_histogram(start, end, bucket_width).await.map_err(|e| AvailabilityError::FetchError(e.to_string()))?;
+
+        // src_ipカラムのないテーブルでも分布は表示できるようにする
+        let top_talkers = match repository.get_top_talkers(start, end, top as i64).await {
+            Ok(top_talkers) => top_talkers,
+            Err(e) => {
+                warn!("送信元ごとの集計に失敗しました: {}", e);
+                Vec::new()
+            },
+        };
+
+        let gaps = find_gaps(start, end, bucket_width, &buckets);
+        Ok(Self {
+            start,
+            end,
+            bucket_width,
+            buckets,
+            gaps,
+            top_talkers,
+        })
+    }
+
+    /// バケット数が`TARGET_BUCKETS`以下になる最小のきりのよい幅
+    pub fn auto_bucket_width(span: Duration) -> Duration {
+        Self::bucket_width_for(span, TARGET_BUCKETS)
+    }
+
+    /// バケット数が`target_buckets`以下になる最小のきりのよい幅
+    pub fn bucket_width_for(span: Duration, target_buckets: i64) -> Duration {
+        const CANDIDATES: [i64; 16] = [
+            1,
+            5,
+            10,
+            30,
+            60,
+            300,
+            600,
+            900,
+            1800,
+            3600,
+            3 * 3600,
+            6 * 3600,
+            12 * 3600,
+            86400,
+            7 * 86400,
+            30 * 86400,
+        ];
+
+        CANDIDATES
+            .iter()
+            .map(|seconds| Duration::seconds(*seconds))
+            .find(|width| span.num_seconds() / width.num_seconds() <= target_buckets.max(1))
+            .unwrap_or_else(|| Duration::seconds(CANDIDATES[CANDIDATES.len() - 1]))
+    }
+
+    /// ターミナル表示用に整形する
+    pub fn render(&self, timezone: Timezone) -> String {
+        let mut out = String::new();
+        let format_time = |datetime: DateTime<Utc>| timezone.localize(datetime).format("%Y-%m-%d %H:%M:%S %:z").to_string();
+
+        let _ = writeln!(
+            out,
+            "期間: {} 〜 {} (バケット幅 {})",
+            format_time(self.start),
+            format_time(self.end),
+            format_duration(self.bucket_width)
+        );
+
+        if self.buckets.is_empty() {
+            let _ = writeln!(out, "この期間にパケットはありません");
+            return out;
+        }
+
+        let max_packets = self.buckets.iter().map(|bucket| bucket.packets).max().unwrap_or(1).max(1);
+        let total_packets: i64 = self.buckets.iter().map(|bucket| bucket.packets).sum();
+        let total_bytes: i64 = self.buckets.iter().map(|bucket| bucket.bytes).sum();
+
+        let _ = writeln!(out, "\nパケット数の分布:");
+        for bucket in &self.buckets {
+            let _ = writeln!(
+                out,
+                "{} │{:<width$}│ {:>10} pkts {:>10}",
+                format_time(bucket.start),
+                bar(bucket.packets, max_packets),
+                bucket.packets,
+                format_bytes(bucket.bytes),
+                width = BAR_WIDTH
+            );
+        }
+        let _ = writeln!(out, "合計: {} pkts / {}", total_packets, format_bytes(total_bytes));
+
+        if self.gaps.is_empty() {
+            let _ = writeln!(out, "\nパケットの欠損区間はありません");
+        } else {
+            let _ = writeln!(out, "\nパケットの欠損区間:");
+            for gap in &self.gaps {
+                let _ = writeln!(out, "  {} 〜 {} ({})", format_time(gap.start), format_time(gap.end), format_duration(gap.end - gap.start));
+            }
+        }
+
+        if !self.top_talkers.is_empty() {
+            let _ = writeln!(out, "\n送信元アドレスの上位:");
+            for (rank, talker) in self.top_talkers.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    "  {:>2}. {:<39} {:>10} pkts {:>10}",
+                    rank + 1,
+                    talker.address,
+                    talker.packets,
+                    format_bytes(talker.bytes)
+                );
+            }
+        }
+
+        out
+    }
+}
+
+/// 空のバケットが続く区間を欠損として返す (期間の先頭と末尾も含む)
+fn find_gaps(start: DateTime<Utc>, end: DateTime<Utc>, bucket_width: Duration, buckets: &[TrafficBucket]) -> Vec<TrafficGap> {
+    let mut gaps = Vec::new();
+    // 直前にパケットが存在した区間の終わり
+    let mut covered_until = start;
+
+    for bucket in buckets {
+        if bucket.start - covered_until >= bucket_width {
+            gaps.push(TrafficGap {
+                start: covered_until,
+                end: bucket.start,
+            });
+        }
+        covered_until = covered_until.max(bucket.start + bucket_width);
+    }
+
+    if end - covered_until >= bucket_width {
+        gaps.push(TrafficGap { start: covered_until, end });
+    }
+
+    gaps
+}
+
+fn bar(value: i64, max: i64) -> String {
+    let eighths = (value as f64 / max as f64 * (BAR_WIDTH * 8) as f64).round() as usize;
+    let mut bar = "█".repeat(eighths / 8);
+    bar.push_str(PARTIAL_BLOCKS[eighths % 8]);
+    // 値が0でなければ最低限の幅を表示する
+    if bar.is_empty() && value > 0 {
+        bar.push_str(PARTIAL_BLOCKS[1]);
+    }
+    bar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 1, 0, minute, 0).unwrap()
+    }
+
+    fn bucket(minute: u32, packets: i64) -> TrafficBucket {
+        TrafficBucket {
+            start: at(minute),
+            packets,
+            bytes: packets * 100,
+        }
+    }
+
+    fn gap_minutes(gaps: &[TrafficGap]) -> Vec<(i64, i64)> {
+        gaps.iter().map(|gap| ((gap.start - at(0)).num_minutes(), (gap.end - at(0)).num_minutes())).collect()
+    }
+
+    #[test]
+    fn finds_gaps_including_leading_and_trailing() {
+        let buckets = [bucket(2, 5), bucket(3, 1), bucket(7, 2)];
+        let gaps = find_gaps(at(0), at(10), Duration::minutes(1), &buckets);
+        assert_eq!(gap_minutes(&gaps), [(0, 2), (4, 7), (8, 10)]);
+    }
+
+    #[test]
+    fn contiguous_buckets_have_no_gaps() {
+        let buckets: Vec<_> = (0..10).map(|minute| bucket(minute, 1)).collect();
+        assert!(find_gaps(at(0), at(10), Duration::minutes(1), &buckets).is_empty());
+        // 1バケット幅に満たない末尾の余りは欠損としない
+        assert!(find_gaps(at(0), at(10) + Duration::seconds(30), Duration::minutes(1), &buckets).is_empty());
+    }
+
+    #[test]
+    fn picks_round_bucket_width() {
+        assert_eq!(AvailabilityReport::auto_bucket_width(Duration::seconds(30)), Duration::seconds(1));
+        assert_eq!(AvailabilityReport::auto_bucket_width(Duration::hours(1)), Duration::minutes(5));
+        assert_eq!(AvailabilityReport::auto_bucket_width(Duration::days(1)), Duration::minutes(30));
+        assert_eq!(AvailabilityReport::auto_bucket_width(Duration::days(10000)), Duration::days(30));
+    }
+
+    #[test]
+    fn formats_bars() {
+        assert_eq!(bar(10, 10).chars().count(), BAR_WIDTH);
+        assert_eq!(bar(1, 1000), PARTIAL_BLOCKS[1]);
+        assert_eq!(bar(0, 10), "");
+    }
+
+    #[test]
+    fn renders_histogram_gaps_and_talkers() {
+        let report = AvailabilityReport {
+            start: at(0),
+            end: at(3),
+            bucket_width: Duration::minutes(1),
+            buckets: vec![bucket(0, 4), bucket(1, 2)],
+            gaps: vec![TrafficGap { start: at(2), end: at(3) }],
+            top_talkers: vec![TopTalker {
+                address: "192.0.2.1".parse().unwrap(),
+                packets: 6,
+                bytes: 600,
+            }],
+        };
+
+        let rendered = report.render(Timezone::Utc);
+        assert!(rendered.starts_with("期間: 2024-05-01 00:00:00 +00:00 〜 2024-05-01 00:03:00 +00:00 (バケット幅 1m)"));
+        assert!(rendered.contains("合計: 6 pkts / 600 B"));
+        assert!(rendered.contains("2024-05-01 00:02:00 +00:00 〜 2024-05-01 00:03:00 +00:00 (1m)"));
+        assert!(rendered.contains(" 1. 192.0.2.1"));
+    }
+}
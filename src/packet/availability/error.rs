@@ -0,0 +1,10 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum AvailabilityError {
+    #[error("パケットの集計に失敗しました: {0}")]
+    FetchError(String),
+
+    #[error("集計する期間が不正です: {0}")]
+    InvalidRange(String),
+}
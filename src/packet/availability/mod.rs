@@ -0,0 +1,4 @@
+mod availability_report;
+mod error;
+
+pub use availability_report::AvailabilityReport;
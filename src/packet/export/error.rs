@@ -0,0 +1,10 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("出力ファイルの書き込みに失敗しました: {0}")]
+    FileWriteError(String),
+
+    #[error("パケットの取得に失敗しました: {0}")]
+    FetchError(String),
+}
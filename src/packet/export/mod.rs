@@ -0,0 +1,6 @@
+mod error;
+mod packet_exporter;
+mod pcap_writer;
+
+pub use packet_exporter::PacketExporter;
+pub(crate) use pcap_writer::PcapWriter;
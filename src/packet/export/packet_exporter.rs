@@ -0,0 +1,67 @@
+use crate::packet::export::error::ExportError;
+use crate::packet::export::pcap_writer::PcapWriter;
+use crate::packet::model::LinkType;
+use crate::packet::reader::{LinkConverter, LinkLayerOptions, OutboundFrame};
+use crate::packet::repository::PacketRepository;
+use crate::packet::transform::TransformPipeline;
+use chrono::{DateTime, Utc};
+use log::{error, info};
+use pnet::util::MacAddr;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+pub struct PacketExporter;
+
+impl PacketExporter {
+    /// 期間内のパケットをEthernetに揃えてpcapへ書き出し、書き出した件数を返す
+    ///
+    /// 変換 (サニタイズ等) は再生時と同じくEthernetフレームに対して適用する。
+    /// `legacy_row_order`は`capture_seq`のないテーブルを行の格納位置の順で読む旧方式を許可する。
+    pub async fn export_to_pcap(
+        path: &Path,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        transforms: &TransformPipeline,
+        legacy_row_order: bool,
+    ) -> Result<usize, ExportError> {
+        let packets = PacketRepository::from_database()
+            .with_legacy_row_order(legacy_row_order)
+            .get_packets_in_timerange(start_time, end_time)
+            .await
+            .map_err(|e| ExportError::FetchError(e.to_string()))?;
+        info!("{}個のパケットを取得しました", packets.len());
+
+        let file = File::create(path).map_err(|e| ExportError::FileWriteError(format!("{}: {}", path.display(), e)))?;
+        let mut writer = PcapWriter::new(BufWriter::new(file), LinkType::LINKTYPE_ETHERNET, PcapWriter::<BufWriter<File>>::DEFAULT_SNAP_LEN)
+            .map_err(|e| ExportError::FileWriteError(e.to_string()))?;
+
+        let converter = LinkConverter::new(LinkLayerOptions::default(), MacAddr::zero());
+        let mut written = 0;
+
+        for packet in &packets {
+            let mut frame = match converter.convert(packet) {
+                Ok(OutboundFrame::Ethernet(frame)) => frame.into_owned(),
+                Ok(OutboundFrame::Ipv4 { .. }) => continue,
+                Err(e) => {
+                    error!("パケットをEthernetに変換できませんでした ({:?}): {}", packet.link_type, e);
+                    continue;
+                },
+            };
+
+            if let Err(e) = transforms.apply(&mut frame) {
+                error!("フレームの変換に失敗しました: {}", e);
+                continue;
+            }
+
+            // 変換で伸縮した分だけ元の長さも合わせる
+            let original_length = (packet.original_length + frame.len()).saturating_sub(packet.captured_length());
+            writer.write_packet(packet.timestamp, &frame, original_length).map_err(|e| ExportError::FileWriteError(e.to_string()))?;
+            written += 1;
+        }
+
+        writer.flush().map_err(|e| ExportError::FileWriteError(e.to_string()))?;
+        info!("{}個のパケットを書き出しました: {}", written, path.display());
+        Ok(written)
+    }
+}
@@ -0,0 +1,43 @@
+use chrono::{DateTime, Utc};
+use std::io::{self, Write};
+
+/// libpcap形式 (マイクロ秒精度) のファイルライター
+pub struct PcapWriter<W: Write> {
+    writer: W,
+    snap_len: u32,
+}
+
+impl<W: Write> PcapWriter<W> {
+    const MAGIC_MICROSECOND: u32 = 0xa1b2_c3d4;
+    const VERSION_MAJOR: u16 = 2;
+    const VERSION_MINOR: u16 = 4;
+    pub const DEFAULT_SNAP_LEN: u32 = 262_144;
+
+    pub fn new(mut writer: W, link_type: u16, snap_len: u32) -> io::Result<Self> {
+        writer.write_all(&Self::MAGIC_MICROSECOND.to_le_bytes())?;
+        writer.write_all(&Self::VERSION_MAJOR.to_le_bytes())?;
+        writer.write_all(&Self::VERSION_MINOR.to_le_bytes())?;
+        // thiszone / sigfigs
+        writer.write_all(&0i32.to_le_bytes())?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(&snap_len.to_le_bytes())?;
+        writer.write_all(&u32::from(link_type).to_le_bytes())?;
+
+        Ok(Self { writer, snap_len })
+    }
+
+    pub fn write_packet(&mut self, timestamp: DateTime<Utc>, data: &[u8], original_length: usize) -> io::Result<()> {
+        let captured = &data[..data.len().min(self.snap_len as usize)];
+        let original_length = original_length.max(data.len());
+
+        self.writer.write_all(&(timestamp.timestamp() as u32).to_le_bytes())?;
+        self.writer.write_all(&timestamp.timestamp_subsec_micros().to_le_bytes())?;
+        self.writer.write_all(&(captured.len() as u32).to_le_bytes())?;
+        self.writer.write_all(&u32::try_from(original_length).unwrap_or(u32::MAX).to_le_bytes())?;
+        self.writer.write_all(captured)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum HistoryError {
+    #[error("再生の履歴を記録できませんでした: {0}")]
+    RecordError(String),
+
+    #[error("再生の履歴を取得できませんでした: {0}")]
+    FetchError(String),
+
+    #[error("指定された再生の履歴がありません: ID {0}")]
+    NotFound(i64),
+}
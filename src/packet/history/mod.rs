@@ -0,0 +1,6 @@
+mod error;
+mod replay_history;
+mod replay_recorder;
+
+pub use replay_history::ReplayHistory;
+pub use replay_recorder::ReplayRecorder;
@@ -0,0 +1,202 @@
+use crate::config::Timezone;
+use crate::packet::history::error::HistoryError;
+use crate::packet::model::ReplayRun;
+use crate::packet::repository::ReplayRunRepository;
+use crate::utils::format::{format_bytes, format_duration};
+use chrono::{DateTime, Utc};
+use std::fmt::Write;
+
+/// 過去の再生の一覧と詳細の表示
+pub struct ReplayHistory {
+    runs: Vec<ReplayRun>,
+}
+
+impl ReplayHistory {
+    /// 新しい順に`limit`件
+    pub async fn recent(limit: usize) -> Result<Self, HistoryError> {
+        let repository = ReplayRunRepository::from_database();
+        repository.ensure_table().await.map_err(|e| HistoryError::FetchError(e.to_string()))?;
+        let runs = repository.list_runs(limit as i64).await.map_err(|e| HistoryError::FetchError(e.to_string()))?;
+        Ok(Self { runs })
+    }
+
+    pub async fn single(id: i64) -> Result<Self, HistoryError> {
+        let repository = ReplayRunRepository::from_database();
+        repository.ensure_table().await.map_err(|e| HistoryError::FetchError(e.to_string()))?;
+        let run = repository.get_run(id).await.map_err(|e| HistoryError::FetchError(e.to_string()))?.ok_or(HistoryError::NotFound(id))?;
+        Ok(Self { runs: vec![run] })
+    }
+
+    /// 1行1件の一覧
+    pub fn render_list(&self, timezone: Timezone) -> String {
+        let mut out = String::new();
+        if self.runs.is_empty() {
+            let _ = writeln!(out, "再生の履歴はありません");
+            return out;
+        }
+
+        let format_time = |datetime: DateTime<Utc>| timezone.localize(datetime).format("%Y-%m-%d %H:%M:%S").to_string();
+        let _ = writeln!(
+            out,
+            "{:>6}  {}  {}  {}  {}  {}  {}",
+            "ID",
+            pad("開始", 19),
+            pad("状態", 6),
+            pad("期間", 42),
+            pad("送信先", 28),
+            pad_left("送信/総数", 13),
+            pad_left("エラー", 6)
+        );
+        for run in &self.runs {
+            let destination = match &run.parameters.destination {
+                Some(destination) => format!("{}:{}", run.parameters.backend, destination),
+                None => run.parameters.backend.clone(),
+            };
+            let (sent, errors) = match &run.statistics {
+                Some(statistics) => (format!("{}/{}", statistics.packets_sent, statistics.packets_total), statistics.errors.to_string()),
+                None => ("-".to_string(), "-".to_string()),
+            };
+            let _ = writeln!(
+                out,
+                "{:>6}  {}  {}  {} 〜 {}  {}  {:>13}  {:>6}",
+                run.id,
+                format_time(run.started_at),
+                pad(&run.status.to_string(), 6),
+                format_time(run.parameters.range_start),
+                format_time(run.parameters.range_end),
+                pad(&destination, 28),
+                sent,
+                errors
+            );
+        }
+        out
+    }
+
+    /// 1件ずつの全項目
+    pub fn render_detail(&self, timezone: Timezone) -> String {
+        let mut out = String::new();
+        let format_time = |datetime: DateTime<Utc>| timezone.localize(datetime).format("%Y-%m-%d %H:%M:%S %:z").to_string();
+
+        for run in &self.runs {
+            let _ = writeln!(out, "再生 ID {}", run.id);
+            let _ = writeln!(out, "  状態:       {}", run.status);
+            let _ = writeln!(out, "  実行者:     {}@{}", run.operator, run.host);
+            let _ = writeln!(out, "  開始:       {}", format_time(run.started_at));
+            match run.finished_at {
+                Some(finished_at) => {
+                    let _ = writeln!(out, "  終了:       {} (所要 {})", format_time(finished_at), format_duration(finished_at - run.started_at));
+                },
+                None => {
+                    let _ = writeln!(out, "  終了:       記録なし (実行中か、終了前に中断されました)");
+                },
+            }
+            let _ = writeln!(
+                out,
+                "  期間:       {} 〜 {}",
+                format_time(run.parameters.range_start),
+                format_time(run.parameters.range_end)
+            );
+            let _ = writeln!(out, "  送信方式:   {}", run.parameters.backend);
+            let _ = writeln!(out, "  送信先:     {}", run.parameters.destination.as_deref().unwrap_or("-"));
+            let _ = writeln!(out, "  速度:       {}", run.parameters.speed);
+            let _ = writeln!(out, "  変換/劣化:  {}", run.parameters.filters);
+            if let Some(statistics) = &run.statistics {
+                let _ = writeln!(
+                    out,
+                    "  送信:       {} / {} pkts ({}), エラー {} 件",
+                    statistics.packets_sent,
+                    statistics.packets_total,
+                    format_bytes(statistics.bytes_sent),
+                    statistics.errors
+                );
+            }
+            if let Some(error_message) = &run.error_message {
+                let _ = writeln!(out, "  エラー:     {}", error_message);
+            }
+        }
+        out
+    }
+}
+
+/// 全角文字を2桁として`width`桁まで右側を空白で埋める
+fn pad(text: &str, width: usize) -> String {
+    format!("{}{}", text, " ".repeat(width.saturating_sub(display_width(text))))
+}
+
+/// 全角文字を2桁として`width`桁まで左側を空白で埋める
+fn pad_left(text: &str, width: usize) -> String {
+    format!("{}{}", " ".repeat(width.saturating_sub(display_width(text))), text)
+}
+
+fn display_width(text: &str) -> usize {
+    text.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::model::{ReplayRunParameters, ReplayRunStatistics, ReplayRunStatus};
+    use chrono::{Duration, TimeZone};
+
+    fn run(id: i64, finished: bool) -> ReplayRun {
+        let started_at = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        ReplayRun {
+            id,
+            started_at,
+            finished_at: finished.then(|| started_at + Duration::seconds(90)),
+            parameters: ReplayRunParameters {
+                range_start: started_at - Duration::hours(1),
+                range_end: started_at,
+                backend: "af_packet".to_string(),
+                destination: Some("eth0".to_string()),
+                filters: "なし".to_string(),
+                speed: "x1".to_string(),
+            },
+            host: "host1".to_string(),
+            operator: "alice".to_string(),
+            status: if finished { ReplayRunStatus::Succeeded } else { ReplayRunStatus::Running },
+            statistics: finished.then_some(ReplayRunStatistics {
+                packets_total: 10,
+                packets_sent: 10,
+                bytes_sent: 2048,
+                errors: 0,
+            }),
+            error_message: None,
+        }
+    }
+
+    #[test]
+    fn lists_runs_one_per_line() {
+        let history = ReplayHistory {
+            runs: vec![run(2, false), run(1, true)],
+        };
+
+        let rendered = history.render_list(Timezone::Utc);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("     2  2024-05-01 12:00:00  実行中"));
+        assert!(lines[1].contains("af_packet:eth0"));
+        assert!(lines[1].ends_with("-       -"));
+        assert!(lines[2].ends_with("10/10       0"));
+        assert_eq!(ReplayHistory { runs: Vec::new() }.render_list(Timezone::Utc), "再生の履歴はありません\n");
+    }
+
+    #[test]
+    fn details_include_duration_and_statistics() {
+        let rendered = ReplayHistory { runs: vec![run(1, true)] }.render_detail(Timezone::Utc);
+        assert!(rendered.contains("実行者:     alice@host1"));
+        assert!(rendered.contains("(所要 1m30s)"));
+        assert!(rendered.contains("送信:       10 / 10 pkts (2.0 KiB), エラー 0 件"));
+
+        let rendered = ReplayHistory { runs: vec![run(2, false)] }.render_detail(Timezone::Utc);
+        assert!(rendered.contains("終了:       記録なし"));
+        assert!(!rendered.contains("送信:"));
+    }
+
+    #[test]
+    fn pads_by_display_width() {
+        assert_eq!(pad("成功", 6), "成功  ");
+        assert_eq!(pad_left("ab", 4), "  ab");
+        assert_eq!(display_width("送信/総数"), 9);
+    }
+}
@@ -0,0 +1,57 @@
+use crate::packet::history::error::HistoryError;
+use crate::packet::model::{ReplayRunParameters, ReplayRunStatistics, ReplayRunStatus};
+use crate::packet::reader::ReplaySnapshot;
+use crate::packet::repository::ReplayRunRepository;
+use log::info;
+
+/// 1回の再生の開始と終了を`replay_runs`へ記録する
+pub struct ReplayRecorder {
+    id: i64,
+}
+
+impl ReplayRecorder {
+    /// 実行中の再生として記録する
+    pub async fn begin(parameters: &ReplayRunParameters) -> Result<Self, HistoryError> {
+        let repository = ReplayRunRepository::from_database();
+        repository.ensure_table().await.map_err(|e| HistoryError::RecordError(e.to_string()))?;
+        let id = repository.insert_run(parameters, &host_name(), &operator_name()).await.map_err(|e| HistoryError::RecordError(e.to_string()))?;
+
+        info!("再生の履歴を記録します: ID {}", id);
+        Ok(Self { id })
+    }
+
+    /// 結果と送信の集計を記録する。`error`は再生が失敗した場合の理由
+    pub async fn finish(self, snapshot: &ReplaySnapshot, error: Option<&str>) -> Result<(), HistoryError> {
+        let status = if error.is_some() { ReplayRunStatus::Failed } else { ReplayRunStatus::Succeeded };
+        let statistics = ReplayRunStatistics {
+            packets_total: snapshot.total as i64,
+            packets_sent: snapshot.sent as i64,
+            bytes_sent: snapshot.bytes as i64,
+            errors: snapshot.errors as i64,
+        };
+
+        ReplayRunRepository::from_database().finish_run(self.id, status, &statistics, error).await.map_err(|e| HistoryError::RecordError(e.to_string()))
+    }
+}
+
+/// 実行したユーザー (sudo経由であれば元のユーザー)
+fn operator_name() -> String {
+    ["SUDO_USER", "USER", "LOGNAME"].iter().find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty())).unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(unix)]
+fn host_name() -> String {
+    let mut buffer = [0u8; 256];
+    // SAFETY: バッファの長さを渡しており、gethostnameはその範囲にのみ書き込む
+    let result = unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) };
+    if result != 0 {
+        return "unknown".to_string();
+    }
+    let length = buffer.iter().position(|&byte| byte == 0).unwrap_or(buffer.len());
+    String::from_utf8_lossy(&buffer[..length]).into_owned()
+}
+
+#[cfg(not(unix))]
+fn host_name() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_else(|_| "unknown".to_string())
+}
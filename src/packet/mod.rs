@@ -1,2 +1,8 @@
+pub mod availability;
+pub mod export;
+pub mod history;
+pub mod model;
+pub mod profile;
 pub mod reader;
 pub mod repository;
+pub mod transform;
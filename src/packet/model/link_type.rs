@@ -0,0 +1,47 @@
+/// パケットのリンク層の種類
+///
+/// 値はpcapの`LINKTYPE_*`に合わせている。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LinkType {
+    /// BSDループバック (ホストバイトオーダーのアドレスファミリ4バイト)
+    Null,
+    #[default]
+    Ethernet,
+    /// L2ヘッダなしのIPv4/IPv6
+    RawIp,
+    Ieee80211,
+    /// OpenBSDループバック (ネットワークバイトオーダーのアドレスファミリ4バイト)
+    Loop,
+    LinuxSll,
+    Ieee80211Radiotap,
+    LinuxSll2,
+    Other(u16),
+}
+
+impl LinkType {
+    pub const LINKTYPE_NULL: u16 = 0;
+    pub const LINKTYPE_ETHERNET: u16 = 1;
+    pub const LINKTYPE_RAW: u16 = 101;
+    pub const LINKTYPE_IEEE802_11: u16 = 105;
+    pub const LINKTYPE_LOOP: u16 = 108;
+    pub const LINKTYPE_LINUX_SLL: u16 = 113;
+    pub const LINKTYPE_IEEE802_11_RADIOTAP: u16 = 127;
+    pub const LINKTYPE_IPV4: u16 = 228;
+    pub const LINKTYPE_IPV6: u16 = 229;
+    pub const LINKTYPE_LINUX_SLL2: u16 = 276;
+
+    pub fn from_linktype(value: u16) -> Self {
+        match value {
+            Self::LINKTYPE_NULL => Self::Null,
+            Self::LINKTYPE_ETHERNET => Self::Ethernet,
+            // 12と14は一部プラットフォームでのDLT_RAWの値
+            12 | 14 | Self::LINKTYPE_RAW | Self::LINKTYPE_IPV4 | Self::LINKTYPE_IPV6 => Self::RawIp,
+            Self::LINKTYPE_IEEE802_11 => Self::Ieee80211,
+            Self::LINKTYPE_LOOP => Self::Loop,
+            Self::LINKTYPE_LINUX_SLL => Self::LinuxSll,
+            Self::LINKTYPE_IEEE802_11_RADIOTAP => Self::Ieee80211Radiotap,
+            Self::LINKTYPE_LINUX_SLL2 => Self::LinuxSll2,
+            other => Self::Other(other),
+        }
+    }
+}
@@ -0,0 +1,11 @@
+mod link_type;
+mod packet;
+mod replay_profile;
+mod replay_run;
+mod traffic;
+
+pub use link_type::LinkType;
+pub use packet::{Packet, PacketMetadata};
+pub use replay_profile::ReplayProfile;
+pub use replay_run::{ReplayRun, ReplayRunParameters, ReplayRunStatistics, ReplayRunStatus};
+pub use traffic::{TopTalker, TrafficBucket};
@@ -0,0 +1,65 @@
+use crate::packet::model::LinkType;
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::net::IpAddr;
+
+/// データベースから読み出した1パケット
+#[derive(Debug, Clone)]
+pub struct Packet {
+    pub timestamp: DateTime<Utc>,
+    pub data: Vec<u8>,
+    /// キャプチャ時のワイヤ上の長さ (スナップ長で切り詰められていれば`data`より長い)
+    pub original_length: usize,
+    /// キャプチャしたインターフェース名またはセンサーID
+    pub source: Option<String>,
+    pub link_type: LinkType,
+    pub metadata: PacketMetadata,
+}
+
+/// テーブルに存在する場合のみ埋まる解析済みのカラム
+#[derive(Debug, Clone, Default)]
+pub struct PacketMetadata {
+    pub src_ip: Option<IpAddr>,
+    pub dst_ip: Option<IpAddr>,
+    pub src_port: Option<u16>,
+    pub dst_port: Option<u16>,
+    pub protocol: Option<u8>,
+}
+
+impl Packet {
+    pub fn new(timestamp: DateTime<Utc>, data: Vec<u8>) -> Self {
+        Self {
+            timestamp,
+            original_length: data.len(),
+            data,
+            source: None,
+            link_type: LinkType::default(),
+            metadata: PacketMetadata::default(),
+        }
+    }
+
+    pub fn captured_length(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.captured_length() < self.original_length
+    }
+}
+
+impl fmt::Display for PacketMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let endpoint = |ip: Option<IpAddr>, port: Option<u16>| match (ip, port) {
+            (Some(ip), Some(port)) => format!("{}:{}", ip, port),
+            (Some(ip), None) => ip.to_string(),
+            (None, Some(port)) => format!("*:{}", port),
+            (None, None) => "*".to_string(),
+        };
+
+        write!(f, "{} -> {}", endpoint(self.src_ip, self.src_port), endpoint(self.dst_ip, self.dst_port))?;
+        if let Some(protocol) = self.protocol {
+            write!(f, " (protocol {})", protocol)?;
+        }
+        Ok(())
+    }
+}
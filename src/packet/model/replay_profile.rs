@@ -0,0 +1,12 @@
+use chrono::{DateTime, Utc};
+
+/// 名前を付けて保存した再生の設定
+///
+/// 再生のコマンドライン引数をそのまま保持し、実行時に解析し直す。
+#[derive(Debug, Clone)]
+pub struct ReplayProfile {
+    pub name: String,
+    pub description: Option<String>,
+    pub arguments: Vec<String>,
+    pub updated_at: DateTime<Utc>,
+}
@@ -0,0 +1,84 @@
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::str::FromStr;
+
+/// 再生の実行状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayRunStatus {
+    /// 実行中 (終了が記録されずにプロセスが止まった場合もこのまま残る)
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl ReplayRunStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Succeeded => "succeeded",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for ReplayRunStatus {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "running" => Ok(Self::Running),
+            "succeeded" => Ok(Self::Succeeded),
+            "failed" => Ok(Self::Failed),
+            other => Err(format!("不明な実行状態です: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for ReplayRunStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Running => "実行中",
+            Self::Succeeded => "成功",
+            Self::Failed => "失敗",
+        })
+    }
+}
+
+/// 再生を始める時点で決まっている設定
+#[derive(Debug, Clone)]
+pub struct ReplayRunParameters {
+    pub range_start: DateTime<Utc>,
+    pub range_end: DateTime<Utc>,
+    /// 送信バックエンドの名前 (pnet / af_packet / ... / dry_run)
+    pub backend: String,
+    /// 送信先のインターフェース (pcapへの書き出しとドライランでは書き出し先のファイル)
+    pub destination: Option<String>,
+    /// 適用した変換と回線劣化の設定
+    pub filters: String,
+    pub speed: String,
+}
+
+/// 再生の結果の集計
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReplayRunStatistics {
+    pub packets_total: i64,
+    pub packets_sent: i64,
+    pub bytes_sent: i64,
+    pub errors: i64,
+}
+
+/// `replay_runs`に記録された1回の再生
+#[derive(Debug, Clone)]
+pub struct ReplayRun {
+    pub id: i64,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub parameters: ReplayRunParameters,
+    pub host: String,
+    /// 実行したOSのユーザー
+    pub operator: String,
+    pub status: ReplayRunStatus,
+    /// 終了が記録されていなければ`None`
+    pub statistics: Option<ReplayRunStatistics>,
+    pub error_message: Option<String>,
+}
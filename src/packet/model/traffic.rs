@@ -0,0 +1,19 @@
+use chrono::{DateTime, Utc};
+use std::net::IpAddr;
+
+/// `time_bucket`で集計した一定時間ごとのパケット量
+#[derive(Debug, Clone)]
+pub struct TrafficBucket {
+    pub start: DateTime<Utc>,
+    pub packets: i64,
+    /// キャプチャされたバイト数の合計
+    pub bytes: i64,
+}
+
+/// 送信元アドレスごとのパケット量
+#[derive(Debug, Clone)]
+pub struct TopTalker {
+    pub address: IpAddr,
+    pub packets: i64,
+    pub bytes: i64,
+}
@@ -0,0 +1,19 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ProfileError {
+    #[error("プロファイルを読み書きできませんでした: {0}")]
+    StorageError(String),
+
+    #[error("プロファイルがありません: {0}")]
+    NotFound(String),
+
+    #[error("同じ名前のプロファイルが既にあります: {0} (置き換える場合は --replace を指定してください)")]
+    AlreadyExists(String),
+
+    #[error("プロファイルには秘密の値を保存できません: {0} (環境変数で指定してください)")]
+    SecretArgument(String),
+
+    #[error("プロファイルの選択に失敗しました: {0}")]
+    SelectionError(String),
+}
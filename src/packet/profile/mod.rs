@@ -0,0 +1,4 @@
+mod error;
+mod replay_profiles;
+
+pub use replay_profiles::ReplayProfiles;
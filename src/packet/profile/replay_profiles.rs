@@ -0,0 +1,158 @@
+use crate::config::Timezone;
+use crate::packet::model::ReplayProfile;
+use crate::packet::profile::error::ProfileError;
+use crate::packet::repository::ReplayProfileRepository;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+/// プロファイルに保存できない引数 (データベースに平文で残るため)
+const SECRET_ARGUMENTS: [&str; 1] = ["--anonymize-key"];
+
+/// 保存済みの再生のプロファイル
+pub struct ReplayProfiles {
+    profiles: Vec<ReplayProfile>,
+}
+
+impl ReplayProfiles {
+    pub async fn load() -> Result<Self, ProfileError> {
+        let repository = ReplayProfileRepository::from_database();
+        repository.ensure_table().await.map_err(|e| ProfileError::StorageError(e.to_string()))?;
+        let profiles = repository.list_profiles().await.map_err(|e| ProfileError::StorageError(e.to_string()))?;
+        Ok(Self { profiles })
+    }
+
+    pub async fn get(name: &str) -> Result<ReplayProfile, ProfileError> {
+        let repository = ReplayProfileRepository::from_database();
+        repository.ensure_table().await.map_err(|e| ProfileError::StorageError(e.to_string()))?;
+        repository.get_profile(name).await.map_err(|e| ProfileError::StorageError(e.to_string()))?.ok_or_else(|| ProfileError::NotFound(name.to_string()))
+    }
+
+    /// 引数は呼び出し側で再生の引数として解析できることを確認しておく
+    pub async fn save(name: &str, description: Option<&str>, arguments: &[String], replace: bool) -> Result<(), ProfileError> {
+        if let Some(argument) =
+            arguments.iter().find(|argument| SECRET_ARGUMENTS.iter().any(|secret| argument.as_str() == *secret || argument.starts_with(&format!("{}=", secret))))
+        {
+            return Err(ProfileError::SecretArgument(argument.split('=').next().unwrap_or(argument).to_string()));
+        }
+
+        let repository = ReplayProfileRepository::from_database();
+        repository.ensure_table().await.map_err(|e| ProfileError::StorageError(e.to_string()))?;
+        if replace {
+            repository.upsert_profile(name, description, arguments).await.map_err(|e| ProfileError::StorageError(e.to_string()))
+        } else if repository.insert_profile(name, description, arguments).await.map_err(|e| ProfileError::StorageError(e.to_string()))? {
+            Ok(())
+        } else {
+            Err(ProfileError::AlreadyExists(name.to_string()))
+        }
+    }
+
+    pub async fn delete(name: &str) -> Result<(), ProfileError> {
+        let repository = ReplayProfileRepository::from_database();
+        repository.ensure_table().await.map_err(|e| ProfileError::StorageError(e.to_string()))?;
+        if repository.delete_profile(name).await.map_err(|e| ProfileError::StorageError(e.to_string()))? {
+            Ok(())
+        } else {
+            Err(ProfileError::NotFound(name.to_string()))
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.profiles.is_empty()
+    }
+
+    /// 一覧を表示し、番号または名前で選ばせる (空欄ならプロファイルを使わない)
+    pub fn prompt(self) -> Result<Option<ReplayProfile>, ProfileError> {
+        println!("\n保存済みのプロファイル:");
+        for (number, profile) in self.profiles.iter().enumerate() {
+            println!("{:>3}. {}", number + 1, summary(profile));
+        }
+
+        print!("\nプロファイルを番号または名前で選択してください (空欄で使わない): ");
+        io::stdout().flush().map_err(|e| ProfileError::SelectionError(e.to_string()))?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).map_err(|e| ProfileError::SelectionError(e.to_string()))?;
+        let input = input.trim();
+        if input.is_empty() {
+            return Ok(None);
+        }
+
+        let index = input.parse::<usize>().ok().and_then(|number| number.checked_sub(1));
+        let mut profiles = self.profiles.into_iter().enumerate();
+        profiles.find(|(i, profile)| Some(*i) == index || profile.name == input).map(|(_, profile)| Some(profile)).ok_or_else(|| ProfileError::NotFound(input.to_string()))
+    }
+
+    pub fn render_list(&self) -> String {
+        let mut out = String::new();
+        if self.profiles.is_empty() {
+            let _ = writeln!(out, "保存済みのプロファイルはありません");
+            return out;
+        }
+        for profile in &self.profiles {
+            let _ = writeln!(out, "{}", summary(profile));
+        }
+        out
+    }
+
+    pub fn render_detail(profile: &ReplayProfile, timezone: Timezone) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "プロファイル {}", profile.name);
+        if let Some(description) = &profile.description {
+            let _ = writeln!(out, "  説明:   {}", description);
+        }
+        let _ = writeln!(out, "  更新:   {}", timezone.localize(profile.updated_at).format("%Y-%m-%d %H:%M:%S %:z"));
+        let _ = writeln!(out, "  引数:   {}", command_line(&profile.arguments));
+        out
+    }
+}
+
+fn summary(profile: &ReplayProfile) -> String {
+    match &profile.description {
+        Some(description) => format!("{} - {}", profile.name, description),
+        None => format!("{} - {}", profile.name, command_line(&profile.arguments)),
+    }
+}
+
+/// 空白を含む引数は引用符で囲んで1行に並べる
+fn command_line(arguments: &[String]) -> String {
+    arguments
+        .iter()
+        .map(|argument| {
+            if argument.contains(char::is_whitespace) {
+                format!("'{}'", argument)
+            } else {
+                argument.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn profile(description: Option<&str>, arguments: &[&str]) -> ReplayProfile {
+        ReplayProfile {
+            name: "lab".to_string(),
+            description: description.map(str::to_string),
+            arguments: arguments.iter().map(|argument| argument.to_string()).collect(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn summarizes_with_description_or_quoted_arguments() {
+        assert_eq!(summary(&profile(Some("検証用"), &["--speed", "2"])), "lab - 検証用");
+        assert_eq!(summary(&profile(None, &["--start", "1 hour ago", "--speed", "2"])), "lab - --start '1 hour ago' --speed 2");
+    }
+
+    #[tokio::test]
+    async fn refuses_to_store_secret_arguments() {
+        let arguments = ["--anonymize-key=secret".to_string()];
+        // 保存先に触れる前に拒否する
+        let result = ReplayProfiles::save("lab", None, &arguments, false).await;
+        assert!(matches!(result, Err(ProfileError::SecretArgument(argument)) if argument == "--anonymize-key"));
+    }
+}
@@ -11,6 +11,18 @@ pub enum PacketReaderError {
     #[error("パケット送信エラー: {0}")]
     SendError(String),
 
+    #[error("未対応のリンク層です: {0}")]
+    UnsupportedLinkType(String),
+
+    #[error("フレームの解析に失敗しました: {0}")]
+    MalformedFrame(String),
+
+    #[error("出力ファイルの書き込みに失敗しました: {0}")]
+    OutputError(String),
+
+    #[error("送信スレッドのエラー: {0}")]
+    ThreadError(String),
+
     #[error("設定エラー: {0}")]
     ConfigurationError(String),
 }
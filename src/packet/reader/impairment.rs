@@ -0,0 +1,193 @@
+use crate::packet::model::Packet;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::time::Duration;
+
+/// netem相当の回線劣化の設定
+///
+/// 確率はいずれも0.0〜1.0。同じシードと同じパケット列からは常に同じ送信計画が得られる。
+#[derive(Debug, Clone, Default)]
+pub struct ImpairmentOptions {
+    pub drop: f64,
+    pub duplicate: f64,
+    /// 後続`reorder_window`個以内のパケットと送信順を入れ替える確率
+    pub reorder: f64,
+    pub reorder_window: usize,
+    /// 1ビットを反転させる確率 (チェックサムは直さない)
+    pub corrupt: f64,
+    pub latency: Duration,
+    /// 遅延に加える±jitterの一様なゆらぎ (ゆらぎによる順序の入れ替わりも起こる)
+    pub jitter: Duration,
+    /// 省略時はランダムに決め、再現できるようにログへ出力する
+    pub seed: Option<u64>,
+}
+
+impl ImpairmentOptions {
+    pub fn is_noop(&self) -> bool {
+        self.drop == 0.0 && self.duplicate == 0.0 && (self.reorder == 0.0 || self.reorder_window == 0) && self.corrupt == 0.0 && self.latency.is_zero() && self.jitter.is_zero()
+    }
+}
+
+/// 1回の送信
+pub struct Transmission {
+    /// 送信するパケットの添字
+    pub index: usize,
+    /// 先頭パケットからの送信時刻
+    pub offset: Duration,
+    /// 反転させるビット位置の元になる乱数 (フレーム長で剰余をとって使う)
+    pub corrupt_bit: Option<u64>,
+}
+
+impl Transmission {
+    /// 送信直前のフレームにビット反転を適用する
+    pub fn corrupt(&self, frame: &mut [u8]) -> bool {
+        match self.corrupt_bit {
+            Some(bit) if !frame.is_empty() => {
+                let bit = bit % (frame.len() as u64 * 8);
+                frame[(bit / 8) as usize] ^= 1 << (bit % 8);
+                true
+            },
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ImpairmentStats {
+    pub dropped: usize,
+    pub duplicated: usize,
+    pub reordered: usize,
+    pub corrupted: usize,
+}
+
+/// 回線劣化を反映した送信計画
+pub struct ImpairmentPlan {
+    pub seed: u64,
+    pub transmissions: Vec<Transmission>,
+    pub stats: ImpairmentStats,
+}
+
+impl ImpairmentPlan {
+    /// キャプチャ時刻の間隔を保った送信計画を立て、設定された劣化を適用する
+    pub fn new(packets: &[Packet], options: &ImpairmentOptions) -> Self {
+        let seed = options.seed.unwrap_or_else(rand::random);
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut stats = ImpairmentStats::default();
+        let mut transmissions = Vec::with_capacity(packets.len());
+
+        let Some(first) = packets.first() else {
+            return Self { seed, transmissions, stats };
+        };
+
+        for (index, packet) in packets.iter().enumerate() {
+            let captured_at = (packet.timestamp - first.timestamp).to_std().unwrap_or_default();
+
+            if rng.gen_bool(options.drop) {
+                stats.dropped += 1;
+                continue;
+            }
+
+            let copies = if rng.gen_bool(options.duplicate) {
+                stats.duplicated += 1;
+                2
+            } else {
+                1
+            };
+
+            for _ in 0..copies {
+                let corrupt_bit = rng.gen_bool(options.corrupt).then(|| rng.gen());
+                transmissions.push(Transmission {
+                    index,
+                    offset: Self::delay(&mut rng, captured_at, options),
+                    corrupt_bit,
+                });
+            }
+        }
+
+        // ゆらぎで前後した送信時刻の順に並べる
+        transmissions.sort_by_key(|transmission| transmission.offset);
+
+        // 送信時刻の枠はそのままに、送るパケットだけを後続と入れ替える
+        if options.reorder_window > 0 {
+            let last = transmissions.len().saturating_sub(1);
+            for i in 0..last {
+                if rng.gen_bool(options.reorder) {
+                    let j = (i + rng.gen_range(1..=options.reorder_window)).min(last);
+                    let (index, corrupt_bit) = (transmissions[j].index, transmissions[j].corrupt_bit);
+                    transmissions[j].index = transmissions[i].index;
+                    transmissions[j].corrupt_bit = transmissions[i].corrupt_bit;
+                    transmissions[i].index = index;
+                    transmissions[i].corrupt_bit = corrupt_bit;
+                    stats.reordered += 1;
+                }
+            }
+        }
+
+        stats.corrupted = transmissions.iter().filter(|transmission| transmission.corrupt_bit.is_some()).count();
+
+        Self { seed, transmissions, stats }
+    }
+
+    fn delay(rng: &mut ChaCha8Rng, captured_at: Duration, options: &ImpairmentOptions) -> Duration {
+        let delayed = captured_at + options.latency;
+        if options.jitter.is_zero() {
+            return delayed;
+        }
+
+        let jitter = options.jitter.as_micros() as i64;
+        let offset = delayed.as_micros() as i64 + rng.gen_range(-jitter..=jitter);
+        Duration::from_micros(offset.max(0) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn packets(count: usize) -> Vec<Packet> {
+        let start = Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap();
+        (0..count).map(|i| Packet::new(start + chrono::Duration::milliseconds(i as i64), vec![i as u8; 64])).collect()
+    }
+
+    fn options(seed: u64) -> ImpairmentOptions {
+        ImpairmentOptions {
+            drop: 0.1,
+            duplicate: 0.1,
+            reorder: 0.2,
+            reorder_window: 3,
+            corrupt: 0.1,
+            latency: Duration::from_millis(5),
+            jitter: Duration::from_millis(2),
+            seed: Some(seed),
+        }
+    }
+
+    fn summary(plan: &ImpairmentPlan) -> Vec<(usize, Duration, Option<u64>)> {
+        plan.transmissions.iter().map(|transmission| (transmission.index, transmission.offset, transmission.corrupt_bit)).collect()
+    }
+
+    #[test]
+    fn same_seed_gives_same_plan() {
+        let packets = packets(500);
+        let first = ImpairmentPlan::new(&packets, &options(42));
+        let second = ImpairmentPlan::new(&packets, &options(42));
+
+        assert_eq!(first.seed, 42);
+        assert_eq!(summary(&first), summary(&second));
+        assert!(first.stats.dropped > 0 && first.stats.duplicated > 0 && first.stats.reordered > 0 && first.stats.corrupted > 0);
+
+        let other = ImpairmentPlan::new(&packets, &options(43));
+        assert_ne!(summary(&first), summary(&other));
+    }
+
+    #[test]
+    fn noop_keeps_capture_timing() {
+        let packets = packets(10);
+        let plan = ImpairmentPlan::new(&packets, &ImpairmentOptions::default());
+
+        assert!(ImpairmentOptions::default().is_noop());
+        let expected: Vec<_> = (0..10).map(|i| (i, Duration::from_millis(i as u64), None)).collect();
+        assert_eq!(summary(&plan), expected);
+    }
+}
@@ -0,0 +1,544 @@
+use crate::packet::model::{LinkType, Packet};
+use crate::packet::reader::error::PacketReaderError;
+use pnet::util::MacAddr;
+use std::borrow::Cow;
+use std::net::Ipv4Addr;
+use std::str::FromStr;
+
+const ETHERNET_HEADER_LEN: usize = 14;
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88a8;
+const VLAN_TAG_LEN: usize = 4;
+const ARPHRD_ETHER: u16 = 1;
+
+/// 非Ethernetのパケットをどの経路で送信するか
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LinkOutput {
+    /// L2ヘッダを合成してEthernetチャネルで送信する
+    #[default]
+    Ethernet,
+    /// IPv4はrawソケットでL3のまま送信する (送信先インターフェースはカーネルの経路で決まる)
+    RawIp,
+}
+
+impl FromStr for LinkOutput {
+    type Err = PacketReaderError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "ethernet" => Ok(Self::Ethernet),
+            "raw_ip" => Ok(Self::RawIp),
+            other => Err(PacketReaderError::ConfigurationError(format!("不明なリンク出力方式です: {}", other))),
+        }
+    }
+}
+
+/// 送信するフレームのL2に関する設定
+#[derive(Debug, Clone)]
+pub struct LinkLayerOptions {
+    pub src_mac: Option<MacAddr>,
+    pub dst_mac: Option<MacAddr>,
+    pub output: LinkOutput,
+    /// 送信するL3パケットのサイズの上限
+    pub mtu: usize,
+}
+
+impl LinkLayerOptions {
+    pub const DEFAULT_MTU: usize = 1500;
+}
+
+impl Default for LinkLayerOptions {
+    fn default() -> Self {
+        Self {
+            src_mac: None,
+            dst_mac: None,
+            output: LinkOutput::default(),
+            mtu: Self::DEFAULT_MTU,
+        }
+    }
+}
+
+/// 送信経路ごとに変換済みのフレーム
+pub enum OutboundFrame<'a> {
+    Ethernet(Cow<'a, [u8]>),
+    Ipv4 {
+        destination: Ipv4Addr,
+        packet: Cow<'a, [u8]>,
+    },
+}
+
+impl OutboundFrame<'_> {
+    /// 送信するバイト列全体のサイズ
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Ethernet(bytes) => bytes.len(),
+            Self::Ipv4 { packet, .. } => packet.len(),
+        }
+    }
+
+    /// MTUと比べるL3パケットのサイズ
+    ///
+    /// EthernetヘッダとVLANタグは含めない。トンネルでカプセル化したフレームは外側のIPパケットの大きさになる。
+    pub fn l3_size(&self) -> usize {
+        let bytes = match self {
+            Self::Ethernet(bytes) => bytes,
+            Self::Ipv4 { packet, .. } => return packet.len(),
+        };
+
+        let mut header_len = ETHERNET_HEADER_LEN;
+        while let Some(ethertype) = bytes.get(header_len - 2..header_len) {
+            let ethertype = u16::from_be_bytes([ethertype[0], ethertype[1]]);
+            if !matches!(ethertype, ETHERTYPE_VLAN | ETHERTYPE_QINQ) || bytes.len() < header_len + VLAN_TAG_LEN {
+                break;
+            }
+            header_len += VLAN_TAG_LEN;
+        }
+        bytes.len().saturating_sub(header_len)
+    }
+
+    /// 記録用にEthernetフレームとして返す (L3のIPv4にはMACアドレスが0のヘッダを付ける)
+    pub fn to_ethernet(&self) -> Cow<'_, [u8]> {
+        match self {
+            Self::Ethernet(bytes) => Cow::Borrowed(bytes),
+            Self::Ipv4 { packet, .. } => {
+                let mut frame = Vec::with_capacity(ETHERNET_HEADER_LEN + packet.len());
+                frame.extend_from_slice(&[0u8; 12]);
+                frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+                frame.extend_from_slice(packet);
+                Cow::Owned(frame)
+            },
+        }
+    }
+
+    /// 元のパケットから切り離し、送信スレッドへ渡せるようにする
+    pub fn into_owned(self) -> OutboundFrame<'static> {
+        match self {
+            Self::Ethernet(bytes) => OutboundFrame::Ethernet(Cow::Owned(bytes.into_owned())),
+            Self::Ipv4 { destination, packet } => OutboundFrame::Ipv4 {
+                destination,
+                packet: Cow::Owned(packet.into_owned()),
+            },
+        }
+    }
+}
+
+/// L2ヘッダを剥がした結果
+struct Decapsulated<'a> {
+    ethertype: u16,
+    payload: &'a [u8],
+    src_mac: Option<MacAddr>,
+    dst_mac: Option<MacAddr>,
+}
+
+/// 保存されたリンク層の種類に関係なく、送信可能なフレームへ変換する
+pub struct LinkConverter {
+    options: LinkLayerOptions,
+    interface_mac: MacAddr,
+}
+
+impl LinkConverter {
+    /// `interface_mac`は送信元MACが他に決まらないときの既定値
+    pub fn new(options: LinkLayerOptions, interface_mac: MacAddr) -> Self {
+        Self { options, interface_mac }
+    }
+
+    pub fn convert<'a>(&self, packet: &'a Packet) -> Result<OutboundFrame<'a>, PacketReaderError> {
+        // Ethernetでキャプチャされたものはそのまま送る
+        if packet.link_type == LinkType::Ethernet {
+            return Ok(OutboundFrame::Ethernet(Cow::Borrowed(&packet.data)));
+        }
+
+        let decapsulated = decapsulate(packet.link_type, &packet.data)?;
+
+        if self.options.output == LinkOutput::RawIp && decapsulated.ethertype == ETHERTYPE_IPV4 {
+            let destination = ipv4_destination(decapsulated.payload)?;
+            return Ok(OutboundFrame::Ipv4 {
+                destination,
+                packet: Cow::Borrowed(decapsulated.payload),
+            });
+        }
+
+        // 設定されたMAC > キャプチャ元のMAC > 既定値 の順に採用する
+        let src_mac = self.options.src_mac.or(decapsulated.src_mac).unwrap_or(self.interface_mac);
+        let dst_mac = self.options.dst_mac.or(decapsulated.dst_mac).unwrap_or(MacAddr::broadcast());
+
+        let mut frame = Vec::with_capacity(ETHERNET_HEADER_LEN + decapsulated.payload.len());
+        frame.extend_from_slice(&dst_mac.octets());
+        frame.extend_from_slice(&src_mac.octets());
+        frame.extend_from_slice(&decapsulated.ethertype.to_be_bytes());
+        frame.extend_from_slice(decapsulated.payload);
+        Ok(OutboundFrame::Ethernet(Cow::Owned(frame)))
+    }
+}
+
+fn decapsulate(link_type: LinkType, data: &[u8]) -> Result<Decapsulated<'_>, PacketReaderError> {
+    match link_type {
+        LinkType::Ethernet => {
+            let header = slice(data, 0, ETHERNET_HEADER_LEN)?;
+            Ok(Decapsulated {
+                ethertype: u16::from_be_bytes([header[12], header[13]]),
+                payload: &data[ETHERNET_HEADER_LEN..],
+                src_mac: Some(mac_at(header, 6)),
+                dst_mac: Some(mac_at(header, 0)),
+            })
+        },
+        // アドレスファミリの値はOSごとに異なるため、IPヘッダのバージョンで判定する
+        LinkType::Null | LinkType::Loop => ip_payload(slice(data, 4, data.len())?),
+        LinkType::RawIp => ip_payload(data),
+        LinkType::LinuxSll => {
+            let header = slice(data, 0, 16)?;
+            let hatype = u16::from_be_bytes([header[2], header[3]]);
+            let addr_len = u16::from_be_bytes([header[4], header[5]]);
+            Ok(Decapsulated {
+                ethertype: u16::from_be_bytes([header[14], header[15]]),
+                payload: &data[16..],
+                src_mac: (hatype == ARPHRD_ETHER && addr_len == 6).then(|| mac_at(header, 6)),
+                dst_mac: None,
+            })
+        },
+        LinkType::LinuxSll2 => {
+            let header = slice(data, 0, 20)?;
+            let hatype = u16::from_be_bytes([header[8], header[9]]);
+            Ok(Decapsulated {
+                ethertype: u16::from_be_bytes([header[0], header[1]]),
+                payload: &data[20..],
+                src_mac: (hatype == ARPHRD_ETHER && header[11] == 6).then(|| mac_at(header, 12)),
+                dst_mac: None,
+            })
+        },
+        LinkType::Ieee80211 => ieee80211_payload(data, false),
+        LinkType::Ieee80211Radiotap => {
+            let (header_len, has_fcs) = radiotap_header(data)?;
+            ieee80211_payload(slice(data, header_len, data.len())?, has_fcs)
+        },
+        LinkType::Other(value) => Err(PacketReaderError::UnsupportedLinkType(format!("LINKTYPE {}", value))),
+    }
+}
+
+fn ip_payload(data: &[u8]) -> Result<Decapsulated<'_>, PacketReaderError> {
+    let ethertype = match data.first().map(|b| b >> 4) {
+        Some(4) => ETHERTYPE_IPV4,
+        Some(6) => ETHERTYPE_IPV6,
+        _ => return Err(PacketReaderError::MalformedFrame("IPヘッダのバージョンを判別できません".to_string())),
+    };
+
+    Ok(Decapsulated {
+        ethertype,
+        payload: data,
+        src_mac: None,
+        dst_mac: None,
+    })
+}
+
+/// 802.11のデータフレームをLLC/SNAPのEtherTypeとペイロードに分解する
+fn ieee80211_payload(data: &[u8], has_fcs: bool) -> Result<Decapsulated<'_>, PacketReaderError> {
+    let data = if has_fcs { slice(data, 0, data.len().saturating_sub(4))? } else { data };
+    let header = slice(data, 0, 24)?;
+
+    let frame_type = (header[0] >> 2) & 0x03;
+    let subtype = header[0] >> 4;
+    let to_ds = header[1] & 0x01 != 0;
+    let from_ds = header[1] & 0x02 != 0;
+    let protected = header[1] & 0x40 != 0;
+    let order = header[1] & 0x80 != 0;
+
+    if frame_type != 2 {
+        return Err(PacketReaderError::UnsupportedLinkType("802.11の管理/制御フレームは変換できません".to_string()));
+    }
+    if protected {
+        return Err(PacketReaderError::UnsupportedLinkType("暗号化された802.11フレームは変換できません".to_string()));
+    }
+    // Null Function等のペイロードを持たないサブタイプ
+    if subtype & 0x04 != 0 {
+        return Err(PacketReaderError::UnsupportedLinkType("ペイロードのない802.11データフレームです".to_string()));
+    }
+
+    let qos = subtype & 0x08 != 0;
+    let mut header_len = 24;
+    if to_ds && from_ds {
+        header_len += 6;
+    }
+    if qos {
+        header_len += 2;
+        if order {
+            header_len += 4;
+        }
+    }
+
+    let addr4 = if to_ds && from_ds { Some(mac_at(slice(data, 0, 30)?, 24)) } else { None };
+    let (dst_mac, src_mac) = match (to_ds, from_ds) {
+        (false, false) => (mac_at(header, 4), mac_at(header, 10)),
+        (true, false) => (mac_at(header, 16), mac_at(header, 10)),
+        (false, true) => (mac_at(header, 4), mac_at(header, 16)),
+        (true, true) => (mac_at(header, 16), addr4.unwrap_or_else(MacAddr::zero)),
+    };
+
+    let llc = slice(data, header_len, header_len + 8)?;
+    if llc[0..3] != [0xaa, 0xaa, 0x03] {
+        return Err(PacketReaderError::UnsupportedLinkType("LLC/SNAPでカプセル化されていない802.11フレームです".to_string()));
+    }
+
+    Ok(Decapsulated {
+        ethertype: u16::from_be_bytes([llc[6], llc[7]]),
+        payload: &data[header_len + 8..],
+        src_mac: Some(src_mac),
+        dst_mac: Some(dst_mac),
+    })
+}
+
+/// radiotapヘッダの長さと、末尾にFCSが付いているかを返す
+fn radiotap_header(data: &[u8]) -> Result<(usize, bool), PacketReaderError> {
+    let header = slice(data, 0, 8)?;
+    let header_len = u16::from_le_bytes([header[2], header[3]]) as usize;
+
+    // presentビットマップは拡張ビット(31)が立っている限り続く
+    let mut offset = 4;
+    let mut present = Vec::new();
+    loop {
+        let word = slice(data, offset, offset + 4)?;
+        let word = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+        present.push(word);
+        offset += 4;
+        if word & (1 << 31) == 0 {
+            break;
+        }
+    }
+
+    // Flagsフィールド(ビット1)の前に置かれ得るのはTSFT(ビット0, 8バイト境界)のみ
+    let mut has_fcs = false;
+    if present[0] & (1 << 1) != 0 {
+        if present[0] & 1 != 0 {
+            offset = offset.div_ceil(8) * 8 + 8;
+        }
+        let flags = slice(data, offset, offset + 1)?[0];
+        has_fcs = flags & 0x10 != 0;
+    }
+
+    Ok((header_len, has_fcs))
+}
+
+fn ipv4_destination(packet: &[u8]) -> Result<Ipv4Addr, PacketReaderError> {
+    let header = slice(packet, 0, 20)?;
+    Ok(Ipv4Addr::new(header[16], header[17], header[18], header[19]))
+}
+
+fn mac_at(data: &[u8], offset: usize) -> MacAddr {
+    MacAddr::new(data[offset], data[offset + 1], data[offset + 2], data[offset + 3], data[offset + 4], data[offset + 5])
+}
+
+fn slice(data: &[u8], start: usize, end: usize) -> Result<&[u8], PacketReaderError> {
+    data.get(start..end).ok_or_else(|| PacketReaderError::MalformedFrame(format!("フレーム長が不足しています: {} bytes", data.len())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    const INTERFACE_MAC: MacAddr = MacAddr(0x02, 0, 0, 0, 0, 0xff);
+    const CAPTURED_MAC: MacAddr = MacAddr(0x02, 0x11, 0x22, 0x33, 0x44, 0x55);
+
+    fn ipv4_packet() -> Vec<u8> {
+        let mut packet = vec![0x45, 0, 0, 24, 0, 0, 0, 0, 64, 17, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2];
+        packet.extend_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        packet
+    }
+
+    fn packet(link_type: LinkType, data: Vec<u8>) -> Packet {
+        Packet {
+            link_type,
+            ..Packet::new(Utc::now(), data)
+        }
+    }
+
+    fn convert(link_type: LinkType, data: Vec<u8>) -> Result<Vec<u8>, PacketReaderError> {
+        let converter = LinkConverter::new(LinkLayerOptions::default(), INTERFACE_MAC);
+        match converter.convert(&packet(link_type, data))? {
+            OutboundFrame::Ethernet(frame) => Ok(frame.into_owned()),
+            OutboundFrame::Ipv4 { .. } => panic!("Ethernetフレームに変換されていません"),
+        }
+    }
+
+    fn ethernet_frame(dst_mac: MacAddr, src_mac: MacAddr, ethertype: u16, payload: &[u8]) -> Vec<u8> {
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&dst_mac.octets());
+        frame.extend_from_slice(&src_mac.octets());
+        frame.extend_from_slice(&ethertype.to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    fn sll_header(protocol: u16) -> Vec<u8> {
+        let mut header = vec![0, 4, 0, 1, 0, 6];
+        header.extend_from_slice(&CAPTURED_MAC.octets());
+        header.extend_from_slice(&[0, 0]);
+        header.extend_from_slice(&protocol.to_be_bytes());
+        header
+    }
+
+    fn sll2_header(protocol: u16) -> Vec<u8> {
+        let mut header = protocol.to_be_bytes().to_vec();
+        header.extend_from_slice(&[0, 0, 0, 0, 0, 2, 0, 1, 4, 6]);
+        header.extend_from_slice(&CAPTURED_MAC.octets());
+        header.extend_from_slice(&[0, 0]);
+        header
+    }
+
+    /// To DSのデータフレーム (宛先はaddr3、送信元はaddr2) とLLC/SNAP
+    fn ieee80211_frame(payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0x08, 0x01, 0, 0];
+        frame.extend_from_slice(&MacAddr(0x02, 0xaa, 0, 0, 0, 1).octets());
+        frame.extend_from_slice(&CAPTURED_MAC.octets());
+        frame.extend_from_slice(&MacAddr::broadcast().octets());
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(&[0xaa, 0xaa, 0x03, 0, 0, 0, 0x08, 0x00]);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[test]
+    fn ethernet_is_sent_as_is() {
+        let frame = ethernet_frame(MacAddr::broadcast(), CAPTURED_MAC, ETHERTYPE_IPV4, &ipv4_packet());
+        assert_eq!(convert(LinkType::Ethernet, frame.clone()).unwrap(), frame);
+    }
+
+    #[test]
+    fn linux_sll_keeps_the_captured_source_mac() {
+        let mut data = sll_header(ETHERTYPE_IPV4);
+        data.extend_from_slice(&ipv4_packet());
+
+        let expected = ethernet_frame(MacAddr::broadcast(), CAPTURED_MAC, ETHERTYPE_IPV4, &ipv4_packet());
+        assert_eq!(convert(LinkType::LinuxSll, data).unwrap(), expected);
+        assert!(matches!(
+            convert(LinkType::LinuxSll, sll_header(ETHERTYPE_IPV4)[..10].to_vec()),
+            Err(PacketReaderError::MalformedFrame(_))
+        ));
+    }
+
+    #[test]
+    fn linux_sll2_keeps_the_captured_source_mac() {
+        let mut data = sll2_header(ETHERTYPE_IPV6);
+        data.extend_from_slice(&[0x60, 0, 0, 0]);
+
+        let expected = ethernet_frame(MacAddr::broadcast(), CAPTURED_MAC, ETHERTYPE_IPV6, &[0x60, 0, 0, 0]);
+        assert_eq!(convert(LinkType::LinuxSll2, data).unwrap(), expected);
+        assert!(matches!(
+            convert(LinkType::LinuxSll2, sll2_header(ETHERTYPE_IPV6)[..12].to_vec()),
+            Err(PacketReaderError::MalformedFrame(_))
+        ));
+    }
+
+    #[test]
+    fn ieee80211_data_frame_is_converted() {
+        let expected = ethernet_frame(MacAddr::broadcast(), CAPTURED_MAC, ETHERTYPE_IPV4, &ipv4_packet());
+        assert_eq!(convert(LinkType::Ieee80211, ieee80211_frame(&ipv4_packet())).unwrap(), expected);
+
+        // LLC/SNAPヘッダの途中で切れている
+        let truncated = ieee80211_frame(&[])[..28].to_vec();
+        assert!(matches!(convert(LinkType::Ieee80211, truncated), Err(PacketReaderError::MalformedFrame(_))));
+    }
+
+    #[test]
+    fn ieee80211_management_frame_is_rejected() {
+        let mut frame = ieee80211_frame(&ipv4_packet());
+        frame[0] = 0x80;
+        assert!(matches!(convert(LinkType::Ieee80211, frame), Err(PacketReaderError::UnsupportedLinkType(_))));
+    }
+
+    #[test]
+    fn radiotap_header_and_fcs_are_stripped() {
+        // presentにFlagsのみ、FlagsにFCSあり
+        let mut data = vec![0, 0, 9, 0, 0x02, 0, 0, 0, 0x10];
+        data.extend_from_slice(&ieee80211_frame(&ipv4_packet()));
+        data.extend_from_slice(&[1, 2, 3, 4]);
+
+        let expected = ethernet_frame(MacAddr::broadcast(), CAPTURED_MAC, ETHERTYPE_IPV4, &ipv4_packet());
+        assert_eq!(convert(LinkType::Ieee80211Radiotap, data).unwrap(), expected);
+        assert!(matches!(
+            convert(LinkType::Ieee80211Radiotap, vec![0, 0, 9, 0, 0x02, 0]),
+            Err(PacketReaderError::MalformedFrame(_))
+        ));
+    }
+
+    #[test]
+    fn null_and_loop_use_the_ip_version() {
+        let expected = ethernet_frame(MacAddr::broadcast(), INTERFACE_MAC, ETHERTYPE_IPV4, &ipv4_packet());
+        for (link_type, family) in [(LinkType::Null, [2, 0, 0, 0]), (LinkType::Loop, [0, 0, 0, 2])] {
+            let mut data = family.to_vec();
+            data.extend_from_slice(&ipv4_packet());
+            assert_eq!(convert(link_type, data).unwrap(), expected);
+            assert!(matches!(convert(link_type, family[..2].to_vec()), Err(PacketReaderError::MalformedFrame(_))));
+        }
+    }
+
+    #[test]
+    fn raw_ip_is_sent_through_a_raw_socket_when_configured() {
+        let options = LinkLayerOptions {
+            output: LinkOutput::RawIp,
+            ..LinkLayerOptions::default()
+        };
+        let converter = LinkConverter::new(options, INTERFACE_MAC);
+
+        let raw_ip = packet(LinkType::RawIp, ipv4_packet());
+        match converter.convert(&raw_ip).unwrap() {
+            OutboundFrame::Ipv4 { destination, packet } => {
+                assert_eq!(destination, Ipv4Addr::new(10, 0, 0, 2));
+                assert_eq!(packet, ipv4_packet().as_slice());
+            },
+            OutboundFrame::Ethernet(_) => panic!("raw IPで送信されていません"),
+        }
+
+        assert!(matches!(
+            converter.convert(&packet(LinkType::RawIp, ipv4_packet()[..12].to_vec())),
+            Err(PacketReaderError::MalformedFrame(_))
+        ));
+        assert!(matches!(converter.convert(&packet(LinkType::RawIp, Vec::new())), Err(PacketReaderError::MalformedFrame(_))));
+    }
+
+    #[test]
+    fn configured_macs_take_precedence() {
+        let options = LinkLayerOptions {
+            src_mac: Some(MacAddr(0x02, 0, 0, 0, 0, 1)),
+            dst_mac: Some(MacAddr(0x02, 0, 0, 0, 0, 2)),
+            ..LinkLayerOptions::default()
+        };
+        let converter = LinkConverter::new(options, INTERFACE_MAC);
+        let mut data = sll_header(ETHERTYPE_IPV4);
+        data.extend_from_slice(&ipv4_packet());
+        let sll = packet(LinkType::LinuxSll, data);
+
+        let OutboundFrame::Ethernet(frame) = converter.convert(&sll).unwrap() else {
+            panic!("Ethernetフレームに変換されていません");
+        };
+        assert_eq!(frame[..12], [2, 0, 0, 0, 0, 2, 2, 0, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn l3_size_excludes_ethernet_header_and_vlan_tags() {
+        let payload = vec![0; 1500];
+        let untagged = OutboundFrame::Ethernet(ethernet_frame(MacAddr::broadcast(), CAPTURED_MAC, ETHERTYPE_IPV4, &payload).into());
+        assert_eq!((untagged.size(), untagged.l3_size()), (1514, 1500));
+
+        let mut tags = vec![0, 100];
+        tags.extend_from_slice(&ETHERTYPE_VLAN.to_be_bytes());
+        tags.extend_from_slice(&[0, 200]);
+        tags.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        tags.extend_from_slice(&payload);
+        let qinq = OutboundFrame::Ethernet(ethernet_frame(MacAddr::broadcast(), CAPTURED_MAC, ETHERTYPE_QINQ, &tags).into());
+        assert_eq!((qinq.size(), qinq.l3_size()), (1522, 1500));
+
+        let ipv4 = OutboundFrame::Ipv4 {
+            destination: Ipv4Addr::LOCALHOST,
+            packet: Cow::Borrowed(&payload),
+        };
+        assert_eq!(ipv4.l3_size(), 1500);
+    }
+
+    #[test]
+    fn parses_link_output() {
+        assert_eq!("raw_ip".parse::<LinkOutput>().unwrap(), LinkOutput::RawIp);
+        assert!("tap".parse::<LinkOutput>().is_err());
+    }
+}
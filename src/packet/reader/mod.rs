@@ -1,5 +1,22 @@
 mod error;
+mod impairment;
+mod link_layer;
 mod packet_reader;
 mod packet_sender;
+mod replay_monitor;
+mod replay_options;
+mod send_queue;
+mod send_schedule;
+mod sender_thread;
+mod transmit;
 
+pub use error::PacketReaderError;
+pub use impairment::ImpairmentOptions;
+pub(crate) use link_layer::{LinkConverter, OutboundFrame};
+pub use link_layer::{LinkLayerOptions, LinkOutput};
 pub use packet_reader::PacketReader;
+pub use replay_monitor::{ReplayMonitor, ReplaySnapshot};
+pub use replay_options::ReplayOptions;
+pub use send_schedule::Pacing;
+pub use sender_thread::SenderThreadOptions;
+pub use transmit::{BackendKind, DryRunOptions, ReplayTarget};
@@ -1,21 +1,30 @@
 use crate::packet::reader::error::PacketReaderError;
 use crate::packet::reader::packet_sender::PacketSender;
+use crate::packet::reader::replay_options::ReplayOptions;
+use crate::packet::reader::transmit::ReplayTarget;
 use crate::packet::repository::PacketRepository;
 use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
 use log::{error, info};
-use pnet::datalink::NetworkInterface;
 
 pub struct PacketReader;
 
 impl PacketReader {
-    pub async fn replay_packets(interface: NetworkInterface, start_time: DateTime<Utc>, end_time: DateTime<Utc>) -> Result<(), PacketReaderError> {
+    pub async fn replay_packets(target: ReplayTarget, start_time: DateTime<Utc>, end_time: DateTime<Utc>, options: ReplayOptions) -> Result<(), PacketReaderError> {
         info!("パケット再生を開始します");
-        info!("期間: {} から {}", start_time, end_time);
+        info!("期間: {} から {}", options.timezone.localize(start_time), options.timezone.localize(end_time));
 
-        match PacketRepository::get_packets_in_timerange(start_time, end_time).await {
+        // 回線劣化の送信計画にはパケット全体が必要なため、取得し終えてから送信を始める
+        let repository = PacketRepository::from_database().with_legacy_row_order(options.legacy_row_order);
+        let packets = match repository.get_packets_partitioned(start_time, end_time, options.fetch_concurrency).await {
+            Ok(packets) => packets.try_collect::<Vec<_>>().await,
+            Err(e) => Err(e),
+        };
+
+        match packets {
             Ok(packets) => {
                 info!("{}個のパケットを取得しました", packets.len());
-                PacketSender::send_packets_with_timing(&interface, packets).await?;
+                PacketSender::send_packets_with_timing(&target, packets, options).await?;
                 info!("パケット再生が完了しました");
                 Ok(())
             },
@@ -1,69 +1,333 @@
+use crate::packet::model::Packet;
 use crate::packet::reader::error::PacketReaderError;
-use chrono::{DateTime, Utc};
-use log::{error, info};
-use pnet::datalink::Channel::Ethernet;
-use pnet::datalink::{self, NetworkInterface};
+use crate::packet::reader::impairment::{ImpairmentPlan, Transmission};
+use crate::packet::reader::link_layer::{LinkConverter, OutboundFrame};
+use crate::packet::reader::replay_monitor::ReplayMonitor;
+use crate::packet::reader::replay_options::ReplayOptions;
+use crate::packet::reader::send_queue::SendQueue;
+use crate::packet::reader::send_schedule::SendSchedule;
+use crate::packet::reader::sender_thread::{QueuedFrame, SenderThread};
+use crate::packet::reader::transmit::{ReplayTarget, TransmitBackend};
+use crate::packet::transform::TransformPipeline;
+use log::{error, info, warn};
+use std::net::Ipv4Addr;
+use std::sync::Arc;
 use std::time::Duration;
-use tokio::time::sleep;
 
 pub struct PacketSender;
 
 impl PacketSender {
-    const MAX_PACKET_SIZE: usize = 1500;
+    /// これより短い間隔のパケットは待機せずに同じバーストとして送る (送信スレッドの待機の精度)
+    const BURST_WINDOW: Duration = Duration::from_micros(50);
+    /// キューへ積む間にランタイムへ処理を譲る間隔 (フレーム数)
+    const YIELD_INTERVAL: usize = 256;
 
-    pub async fn send_packets_with_timing(interface: &NetworkInterface, packets: Vec<(DateTime<Utc>, Vec<u8>)>) -> Result<(), PacketReaderError> {
+    pub async fn send_packets_with_timing(target: &ReplayTarget, packets: Vec<Packet>, options: ReplayOptions) -> Result<(), PacketReaderError> {
         if packets.is_empty() {
             info!("送信するパケットがありません");
             return Ok(());
         }
 
-        let mut tx = match datalink::channel(interface, Default::default()) {
-            Ok(Ethernet(tx, _)) => tx,
-            Ok(_) => return Err(PacketReaderError::UnsupportedChannelType),
-            Err(e) => return Err(PacketReaderError::NetworkError(e.to_string())),
+        let backend = target.open()?;
+        info!("送信先: {}", backend.description());
+
+        let mtu = options.link.mtu;
+        let converter = LinkConverter::new(options.link, backend.source_mac());
+        let transforms = options.transforms;
+        // サニタイズ中は、取り込み時に解析された元のアドレスをログに出さない
+        let redact_flow = transforms.sanitizes();
+
+        let plan = ImpairmentPlan::new(&packets, &options.impairment);
+        if !options.impairment.is_noop() {
+            info!("回線劣化を適用します: seed = {}", plan.seed);
+        }
+
+        let monitor = options.monitor.as_deref();
+        if let Some(monitor) = monitor {
+            monitor.set_total(plan.transmissions.len());
+        }
+
+        let schedule = SendSchedule::new(&plan.transmissions, options.pacing, Self::BURST_WINDOW);
+        let packets = Arc::new(packets);
+        let preparer = FramePreparer {
+            converter,
+            transforms,
+            mtu,
+            monitor,
         };
 
-        info!("パケット送信を開始します: {} パケット", packets.len());
-        let mut last_packet_time = packets[0].0;
+        // 事前読み込みでは全てのフレームを送信開始前に作っておき、送信中は送るだけにする
+        let mut preloaded = options.preload.then(|| {
+            info!("送信するフレームを事前に準備します");
+            let frames: Vec<Option<PreparedFrame<'static>>> =
+                plan.transmissions.iter().map(|transmission| preparer.prepare(&packets[transmission.index], transmission).map(PreparedFrame::into_owned)).collect();
+            let bytes: usize = frames.iter().flatten().map(|frame| frame.size).sum();
+            info!("{} フレーム ({} bytes) を準備しました", frames.iter().flatten().count(), bytes);
+            frames
+        });
 
-        for (i, (timestamp, raw_packet)) in packets.iter().enumerate() {
-            // 前のパケットとの時間差を計算して待機
-            let time_diff = *timestamp - last_packet_time;
-            if time_diff.num_microseconds().unwrap_or(0) > 0 {
-                sleep(Duration::from_micros(time_diff.num_microseconds().unwrap_or(0) as u64)).await;
-            }
+        let queue = Arc::new(SendQueue::new(options.sender.queue_capacity));
+        if let Some(monitor) = monitor {
+            monitor.set_queue_capacity(queue.capacity());
+        }
+        let sender = SenderThread {
+            backend,
+            queue: queue.clone(),
+            packets: packets.clone(),
+            monitor: options.monitor.clone(),
+            timezone: options.timezone,
+            total: plan.transmissions.len(),
+            redact_flow,
+        }
+        .spawn(&options.sender)?;
 
-            if raw_packet.len() > Self::MAX_PACKET_SIZE {
-                error!("パケットサイズが制限を超えています: {} bytes (最大: {} bytes)", raw_packet.len(), Self::MAX_PACKET_SIZE);
-                continue;
+        info!("パケット送信を開始します: {} パケット / {} 回の送信枠", plan.transmissions.len(), schedule.slots.len());
+
+        // 送信スレッドが止まった場合は積み込みをやめ、スレッドの結果を返す
+        let mut full_waits = 0;
+        'slots: for slot in &schedule.slots {
+            for i in slot.transmissions.clone() {
+                let transmission = &plan.transmissions[i];
+                let prepared = match preloaded.as_mut() {
+                    Some(frames) => frames[i].take(),
+                    None => preparer.prepare(&packets[transmission.index], transmission).map(PreparedFrame::into_owned),
+                };
+                let Some(PreparedFrame { frame, size }) = prepared else {
+                    continue;
+                };
+
+                let item = QueuedFrame {
+                    sequence: i,
+                    packet: transmission.index,
+                    slot_at: slot.at,
+                    at: options.pacing.scale(transmission.offset),
+                    frame,
+                    size,
+                };
+                let pushed = queue
+                    .push(item, || {
+                        full_waits += 1;
+                        if let Some(monitor) = monitor {
+                            monitor.record_queue_full();
+                        }
+                    })
+                    .await;
+                if !pushed {
+                    break 'slots;
+                }
+                if let Some(monitor) = monitor {
+                    monitor.record_queue_depth(queue.len());
+                }
+                if i % Self::YIELD_INTERVAL == 0 {
+                    tokio::task::yield_now().await;
+                }
             }
+        }
+        queue.close();
+
+        match tokio::task::spawn_blocking(move || sender.join()).await {
+            Ok(Ok(result)) => result?,
+            _ => return Err(PacketReaderError::ThreadError("送信スレッドが異常終了しました".to_string())),
+        }
+
+        info!("送信キュー: 容量 {}, 満杯で待機 {} 回", queue.capacity(), full_waits);
+        if !options.impairment.is_noop() {
+            let stats = plan.stats;
+            info!(
+                "回線劣化の結果: 破棄 {} / 複製 {} / 順序入れ替え {} / ビット反転 {}",
+                stats.dropped, stats.duplicated, stats.reordered, stats.corrupted
+            );
+        }
+        if target.is_dry_run() {
+            info!("ドライランのため、パケットは実際には送信していません");
+        }
+        info!("パケット送信が完了しました");
+        Ok(())
+    }
+
+    /// バックエンドに溜まったフレームを送り出す。送信の失敗は記録して再生を続ける
+    pub(super) fn flush(backend: &mut dyn TransmitBackend, monitor: Option<&ReplayMonitor>) -> Result<(), PacketReaderError> {
+        match backend.flush() {
+            Err(e @ PacketReaderError::SendError(_)) => {
+                Self::report_error(monitor, e.to_string());
+                Ok(())
+            },
+            result => result,
+        }
+    }
+
+    /// エラーをログへ出力し、進捗を監視している場合はそちらにも記録する
+    pub(super) fn report_error(monitor: Option<&ReplayMonitor>, message: String) {
+        error!("{}", message);
+        if let Some(monitor) = monitor {
+            monitor.record_error(message);
+        }
+    }
+}
+
+/// 送信計画の1件分のフレーム
+struct PreparedFrame<'a> {
+    frame: OutboundFrame<'a>,
+    /// ログと統計に使う送信サイズ
+    size: usize,
+}
+
+impl PreparedFrame<'_> {
+    fn into_owned(self) -> PreparedFrame<'static> {
+        PreparedFrame {
+            frame: self.frame.into_owned(),
+            size: self.size,
+        }
+    }
+}
 
-            match tx.send_to(raw_packet, None) {
-                Some(Ok(_)) => {
-                    info!(
-                        "{index}> 送信したパケット: {packet_size}bytes, timestamp = {timestamp}",
-                        index = i + 1,
-                        packet_size = raw_packet.len(),
-                        timestamp = timestamp.format("%Y-%m-%d %H:%M:%S.%f").to_string()
-                    );
-                    if i % 1000 == 0 {
-                        info!("パケット送信進捗: {}/{}", i + 1, packets.len());
+/// パケットを送信できるフレームに変換する
+///
+/// 変換・ビット反転・サイズの確認を行い、送れないものはエラーを記録して`None`を返す。
+struct FramePreparer<'m> {
+    converter: LinkConverter,
+    transforms: TransformPipeline,
+    /// 送信するL3パケットのサイズの上限
+    mtu: usize,
+    monitor: Option<&'m ReplayMonitor>,
+}
+
+impl FramePreparer<'_> {
+    fn prepare<'a>(&self, packet: &'a Packet, transmission: &Transmission) -> Option<PreparedFrame<'a>> {
+        if packet.is_truncated() {
+            warn!(
+                "切り詰められたパケットをそのまま送信します: キャプチャ長 {} bytes / 元の長さ {} bytes",
+                packet.captured_length(),
+                packet.original_length
+            );
+        }
+
+        let mut frame = match self.converter.convert(packet) {
+            Ok(frame) => frame,
+            Err(e) => {
+                PacketSender::report_error(self.monitor, format!("パケットを送信可能な形式に変換できませんでした ({:?}): {}", packet.link_type, e));
+                return None;
+            },
+        };
+
+        // VLAN/トンネルの変換はEthernetで送るフレームにのみ適用し、L3のまま送るIPv4にはサニタイズのみ適用する
+        if !self.transforms.is_empty() {
+            frame = match frame {
+                OutboundFrame::Ethernet(bytes) => {
+                    let mut bytes = bytes.into_owned();
+                    if let Err(e) = self.transforms.apply(&mut bytes) {
+                        PacketSender::report_error(self.monitor, format!("フレームの変換に失敗しました: {}", e));
+                        return None;
                     }
+                    OutboundFrame::Ethernet(bytes.into())
                 },
-                Some(Err(e)) => {
-                    error!("パケット送信エラー: {}", e);
-                    continue;
+                OutboundFrame::Ipv4 { destination, packet } if self.transforms.sanitizes() => {
+                    let mut packet = packet.into_owned();
+                    if let Err(e) = self.transforms.apply_ipv4(&mut packet) {
+                        PacketSender::report_error(self.monitor, format!("IPパケットのサニタイズに失敗しました: {}", e));
+                        return None;
+                    }
+                    // 宛先も匿名化後のアドレスに合わせる
+                    let destination = packet.get(16..20).map(|octets| Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3])).unwrap_or(destination);
+                    OutboundFrame::Ipv4 {
+                        destination,
+                        packet: packet.into(),
+                    }
                 },
-                None => {
-                    error!("パケット送信エラー: 宛先が指定されていません");
-                    continue;
+                frame => frame,
+            };
+        }
+
+        // ビット反転は全ての変換の後、実際に送るバイト列に対して行う
+        if transmission.corrupt_bit.is_some() {
+            match &mut frame {
+                OutboundFrame::Ethernet(bytes) | OutboundFrame::Ipv4 { packet: bytes, .. } => {
+                    transmission.corrupt(bytes.to_mut());
                 },
             }
+        }
 
-            last_packet_time = *timestamp;
+        if frame.l3_size() > self.mtu {
+            PacketSender::report_error(
+                self.monitor,
+                format!("パケットサイズがMTUを超えています: L3 {} bytes (MTU: {} bytes)", frame.l3_size(), self.mtu),
+            );
+            return None;
         }
 
-        info!("パケット送信が完了しました");
-        Ok(())
+        let size = frame.size();
+        Some(PreparedFrame { frame, size })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::model::LinkType;
+    use crate::packet::reader::send_schedule::Pacing;
+    use crate::packet::reader::transmit::MemorySink;
+    use chrono::{TimeZone, Utc};
+    use std::sync::Arc;
+
+    fn packet(millisecond: i64, link_type: LinkType, data: Vec<u8>) -> Packet {
+        Packet {
+            link_type,
+            ..Packet::new(Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap() + chrono::Duration::milliseconds(millisecond), data)
+        }
+    }
+
+    fn ethernet_frame(last_octet: u8, payload_len: usize) -> Vec<u8> {
+        let mut frame = vec![0x02, 0, 0, 0, 0, last_octet, 0x02, 0, 0, 0, 0, 0xaa, 0x08, 0x00];
+        frame.resize(frame.len() + payload_len, last_octet);
+        frame
+    }
+
+    #[tokio::test]
+    async fn replays_frames_in_order_to_memory_sink() {
+        let ipv4 = vec![0x45, 0, 0, 20, 0, 0, 0, 0, 64, 17, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2];
+        let packets = vec![
+            packet(0, LinkType::Ethernet, ethernet_frame(1, 46)),
+            packet(1, LinkType::RawIp, ipv4.clone()),
+            // MTUを超えるフレームは送らない
+            packet(2, LinkType::Ethernet, ethernet_frame(3, 1501)),
+            packet(3, LinkType::Ethernet, ethernet_frame(4, 46)),
+        ];
+        let sink = MemorySink::default();
+        let monitor = Arc::new(ReplayMonitor::default());
+        let options = ReplayOptions {
+            monitor: Some(monitor.clone()),
+            ..ReplayOptions::default()
+        };
+
+        PacketSender::send_packets_with_timing(&ReplayTarget::Memory(sink.clone()), packets, options).await.unwrap();
+
+        let frames = sink.take_frames();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0], ethernet_frame(1, 46));
+        // 非Ethernetのパケットには送信元MAC (メモリでは0) のEthernetヘッダが付く
+        assert_eq!(&frames[1][12..14], [0x08, 0x00]);
+        assert_eq!(&frames[1][14..], ipv4.as_slice());
+        assert_eq!(frames[2], ethernet_frame(4, 46));
+
+        let snapshot = monitor.snapshot();
+        assert_eq!((snapshot.total, snapshot.sent, snapshot.errors), (4, 3, 1));
+    }
+
+    #[tokio::test]
+    async fn preloaded_top_speed_replay_sends_the_same_frames() {
+        let packets = vec![
+            packet(0, LinkType::Ethernet, ethernet_frame(1, 46)),
+            packet(500, LinkType::Ethernet, ethernet_frame(2, 46)),
+        ];
+        let sink = MemorySink::default();
+        let options = ReplayOptions {
+            pacing: Pacing::TopSpeed,
+            preload: true,
+            ..ReplayOptions::default()
+        };
+
+        PacketSender::send_packets_with_timing(&ReplayTarget::Memory(sink.clone()), packets, options).await.unwrap();
+
+        assert_eq!(sink.take_frames(), [ethernet_frame(1, 46), ethernet_frame(2, 46)]);
     }
 }
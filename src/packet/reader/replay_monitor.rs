@@ -0,0 +1,115 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// 保持するエラーメッセージの件数
+const RECENT_ERROR_LIMIT: usize = 50;
+
+/// 再生の進捗をダッシュボード等から参照するためのカウンタ
+///
+/// 送信ループからは更新のみ行い、参照側は`snapshot`で定期的に読み出す。
+#[derive(Default)]
+pub struct ReplayMonitor {
+    total: AtomicU64,
+    sent: AtomicU64,
+    bytes: AtomicU64,
+    errors: AtomicU64,
+    queue_depth: AtomicU64,
+    queue_capacity: AtomicU64,
+    queue_full_waits: AtomicU64,
+    recent_errors: Mutex<VecDeque<String>>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReplaySnapshot {
+    pub total: u64,
+    pub sent: u64,
+    pub bytes: u64,
+    pub errors: u64,
+    /// 送信スレッドへのキューに溜まっているフレーム数
+    pub queue_depth: u64,
+    pub queue_capacity: u64,
+    /// キューが満杯で準備側が待たされた回数
+    pub queue_full_waits: u64,
+}
+
+impl ReplayMonitor {
+    pub fn set_total(&self, total: usize) {
+        self.total.store(total as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_sent(&self, bytes: usize) {
+        self.sent.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_error(&self, message: String) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut recent_errors) = self.recent_errors.lock() {
+            if recent_errors.len() == RECENT_ERROR_LIMIT {
+                recent_errors.pop_front();
+            }
+            recent_errors.push_back(message);
+        }
+    }
+
+    pub fn set_queue_capacity(&self, capacity: usize) {
+        self.queue_capacity.store(capacity as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_queue_depth(&self, depth: usize) {
+        self.queue_depth.store(depth as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_queue_full(&self) {
+        self.queue_full_waits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> ReplaySnapshot {
+        ReplaySnapshot {
+            total: self.total.load(Ordering::Relaxed),
+            sent: self.sent.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            queue_depth: self.queue_depth.load(Ordering::Relaxed),
+            queue_capacity: self.queue_capacity.load(Ordering::Relaxed),
+            queue_full_waits: self.queue_full_waits.load(Ordering::Relaxed),
+        }
+    }
+
+    /// 新しいものから順に返す
+    pub fn recent_errors(&self) -> Vec<String> {
+        self.recent_errors.lock().map(|recent_errors| recent_errors.iter().rev().cloned().collect()).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_sent_packets_and_errors() {
+        let monitor = ReplayMonitor::default();
+        monitor.set_total(3);
+        monitor.record_sent(100);
+        monitor.record_sent(60);
+        monitor.record_error("送信エラー".to_string());
+
+        let snapshot = monitor.snapshot();
+        assert_eq!((snapshot.total, snapshot.sent, snapshot.bytes, snapshot.errors), (3, 2, 160, 1));
+    }
+
+    #[test]
+    fn keeps_only_recent_errors_newest_first() {
+        let monitor = ReplayMonitor::default();
+        for i in 0..RECENT_ERROR_LIMIT + 5 {
+            monitor.record_error(i.to_string());
+        }
+
+        let recent = monitor.recent_errors();
+        assert_eq!(recent.len(), RECENT_ERROR_LIMIT);
+        assert_eq!(recent.first().map(String::as_str), Some("54"));
+        assert_eq!(recent.last().map(String::as_str), Some("5"));
+        assert_eq!(monitor.snapshot().errors, (RECENT_ERROR_LIMIT + 5) as u64);
+    }
+}
@@ -0,0 +1,32 @@
+use crate::config::Timezone;
+use crate::packet::reader::impairment::ImpairmentOptions;
+use crate::packet::reader::link_layer::LinkLayerOptions;
+use crate::packet::reader::replay_monitor::ReplayMonitor;
+use crate::packet::reader::send_schedule::Pacing;
+use crate::packet::reader::sender_thread::SenderThreadOptions;
+use crate::packet::transform::TransformPipeline;
+use std::sync::Arc;
+
+/// 1回の再生に適用する設定
+#[derive(Default)]
+pub struct ReplayOptions {
+    /// パケットを並列に取得する際の同時接続数
+    pub fetch_concurrency: usize,
+    pub link: LinkLayerOptions,
+    /// Ethernetで送信するフレームに送信直前で適用する変換
+    pub transforms: TransformPipeline,
+    /// 送信計画に適用する回線劣化
+    pub impairment: ImpairmentOptions,
+    /// ログに出すパケットのタイムスタンプの表示に使うタイムゾーン
+    pub timezone: Timezone,
+    /// 再生速度
+    pub pacing: Pacing,
+    /// 送信を始める前に全てのフレームを準備しておく (送信中の変換処理による遅れをなくす)
+    pub preload: bool,
+    /// 送信スレッドとキューの設定
+    pub sender: SenderThreadOptions,
+    /// 指定されていれば送信の進捗を記録する
+    pub monitor: Option<Arc<ReplayMonitor>>,
+    /// `capture_seq`のないテーブルを行の格納位置の順で再生する旧方式を許可する
+    pub legacy_row_order: bool,
+}
@@ -0,0 +1,143 @@
+use crossbeam_queue::ArrayQueue;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+/// 非同期の準備側から送信スレッドへフレームを渡すロックフリーの有界キュー
+///
+/// 満杯の間は準備側が送信側の取り出しを待つ (バックプレッシャー)。送信側が終了した後は積めなくなる。
+pub struct SendQueue<T> {
+    queue: ArrayQueue<T>,
+    /// 送信側が取り出して空きができたことを準備側へ知らせる
+    space: Notify,
+    producer_done: AtomicBool,
+    consumer_done: AtomicBool,
+}
+
+impl<T> SendQueue<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            queue: ArrayQueue::new(capacity.max(1)),
+            space: Notify::new(),
+            producer_done: AtomicBool::new(false),
+            consumer_done: AtomicBool::new(false),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.queue.capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// 空きができるまで待ってから積む。満杯で待った場合は`on_full`を1回呼ぶ
+    ///
+    /// 送信側が既に終了していれば積まずに`false`を返す。
+    pub async fn push(&self, item: T, on_full: impl FnOnce()) -> bool {
+        let mut item = item;
+        let mut on_full = Some(on_full);
+        loop {
+            if self.consumer_done.load(Ordering::Acquire) {
+                return false;
+            }
+            match self.queue.push(item) {
+                Ok(()) => return true,
+                Err(rejected) => {
+                    item = rejected;
+                    if let Some(on_full) = on_full.take() {
+                        on_full();
+                    }
+                    // 待ち手がいない間の通知は許可として残るため、取り出しを取りこぼすことはない
+                    self.space.notified().await;
+                },
+            }
+        }
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        let item = self.queue.pop()?;
+        self.space.notify_one();
+        Some(item)
+    }
+
+    /// これ以上積まないことを送信側へ知らせる
+    pub fn close(&self) {
+        self.producer_done.store(true, Ordering::Release);
+    }
+
+    /// 準備側が積み終わり、キューも空になったか
+    pub fn is_drained(&self) -> bool {
+        self.producer_done.load(Ordering::Acquire) && self.queue.is_empty()
+    }
+
+    /// 送信側の終了を準備側へ知らせる
+    pub fn finish_consumer(&self) {
+        self.consumer_done.store(true, Ordering::Release);
+        self.space.notify_one();
+    }
+}
+
+/// 破棄されるときに送信側の終了をキューへ知らせる
+///
+/// 送信スレッドがエラーやパニックで止まった場合も、準備側が空きを待ち続けないようにする。
+pub struct ConsumerGuard<T>(pub Arc<SendQueue<T>>);
+
+impl<T> Drop for ConsumerGuard<T> {
+    fn drop(&mut self) {
+        self.0.finish_consumer();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn delivers_items_in_order_until_drained() {
+        let queue = SendQueue::new(4);
+        for i in 0..3 {
+            assert!(queue.push(i, || panic!("満杯ではありません")).await);
+        }
+        queue.close();
+
+        assert!(!queue.is_drained());
+        assert_eq!((queue.pop(), queue.pop(), queue.pop(), queue.pop()), (Some(0), Some(1), Some(2), None));
+        assert!(queue.is_drained());
+    }
+
+    #[tokio::test]
+    async fn full_queue_waits_for_consumer() {
+        let queue = Arc::new(SendQueue::new(1));
+        assert!(queue.push(1, || {}).await);
+
+        let consumer = Arc::clone(&queue);
+        let pop = tokio::spawn(async move {
+            tokio::task::yield_now().await;
+            consumer.pop()
+        });
+        let mut waited = false;
+        assert!(queue.push(2, || waited = true).await);
+
+        assert!(waited);
+        assert_eq!(pop.await.unwrap(), Some(1));
+        assert_eq!(queue.pop(), Some(2));
+    }
+
+    #[tokio::test]
+    async fn dropped_consumer_guard_stops_producer() {
+        let queue = Arc::new(SendQueue::new(1));
+        assert!(queue.push(1, || {}).await);
+
+        let consumer = Arc::clone(&queue);
+        std::thread::spawn(move || {
+            let _guard = ConsumerGuard(consumer);
+            panic!("送信スレッドの異常終了");
+        })
+        .join()
+        .unwrap_err();
+
+        assert!(!queue.push(2, || {}).await);
+    }
+}
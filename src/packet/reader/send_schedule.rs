@@ -0,0 +1,117 @@
+use crate::packet::reader::impairment::Transmission;
+use std::fmt;
+use std::ops::Range;
+use std::time::Duration;
+
+/// 再生速度の指定
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pacing {
+    /// 元の間隔に倍率をかけて送る (1.0で等倍)
+    Speed(f64),
+    /// 待機せずにできるだけ速く送る
+    TopSpeed,
+}
+
+impl Default for Pacing {
+    fn default() -> Self {
+        Self::Speed(1.0)
+    }
+}
+
+impl Pacing {
+    /// 先頭パケットからの時間を再生開始からの送信時刻に変換する
+    pub fn scale(&self, offset: Duration) -> Duration {
+        match self {
+            Self::Speed(speed) => offset.div_f64(*speed),
+            Self::TopSpeed => Duration::ZERO,
+        }
+    }
+}
+
+impl fmt::Display for Pacing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Speed(speed) => write!(f, "{}x", speed),
+            Self::TopSpeed => write!(f, "top-speed"),
+        }
+    }
+}
+
+/// 同じ時刻にまとめて送る送信の範囲
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeSlot {
+    /// 再生開始からの送信時刻 (速度の倍率を適用済み)
+    pub at: Duration,
+    /// 送信計画の添字の範囲
+    pub transmissions: Range<usize>,
+}
+
+/// 送信計画を時間枠に区切った送信スケジュール
+///
+/// 枠の先頭から`window`未満の間隔で続く送信は同じ枠にまとめ、枠ごとに1回だけ待機してまとめて送る。
+/// 送信時刻は再生開始からの絶対時刻で持つため、待機や送信の遅れは後続の枠で取り戻される。
+#[derive(Debug, Default)]
+pub struct SendSchedule {
+    pub slots: Vec<TimeSlot>,
+}
+
+impl SendSchedule {
+    /// `transmissions`は送信時刻順に並んでいること
+    pub fn new(transmissions: &[Transmission], pacing: Pacing, window: Duration) -> Self {
+        let mut slots: Vec<TimeSlot> = Vec::new();
+
+        for (index, transmission) in transmissions.iter().enumerate() {
+            let at = pacing.scale(transmission.offset);
+
+            match slots.last_mut() {
+                Some(slot) if at.saturating_sub(slot.at) < window => slot.transmissions.end = index + 1,
+                _ => slots.push(TimeSlot {
+                    at,
+                    transmissions: index..index + 1,
+                }),
+            }
+        }
+
+        Self { slots }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transmissions(offsets_ms: &[u64]) -> Vec<Transmission> {
+        offsets_ms
+            .iter()
+            .enumerate()
+            .map(|(index, offset)| Transmission {
+                index,
+                offset: Duration::from_millis(*offset),
+                corrupt_bit: None,
+            })
+            .collect()
+    }
+
+    fn slots(schedule: &SendSchedule) -> Vec<(u64, Range<usize>)> {
+        schedule.slots.iter().map(|slot| (slot.at.as_millis() as u64, slot.transmissions.clone())).collect()
+    }
+
+    #[test]
+    fn groups_transmissions_within_window() {
+        let schedule = SendSchedule::new(&transmissions(&[0, 0, 1, 5, 9, 10, 30]), Pacing::default(), Duration::from_millis(2));
+        assert_eq!(slots(&schedule), [(0, 0..3), (5, 3..4), (9, 4..6), (30, 6..7)]);
+    }
+
+    #[test]
+    fn applies_speed_before_grouping() {
+        let schedule = SendSchedule::new(&transmissions(&[0, 4, 20]), Pacing::Speed(2.0), Duration::from_millis(3));
+        assert_eq!(slots(&schedule), [(0, 0..2), (10, 2..3)]);
+    }
+
+    #[test]
+    fn top_speed_sends_everything_in_one_slot() {
+        let schedule = SendSchedule::new(&transmissions(&[0, 100, 1000]), Pacing::TopSpeed, Duration::from_millis(1));
+        assert_eq!(slots(&schedule), [(0, 0..3)]);
+        assert!(SendSchedule::new(&[], Pacing::TopSpeed, Duration::from_millis(1)).slots.is_empty());
+    }
+}
@@ -0,0 +1,243 @@
+use crate::config::Timezone;
+use crate::packet::model::Packet;
+use crate::packet::reader::error::PacketReaderError;
+use crate::packet::reader::link_layer::OutboundFrame;
+use crate::packet::reader::packet_sender::PacketSender;
+use crate::packet::reader::replay_monitor::ReplayMonitor;
+use crate::packet::reader::send_queue::{ConsumerGuard, SendQueue};
+use crate::packet::reader::transmit::TransmitBackend;
+use log::{info, trace, warn};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// 送信スレッドの設定
+#[derive(Debug, Clone)]
+pub struct SenderThreadOptions {
+    /// 準備済みのフレームを溜めておくキューの大きさ
+    pub queue_capacity: usize,
+    /// 送信スレッドを固定するCPUの番号
+    pub cpu: Option<usize>,
+    /// 送信スレッドのリアルタイム優先度 (SCHED_FIFO, 1〜99)
+    pub realtime_priority: Option<i32>,
+}
+
+impl Default for SenderThreadOptions {
+    fn default() -> Self {
+        Self {
+            queue_capacity: 4096,
+            cpu: None,
+            realtime_priority: None,
+        }
+    }
+}
+
+/// 送信スレッドへ渡す1フレーム
+pub struct QueuedFrame {
+    /// 送信計画での順番
+    pub sequence: usize,
+    /// 元のパケットの添字 (ログ用)
+    pub packet: usize,
+    /// 所属する送信枠の送信時刻
+    pub slot_at: Duration,
+    /// このフレーム自身の送信時刻
+    pub at: Duration,
+    pub frame: OutboundFrame<'static>,
+    pub size: usize,
+}
+
+/// tokioのランタイムとは別のOSスレッドで、キューから受け取ったフレームを送信時刻どおりに送る
+///
+/// ブロッキングする送信のシステムコールや精密な待機で、ランタイム上の他のタスクを止めないようにする。
+/// 送信時刻の直前まではスリープし、残りはスピンして待つ。
+pub struct SenderThread {
+    pub backend: Box<dyn TransmitBackend>,
+    pub queue: Arc<SendQueue<QueuedFrame>>,
+    pub packets: Arc<Vec<Packet>>,
+    pub monitor: Option<Arc<ReplayMonitor>>,
+    pub timezone: Timezone,
+    /// 送信計画の件数 (進捗のログ用)
+    pub total: usize,
+    /// サニタイズ中は、取り込み時に解析された元のアドレスをログに出さない
+    pub redact_flow: bool,
+}
+
+impl SenderThread {
+    /// 送信時刻までの残りがこれより短くなったらスリープせずにスピンする
+    const SPIN_THRESHOLD: Duration = Duration::from_micros(200);
+    /// キューが空のときにスリープせずに待つ回数
+    const IDLE_YIELDS: u32 = 64;
+    const IDLE_SLEEP: Duration = Duration::from_micros(50);
+
+    pub fn spawn(self, options: &SenderThreadOptions) -> Result<JoinHandle<Result<(), PacketReaderError>>, PacketReaderError> {
+        let options = options.clone();
+        thread::Builder::new()
+            .name("packet-sender".to_string())
+            .spawn(move || {
+                // エラーやパニックで止まった場合も準備側に知らせ、キューへの積み込みを止めさせる
+                let _guard = ConsumerGuard(self.queue.clone());
+                configure_current_thread(&options);
+                self.run()
+            })
+            .map_err(|e| PacketReaderError::ThreadError(format!("送信スレッドを起動できませんでした: {}", e)))
+    }
+
+    fn run(mut self) -> Result<(), PacketReaderError> {
+        let monitor = self.monitor.clone();
+        let monitor = monitor.as_deref();
+        let mut started: Option<Instant> = None;
+        let mut current_slot: Option<Duration> = None;
+        let mut unflushed = false;
+        let mut idle_count = 0;
+
+        loop {
+            let Some(item) = self.queue.pop() else {
+                if self.queue.is_drained() {
+                    break;
+                }
+                // 準備が追いついていない間も、溜めたフレームは先に送り出しておく
+                if unflushed {
+                    PacketSender::flush(self.backend.as_mut(), monitor)?;
+                    unflushed = false;
+                }
+                idle_count += 1;
+                if idle_count < Self::IDLE_YIELDS {
+                    thread::yield_now();
+                } else {
+                    thread::sleep(Self::IDLE_SLEEP);
+                }
+                continue;
+            };
+            idle_count = 0;
+            if let Some(monitor) = monitor {
+                monitor.record_queue_depth(self.queue.len());
+            }
+
+            // 最初のフレームを受け取った時点を再生の開始とする
+            let started = match started {
+                Some(started) => started,
+                None => {
+                    self.backend.start()?;
+                    *started.insert(Instant::now())
+                },
+            };
+
+            if current_slot != Some(item.slot_at) {
+                // 枠の分は次の待機に入る前に送り出す
+                if unflushed {
+                    PacketSender::flush(self.backend.as_mut(), monitor)?;
+                    unflushed = false;
+                }
+                wait_until(started + item.slot_at);
+                current_slot = Some(item.slot_at);
+            }
+
+            match self.backend.send_at(&item.frame, item.at) {
+                Ok(()) => {
+                    unflushed = true;
+                    if let Some(monitor) = monitor {
+                        monitor.record_sent(item.size);
+                    }
+                    // 1パケットごとのログは送信の間隔を乱さないようtraceにする
+                    if log::log_enabled!(log::Level::Trace) {
+                        let packet = &self.packets[item.packet];
+                        let timestamp = self.timezone.localize(packet.timestamp).format("%Y-%m-%d %H:%M:%S%.6f %:z");
+                        if self.redact_flow {
+                            trace!("{}> 送信したパケット: {}bytes, timestamp = {}", item.sequence + 1, item.size, timestamp);
+                        } else {
+                            trace!(
+                                "{}> 送信したパケット: {}bytes, timestamp = {}, {}",
+                                item.sequence + 1,
+                                item.size,
+                                timestamp,
+                                packet.metadata
+                            );
+                        }
+                    }
+                    if item.sequence % 1000 == 0 {
+                        info!("パケット送信進捗: {}/{}", item.sequence + 1, self.total);
+                    }
+                },
+                // 送信できなかったパケットは飛ばし、送信先自体の問題は再生を中止する
+                Err(e @ PacketReaderError::SendError(_)) => PacketSender::report_error(monitor, e.to_string()),
+                Err(e) => return Err(e),
+            }
+        }
+
+        PacketSender::flush(self.backend.as_mut(), monitor)?;
+        self.backend.finish()
+    }
+}
+
+fn wait_until(deadline: Instant) {
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return;
+        }
+        let remaining = deadline - now;
+        if remaining > SenderThread::SPIN_THRESHOLD {
+            thread::sleep(remaining - SenderThread::SPIN_THRESHOLD);
+        } else {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+/// CPUの固定とリアルタイム優先度の設定 (失敗しても警告だけで送信は続ける)
+fn configure_current_thread(options: &SenderThreadOptions) {
+    if let Some(cpu) = options.cpu {
+        match pin_to_cpu(cpu) {
+            Ok(()) => info!("送信スレッドをCPU {} に固定しました", cpu),
+            Err(e) => warn!("送信スレッドをCPU {} に固定できませんでした: {}", cpu, e),
+        }
+    }
+    if let Some(priority) = options.realtime_priority {
+        match set_realtime_priority(priority) {
+            Ok(()) => info!("送信スレッドの優先度をSCHED_FIFO {} にしました", priority),
+            Err(e) => warn!("送信スレッドの優先度を変更できませんでした (CAP_SYS_NICEが必要です): {}", e),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn pin_to_cpu(cpu: usize) -> std::io::Result<()> {
+    // CPU_SETは範囲外の番号でパニックするため先に確認する
+    if cpu >= libc::CPU_SETSIZE as usize {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("CPUの番号は{}未満で指定してください", libc::CPU_SETSIZE),
+        ));
+    }
+    // SAFETY: cpu_set_tは全て0で初期化してよく、cpuはCPU_SETSIZE未満であることを確認済み
+    let result = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set)
+    };
+    if result < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn set_realtime_priority(priority: i32) -> std::io::Result<()> {
+    let param = libc::sched_param { sched_priority: priority };
+    // SAFETY: 呼び出し元のスレッド自身の設定だけを変更する
+    let result = unsafe { libc::pthread_setschedparam(libc::pthread_self(), libc::SCHED_FIFO, &param) };
+    if result != 0 {
+        return Err(std::io::Error::from_raw_os_error(result));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_to_cpu(_cpu: usize) -> std::io::Result<()> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "この環境では対応していません"))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_realtime_priority(_priority: i32) -> std::io::Result<()> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "この環境では対応していません"))
+}
@@ -0,0 +1,112 @@
+use crate::packet::reader::error::PacketReaderError;
+use crate::packet::reader::link_layer::OutboundFrame;
+use crate::packet::reader::transmit::raw_ip::RawIpSender;
+use crate::packet::reader::transmit::transmit_backend::TransmitBackend;
+use log::warn;
+use pnet::datalink::NetworkInterface;
+use pnet::util::MacAddr;
+use std::io;
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+/// AF_PACKETソケットで直接送信する
+///
+/// `PACKET_QDISC_BYPASS`でqdisc (tc) を経由せずにドライバへ渡すため、送信側のキューイングによる遅延がなくなる。
+/// その代わりtcでの帯域制御やnetemは効かない。
+pub struct AfPacketBackend {
+    socket: OwnedFd,
+    raw_ip: RawIpSender,
+    interface: NetworkInterface,
+    qdisc_bypass: bool,
+}
+
+impl AfPacketBackend {
+    pub fn open(interface: &NetworkInterface) -> Result<Self, PacketReaderError> {
+        let socket = open_packet_socket(interface)?;
+
+        // 古いカーネル (3.14未満) では使えないため、失敗しても通常の経路で送る
+        let qdisc_bypass = match set_socket_option(&socket, libc::SOL_PACKET, libc::PACKET_QDISC_BYPASS, 1) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("PACKET_QDISC_BYPASSを有効にできませんでした。qdiscを経由して送信します: {}", e);
+                false
+            },
+        };
+
+        Ok(Self {
+            socket,
+            raw_ip: RawIpSender::default(),
+            interface: interface.clone(),
+            qdisc_bypass,
+        })
+    }
+}
+
+impl TransmitBackend for AfPacketBackend {
+    fn description(&self) -> String {
+        format!("{} (AF_PACKET{})", self.interface.name, if self.qdisc_bypass { ", qdisc bypass" } else { "" })
+    }
+
+    fn source_mac(&self) -> MacAddr {
+        self.interface.mac.unwrap_or_else(MacAddr::zero)
+    }
+
+    fn send(&mut self, frame: &OutboundFrame) -> Result<(), PacketReaderError> {
+        match frame {
+            OutboundFrame::Ethernet(bytes) => {
+                // SAFETY: 有効なソケットと、長さを渡したスライスへのポインタのみを使う
+                let sent = unsafe { libc::send(self.socket.as_raw_fd(), bytes.as_ptr().cast(), bytes.len(), 0) };
+                if sent < 0 {
+                    return Err(PacketReaderError::SendError(io::Error::last_os_error().to_string()));
+                }
+                Ok(())
+            },
+            OutboundFrame::Ipv4 { destination, packet } => self.raw_ip.send(*destination, packet),
+        }
+    }
+}
+
+/// 送信専用のAF_PACKET (SOCK_RAW) ソケットを開き、インターフェースにbindする
+///
+/// プロトコルに0を指定し、受信したフレームがソケットに溜まらないようにする。
+pub(super) fn open_packet_socket(interface: &NetworkInterface) -> Result<OwnedFd, PacketReaderError> {
+    // SAFETY: 戻り値を検査し、成功した場合のみ所有権をOwnedFdへ移す
+    let fd = unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW, 0) };
+    if fd < 0 {
+        return Err(PacketReaderError::NetworkError(format!(
+            "AF_PACKETソケットを開けませんでした: {}",
+            io::Error::last_os_error()
+        )));
+    }
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    // SAFETY: sockaddr_llは全て0で初期化してよい
+    let mut address: libc::sockaddr_ll = unsafe { mem::zeroed() };
+    address.sll_family = libc::AF_PACKET as u16;
+    address.sll_ifindex = interface.index as i32;
+    let result = unsafe {
+        libc::bind(
+            socket.as_raw_fd(),
+            (&address as *const libc::sockaddr_ll).cast(),
+            mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+        )
+    };
+    if result < 0 {
+        return Err(PacketReaderError::NetworkError(format!(
+            "{} へbindできませんでした: {}",
+            interface.name,
+            io::Error::last_os_error()
+        )));
+    }
+
+    Ok(socket)
+}
+
+pub(super) fn set_socket_option<T>(socket: &OwnedFd, level: libc::c_int, name: libc::c_int, value: T) -> io::Result<()> {
+    // SAFETY: valueは呼び出しの間有効で、その大きさをそのまま渡す
+    let result = unsafe { libc::setsockopt(socket.as_raw_fd(), level, name, (&value as *const T).cast(), mem::size_of::<T>() as libc::socklen_t) };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use crate::packet::reader::error::PacketReaderError;
+use crate::packet::reader::link_layer::OutboundFrame;
+use crate::packet::reader::transmit::transmit_backend::TransmitBackend;
+#[cfg(test)]
+use std::sync::{Arc, Mutex};
+
+/// 送信したフレームをメモリに保持する
+///
+/// クローンしたものは同じバッファを共有するため、再生に渡した後も元の値から結果を読み出せる。
+/// 再生結果の検証向けで、L3のIPv4パケットはMACアドレスが0のEthernetフレームとして保持する。
+#[cfg(test)]
+#[derive(Debug, Clone, Default)]
+pub struct MemorySink {
+    frames: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+#[cfg(test)]
+impl MemorySink {
+    /// これまでに送信されたフレームを取り出す
+    pub fn take_frames(&self) -> Vec<Vec<u8>> {
+        self.frames.lock().map(|mut frames| std::mem::take(&mut *frames)).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+impl TransmitBackend for MemorySink {
+    fn description(&self) -> String {
+        "メモリ".to_string()
+    }
+
+    fn send(&mut self, frame: &OutboundFrame) -> Result<(), PacketReaderError> {
+        let mut frames = self.frames.lock().map_err(|_| PacketReaderError::OutputError("バッファのロックに失敗しました".to_string()))?;
+        frames.push(frame.to_ethernet().into_owned());
+        Ok(())
+    }
+}
+
+/// 何も送らずに破棄する (出力先を指定しないドライラン)
+pub struct DiscardBackend;
+
+impl TransmitBackend for DiscardBackend {
+    fn description(&self) -> String {
+        "破棄".to_string()
+    }
+
+    fn send(&mut self, _frame: &OutboundFrame) -> Result<(), PacketReaderError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,21 @@
+#[cfg(target_os = "linux")]
+mod af_packet;
+mod memory_sink;
+mod pcap_file;
+mod pnet_channel;
+mod raw_ip;
+mod replay_target;
+#[cfg(target_os = "linux")]
+mod sendmmsg;
+#[cfg(target_os = "linux")]
+mod tap_device;
+mod transmit_backend;
+#[cfg(target_os = "linux")]
+mod tx_ring;
+#[cfg(target_os = "linux")]
+mod txtime;
+
+#[cfg(test)]
+pub(crate) use memory_sink::MemorySink;
+pub use replay_target::{BackendKind, DryRunOptions, ReplayTarget};
+pub use transmit_backend::TransmitBackend;
@@ -0,0 +1,45 @@
+use crate::packet::export::PcapWriter;
+use crate::packet::model::LinkType;
+use crate::packet::reader::error::PacketReaderError;
+use crate::packet::reader::link_layer::OutboundFrame;
+use crate::packet::reader::transmit::transmit_backend::TransmitBackend;
+use chrono::Utc;
+use log::info;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+/// 送信するはずだったフレームをpcapへ書き出す
+///
+/// タイムスタンプには送信したはずの時刻 (待機後の現在時刻) を使うため、ペーシングの結果をそのまま確認できる。
+pub struct PcapFileBackend {
+    writer: PcapWriter<BufWriter<File>>,
+    path: PathBuf,
+}
+
+impl PcapFileBackend {
+    pub fn create(path: &Path) -> Result<Self, PacketReaderError> {
+        let file = File::create(path).map_err(|e| PacketReaderError::OutputError(format!("{}: {}", path.display(), e)))?;
+        let writer = PcapWriter::new(BufWriter::new(file), LinkType::LINKTYPE_ETHERNET, PcapWriter::<BufWriter<File>>::DEFAULT_SNAP_LEN)
+            .map_err(|e| PacketReaderError::OutputError(e.to_string()))?;
+
+        Ok(Self { writer, path: path.to_path_buf() })
+    }
+}
+
+impl TransmitBackend for PcapFileBackend {
+    fn description(&self) -> String {
+        format!("{} (pcap)", self.path.display())
+    }
+
+    fn send(&mut self, frame: &OutboundFrame) -> Result<(), PacketReaderError> {
+        let bytes = frame.to_ethernet();
+        self.writer.write_packet(Utc::now(), &bytes, bytes.len()).map_err(|e| PacketReaderError::OutputError(e.to_string()))
+    }
+
+    fn finish(&mut self) -> Result<(), PacketReaderError> {
+        self.writer.flush().map_err(|e| PacketReaderError::OutputError(e.to_string()))?;
+        info!("フレームを書き出しました: {}", self.path.display());
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use crate::packet::reader::error::PacketReaderError;
+use crate::packet::reader::link_layer::OutboundFrame;
+use crate::packet::reader::transmit::raw_ip::RawIpSender;
+use crate::packet::reader::transmit::transmit_backend::TransmitBackend;
+use pnet::datalink::Channel::Ethernet;
+use pnet::datalink::{self, DataLinkSender, NetworkInterface};
+use pnet::util::MacAddr;
+
+/// pnetのEthernetチャネルで送信する (既定のバックエンド)
+pub struct PnetChannelBackend {
+    tx: Box<dyn DataLinkSender>,
+    raw_ip: RawIpSender,
+    interface: NetworkInterface,
+}
+
+impl PnetChannelBackend {
+    pub fn open(interface: &NetworkInterface) -> Result<Self, PacketReaderError> {
+        let tx = match datalink::channel(interface, Default::default()) {
+            Ok(Ethernet(tx, _)) => tx,
+            Ok(_) => return Err(PacketReaderError::UnsupportedChannelType),
+            Err(e) => return Err(PacketReaderError::NetworkError(e.to_string())),
+        };
+
+        Ok(Self {
+            tx,
+            raw_ip: RawIpSender::default(),
+            interface: interface.clone(),
+        })
+    }
+}
+
+impl TransmitBackend for PnetChannelBackend {
+    fn description(&self) -> String {
+        format!("{} (pnet)", self.interface.name)
+    }
+
+    fn source_mac(&self) -> MacAddr {
+        self.interface.mac.unwrap_or_else(MacAddr::zero)
+    }
+
+    fn send(&mut self, frame: &OutboundFrame) -> Result<(), PacketReaderError> {
+        match frame {
+            OutboundFrame::Ethernet(bytes) => match self.tx.send_to(bytes, None) {
+                Some(result) => result.map_err(|e| PacketReaderError::SendError(e.to_string())),
+                None => Err(PacketReaderError::SendError("宛先が指定されていません".to_string())),
+            },
+            OutboundFrame::Ipv4 { destination, packet } => self.raw_ip.send(*destination, packet),
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use crate::packet::reader::error::PacketReaderError;
+use log::info;
+use pnet::packet::ip::IpNextHeaderProtocol;
+use pnet::packet::ipv4::Ipv4Packet;
+use pnet::transport::{transport_channel, TransportChannelType, TransportSender};
+use std::net::{IpAddr, Ipv4Addr};
+
+/// IPv4パケットをL3のままカーネルの経路で送るrawソケット
+///
+/// 非Ethernetのパケットを実際にL3で送るときだけ開く。
+#[derive(Default)]
+pub struct RawIpSender {
+    tx: Option<TransportSender>,
+}
+
+impl RawIpSender {
+    /// IP_HDRINCL付きでどのプロトコルも送れるIPPROTO_RAW
+    const IPPROTO_RAW: u8 = 255;
+
+    pub fn send(&mut self, destination: Ipv4Addr, packet: &[u8]) -> Result<(), PacketReaderError> {
+        let tx = match self.tx.as_mut() {
+            Some(tx) => tx,
+            None => self.tx.insert(Self::open()?),
+        };
+        let ipv4 = Ipv4Packet::new(packet).ok_or_else(|| PacketReaderError::SendError("IPv4パケットとして解釈できません".to_string()))?;
+        tx.send_to(ipv4, IpAddr::V4(destination)).map(|_| ()).map_err(|e| PacketReaderError::SendError(e.to_string()))
+    }
+
+    fn open() -> Result<TransportSender, PacketReaderError> {
+        let channel_type = TransportChannelType::Layer3(IpNextHeaderProtocol::new(Self::IPPROTO_RAW));
+        let (tx, _) = transport_channel(4096, channel_type).map_err(|e| PacketReaderError::NetworkError(e.to_string()))?;
+        info!("L3送信用のrawソケットを開きました");
+        Ok(tx)
+    }
+}
@@ -0,0 +1,145 @@
+use crate::packet::reader::error::PacketReaderError;
+#[cfg(target_os = "linux")]
+use crate::packet::reader::transmit::af_packet::AfPacketBackend;
+use crate::packet::reader::transmit::memory_sink::DiscardBackend;
+#[cfg(test)]
+use crate::packet::reader::transmit::memory_sink::MemorySink;
+use crate::packet::reader::transmit::pcap_file::PcapFileBackend;
+use crate::packet::reader::transmit::pnet_channel::PnetChannelBackend;
+#[cfg(target_os = "linux")]
+use crate::packet::reader::transmit::sendmmsg::SendmmsgBackend;
+#[cfg(target_os = "linux")]
+use crate::packet::reader::transmit::tap_device::TapDeviceBackend;
+use crate::packet::reader::transmit::transmit_backend::TransmitBackend;
+#[cfg(target_os = "linux")]
+use crate::packet::reader::transmit::tx_ring::TxRingBackend;
+#[cfg(target_os = "linux")]
+use crate::packet::reader::transmit::txtime::TxTimeBackend;
+use pnet::datalink::NetworkInterface;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// インターフェースへ送信する際のバックエンドの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackendKind {
+    /// pnetのEthernetチャネル
+    #[default]
+    Pnet,
+    /// PACKET_QDISC_BYPASSを有効にしたAF_PACKETソケット
+    AfPacket,
+    /// メモリマップした送信リング (PACKET_TX_RING) を使うAF_PACKETソケット
+    TxRing,
+    /// 送信枠ごとにsendmmsgでまとめて送るAF_PACKETソケット
+    Sendmmsg,
+    /// SO_TXTIMEで送信時刻を指定し、ETF qdiscに送り出させる (ETFがなければ通常のAF_PACKET)
+    TxTime,
+    /// TAPデバイス (--interfaceの名前で指定し、なければ作成する)
+    Tap,
+    /// pcapファイル (--outputで指定する)
+    Pcap,
+}
+
+impl BackendKind {
+    /// 既存のインターフェースを選んで送信するか
+    pub fn uses_interface(self) -> bool {
+        matches!(self, Self::Pnet | Self::AfPacket | Self::TxRing | Self::Sendmmsg | Self::TxTime)
+    }
+}
+
+impl FromStr for BackendKind {
+    type Err = PacketReaderError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "pnet" => Ok(Self::Pnet),
+            "af_packet" => Ok(Self::AfPacket),
+            "tx_ring" => Ok(Self::TxRing),
+            "sendmmsg" => Ok(Self::Sendmmsg),
+            "txtime" => Ok(Self::TxTime),
+            "tap" => Ok(Self::Tap),
+            "pcap" => Ok(Self::Pcap),
+            other => Err(PacketReaderError::ConfigurationError(format!("不明な送信バックエンドです: {}", other))),
+        }
+    }
+}
+
+/// 実際には送信しない再生の設定
+#[derive(Debug, Clone, Default)]
+pub struct DryRunOptions {
+    /// 送信するはずだったフレームを書き出すpcap。`None`なら破棄する
+    pub output: Option<PathBuf>,
+}
+
+/// 再生したパケットの送り先
+pub enum ReplayTarget {
+    Pnet(NetworkInterface),
+    AfPacket(NetworkInterface),
+    TxRing(NetworkInterface),
+    Sendmmsg(NetworkInterface),
+    TxTime(NetworkInterface),
+    /// TAPデバイス (存在しなければ作成する)
+    Tap(String),
+    /// 送信したフレームをpcapへ書き出す
+    PcapFile(PathBuf),
+    /// 送信せずに処理だけを行う
+    DryRun(DryRunOptions),
+    /// 送信したフレームをメモリに保持する
+    #[cfg(test)]
+    Memory(MemorySink),
+}
+
+impl ReplayTarget {
+    pub fn open(&self) -> Result<Box<dyn TransmitBackend>, PacketReaderError> {
+        Ok(match self {
+            Self::Pnet(interface) => Box::new(PnetChannelBackend::open(interface)?),
+            #[cfg(target_os = "linux")]
+            Self::AfPacket(interface) => Box::new(AfPacketBackend::open(interface)?),
+            #[cfg(target_os = "linux")]
+            Self::TxRing(interface) => Box::new(TxRingBackend::open(interface)?),
+            #[cfg(target_os = "linux")]
+            Self::Sendmmsg(interface) => Box::new(SendmmsgBackend::open(interface)?),
+            #[cfg(target_os = "linux")]
+            Self::TxTime(interface) => TxTimeBackend::open_with_fallback(interface)?,
+            #[cfg(target_os = "linux")]
+            Self::Tap(name) => Box::new(TapDeviceBackend::open(name)?),
+            #[cfg(not(target_os = "linux"))]
+            Self::AfPacket(_) | Self::TxRing(_) | Self::Sendmmsg(_) | Self::TxTime(_) | Self::Tap(_) => return Err(PacketReaderError::UnsupportedChannelType),
+            Self::PcapFile(path) | Self::DryRun(DryRunOptions { output: Some(path) }) => Box::new(PcapFileBackend::create(path)?),
+            Self::DryRun(DryRunOptions { output: None }) => Box::new(DiscardBackend),
+            #[cfg(test)]
+            Self::Memory(sink) => Box::new(sink.clone()),
+        })
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        matches!(self, Self::DryRun(_))
+    }
+
+    /// 履歴に残す送信方式の名前
+    pub fn backend_name(&self) -> &'static str {
+        match self {
+            Self::Pnet(_) => "pnet",
+            Self::AfPacket(_) => "af_packet",
+            Self::TxRing(_) => "tx_ring",
+            Self::Sendmmsg(_) => "sendmmsg",
+            Self::TxTime(_) => "txtime",
+            Self::Tap(_) => "tap",
+            Self::PcapFile(_) => "pcap",
+            Self::DryRun(_) => "dry_run",
+            #[cfg(test)]
+            Self::Memory(_) => "memory",
+        }
+    }
+
+    /// 送信先のインターフェース名 (pcapへの書き出しとドライランでは書き出し先のファイル)
+    pub fn destination(&self) -> Option<String> {
+        match self {
+            Self::Pnet(interface) | Self::AfPacket(interface) | Self::TxRing(interface) | Self::Sendmmsg(interface) | Self::TxTime(interface) => Some(interface.name.clone()),
+            Self::Tap(name) => Some(name.clone()),
+            Self::PcapFile(path) => Some(path.display().to_string()),
+            Self::DryRun(DryRunOptions { output }) => output.as_ref().map(|path| path.display().to_string()),
+            #[cfg(test)]
+            Self::Memory(_) => None,
+        }
+    }
+}
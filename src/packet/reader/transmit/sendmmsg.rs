@@ -0,0 +1,126 @@
+use crate::packet::reader::error::PacketReaderError;
+use crate::packet::reader::link_layer::OutboundFrame;
+use crate::packet::reader::transmit::af_packet::{open_packet_socket, set_socket_option};
+use crate::packet::reader::transmit::raw_ip::RawIpSender;
+use crate::packet::reader::transmit::transmit_backend::TransmitBackend;
+use log::warn;
+use pnet::datalink::NetworkInterface;
+use pnet::util::MacAddr;
+use std::io;
+use std::mem;
+use std::os::fd::{AsRawFd, OwnedFd};
+
+/// 送信枠のフレームを溜めて、AF_PACKETのrawソケットへ`sendmmsg`でまとめて送る
+///
+/// `flush` (送信枠の終わり) か、溜めたフレームが`BATCH_SIZE`に達した時点で送る。
+/// フレームのバッファは使い回すため、送信中の確保は最初の数枠だけで済む。
+pub struct SendmmsgBackend {
+    socket: OwnedFd,
+    buffers: Vec<Vec<u8>>,
+    /// `buffers`のうち送信待ちのフレームの数
+    queued: usize,
+    raw_ip: RawIpSender,
+    interface: NetworkInterface,
+}
+
+impl SendmmsgBackend {
+    /// 1回の`sendmmsg`で渡すフレームの上限 (UIO_MAXIOV)
+    const BATCH_SIZE: usize = 1024;
+
+    pub fn open(interface: &NetworkInterface) -> Result<Self, PacketReaderError> {
+        let socket = open_packet_socket(interface)?;
+        if let Err(e) = set_socket_option(&socket, libc::SOL_PACKET, libc::PACKET_QDISC_BYPASS, 1) {
+            warn!("PACKET_QDISC_BYPASSを有効にできませんでした。qdiscを経由して送信します: {}", e);
+        }
+
+        Ok(Self {
+            socket,
+            buffers: Vec::new(),
+            queued: 0,
+            raw_ip: RawIpSender::default(),
+            interface: interface.clone(),
+        })
+    }
+
+    fn queue(&mut self, bytes: &[u8]) -> Result<(), PacketReaderError> {
+        if self.queued == self.buffers.len() {
+            self.buffers.push(Vec::new());
+        }
+        let buffer = &mut self.buffers[self.queued];
+        buffer.clear();
+        buffer.extend_from_slice(bytes);
+        self.queued += 1;
+
+        if self.queued == Self::BATCH_SIZE {
+            self.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl TransmitBackend for SendmmsgBackend {
+    fn description(&self) -> String {
+        format!("{} (AF_PACKET sendmmsg, 最大 {} フレーム/回)", self.interface.name, Self::BATCH_SIZE)
+    }
+
+    fn source_mac(&self) -> MacAddr {
+        self.interface.mac.unwrap_or_else(MacAddr::zero)
+    }
+
+    fn send(&mut self, frame: &OutboundFrame) -> Result<(), PacketReaderError> {
+        match frame {
+            OutboundFrame::Ethernet(bytes) => self.queue(bytes),
+            OutboundFrame::Ipv4 { destination, packet } => self.raw_ip.send(*destination, packet),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), PacketReaderError> {
+        let queued = mem::take(&mut self.queued);
+        if queued == 0 {
+            return Ok(());
+        }
+
+        let mut iovecs: Vec<libc::iovec> = self.buffers[..queued]
+            .iter()
+            .map(|buffer| libc::iovec {
+                iov_base: buffer.as_ptr() as *mut libc::c_void,
+                iov_len: buffer.len(),
+            })
+            .collect();
+        let mut messages: Vec<libc::mmsghdr> = iovecs
+            .iter_mut()
+            .map(|iovec| {
+                // SAFETY: mmsghdrは全て0で初期化してよい (宛先はbind済みのため指定しない)
+                let mut message: libc::mmsghdr = unsafe { mem::zeroed() };
+                message.msg_hdr.msg_iov = iovec;
+                message.msg_hdr.msg_iovlen = 1;
+                message
+            })
+            .collect();
+
+        // 一部だけ送られた場合は残りを送り直す
+        let mut sent = 0;
+        while sent < queued {
+            // SAFETY: messagesとその指すバッファはこの呼び出しの間有効
+            let result = unsafe { libc::sendmmsg(self.socket.as_raw_fd(), messages[sent..].as_mut_ptr(), (queued - sent) as libc::c_uint, 0) };
+            if result < 0 {
+                let error = io::Error::last_os_error();
+                if error.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(PacketReaderError::SendError(format!(
+                    "{} フレーム中 {} フレームを送信できませんでした: {}",
+                    queued,
+                    queued - sent,
+                    error
+                )));
+            }
+            sent += result as usize;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), PacketReaderError> {
+        self.flush()
+    }
+}
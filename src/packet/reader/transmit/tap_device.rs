@@ -0,0 +1,68 @@
+use crate::packet::reader::error::PacketReaderError;
+use crate::packet::reader::link_layer::OutboundFrame;
+use crate::packet::reader::transmit::transmit_backend::TransmitBackend;
+use log::info;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::mem;
+use std::os::fd::AsRawFd;
+
+/// TAPデバイスへフレームを書き込む
+///
+/// 書き込んだフレームはTAPデバイスが受信したものとしてカーネルに渡るため、
+/// ネットワーク名前空間やブリッジと組み合わせて実機なしで再生を確認できる。
+/// 指定した名前のデバイスがなければ作成する (再生の終了とともに削除される)。
+pub struct TapDeviceBackend {
+    device: File,
+    name: String,
+}
+
+impl TapDeviceBackend {
+    const TUN_DEVICE: &'static str = "/dev/net/tun";
+
+    pub fn open(name: &str) -> Result<Self, PacketReaderError> {
+        if name.is_empty() || name.len() >= libc::IFNAMSIZ {
+            return Err(PacketReaderError::ConfigurationError(format!("TAPデバイスの名前が不正です: {}", name)));
+        }
+
+        let device = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(Self::TUN_DEVICE)
+            .map_err(|e| PacketReaderError::NetworkError(format!("{} を開けませんでした: {}", Self::TUN_DEVICE, e)))?;
+
+        // SAFETY: ifreqは全て0で初期化してよく、名前はIFNAMSIZ未満であることを確認済み
+        let mut request: libc::ifreq = unsafe { mem::zeroed() };
+        for (destination, source) in request.ifr_name.iter_mut().zip(name.bytes()) {
+            *destination = source as libc::c_char;
+        }
+        request.ifr_ifru.ifru_flags = (libc::IFF_TAP | libc::IFF_NO_PI) as libc::c_short;
+
+        let result = unsafe { libc::ioctl(device.as_raw_fd(), libc::TUNSETIFF, &request as *const libc::ifreq) };
+        if result < 0 {
+            return Err(PacketReaderError::NetworkError(format!(
+                "TAPデバイス {} を作成できませんでした: {}",
+                name,
+                io::Error::last_os_error()
+            )));
+        }
+
+        info!("TAPデバイス {} を開きました (停止している場合は ip link set {} up で起動してください)", name, name);
+        Ok(Self { device, name: name.to_string() })
+    }
+}
+
+impl TransmitBackend for TapDeviceBackend {
+    fn description(&self) -> String {
+        format!("{} (TAP)", self.name)
+    }
+
+    fn send(&mut self, frame: &OutboundFrame) -> Result<(), PacketReaderError> {
+        match frame {
+            OutboundFrame::Ethernet(bytes) => self.device.write_all(bytes).map_err(|e| PacketReaderError::SendError(e.to_string())),
+            OutboundFrame::Ipv4 { .. } => Err(PacketReaderError::SendError(
+                "TAPデバイスへはL3のまま送信できません (REPLAY_LINK_OUTPUT=ethernet を使ってください)".to_string(),
+            )),
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use crate::packet::reader::error::PacketReaderError;
+use crate::packet::reader::link_layer::OutboundFrame;
+use pnet::util::MacAddr;
+use std::time::Duration;
+
+/// 変換済みのフレームを実際に送り出す先
+///
+/// ペーシング・変換・回線劣化は`PacketSender`側で行い、バックエンドは渡されたフレームを1つずつ送るだけにする。
+/// 個々のパケットの送信失敗は`PacketReaderError::SendError`で返し、それ以外のエラーで再生は中止される。
+pub trait TransmitBackend: Send {
+    /// ログに表示する送信先の説明
+    fn description(&self) -> String;
+
+    /// 非EthernetのパケットにL2ヘッダを合成する際の送信元MAC
+    fn source_mac(&self) -> MacAddr {
+        MacAddr::zero()
+    }
+
+    /// 最初のフレームを送る直前に呼ばれる (送信時刻の基準を決める等)
+    fn start(&mut self) -> Result<(), PacketReaderError> {
+        Ok(())
+    }
+
+    fn send(&mut self, frame: &OutboundFrame) -> Result<(), PacketReaderError>;
+
+    /// 再生開始から`at`の時点で送るべきフレームを送る
+    ///
+    /// 送信時刻をカーネルへ渡せるバックエンドだけが`at`を使い、それ以外は`send`と同じ。
+    fn send_at(&mut self, frame: &OutboundFrame, _at: Duration) -> Result<(), PacketReaderError> {
+        self.send(frame)
+    }
+
+    /// `send`で溜めたフレームを送り出す
+    ///
+    /// 待機に入る直前に呼ばれる。1フレームずつ送るバックエンドでは何もしない。
+    fn flush(&mut self) -> Result<(), PacketReaderError> {
+        Ok(())
+    }
+
+    /// 全てのフレームを送った後に呼ばれる (溜めたフレームやバッファの書き出し等)
+    fn finish(&mut self) -> Result<(), PacketReaderError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+use crate::packet::reader::error::PacketReaderError;
+use crate::packet::reader::link_layer::OutboundFrame;
+use crate::packet::reader::transmit::af_packet::{open_packet_socket, set_socket_option};
+use crate::packet::reader::transmit::raw_ip::RawIpSender;
+use crate::packet::reader::transmit::transmit_backend::TransmitBackend;
+use log::{info, warn};
+use pnet::datalink::NetworkInterface;
+use pnet::util::MacAddr;
+use std::io;
+use std::mem;
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// メモリマップしたAF_PACKETの送信リング (PACKET_TX_RING / TPACKET_V2) で送信する
+///
+/// `send`ではリングのスロットにフレームを書き込むだけで、`flush`の1回の`send`システムコールで
+/// 溜まったフレームをまとめてカーネルへ渡す。`PacketSender`は待機の直前に`flush`するため、
+/// 同じ (または近い) タイムスタンプのパケットは1回のシステムコールで送られる。
+pub struct TxRingBackend {
+    socket: OwnedFd,
+    ring: NonNull<u8>,
+    ring_len: usize,
+    frame_count: usize,
+    /// 次に書き込むスロット
+    current: usize,
+    /// 書き込んだがまだカーネルへ渡していないフレームの数
+    pending: usize,
+    raw_ip: RawIpSender,
+    interface: NetworkInterface,
+}
+
+// SAFETY: リングのメモリはこの構造体だけが所有し、同時に複数のスレッドからは触らない
+unsafe impl Send for TxRingBackend {}
+
+impl TxRingBackend {
+    const FRAME_SIZE: usize = 2048;
+    const BLOCK_SIZE: usize = 16 * 4096;
+    const BLOCK_COUNT: usize = 16;
+    /// フレームの先頭からデータまでのオフセット (TPACKET_ALIGN(sizeof(tpacket2_hdr)))
+    const DATA_OFFSET: usize = (mem::size_of::<libc::tpacket2_hdr>() + 15) & !15;
+
+    pub fn open(interface: &NetworkInterface) -> Result<Self, PacketReaderError> {
+        let socket = open_packet_socket(interface)?;
+        let option_error = |name: &str, e: io::Error| PacketReaderError::NetworkError(format!("{}を設定できませんでした: {}", name, e));
+
+        set_socket_option(&socket, libc::SOL_PACKET, libc::PACKET_VERSION, libc::tpacket_versions::TPACKET_V2 as libc::c_int).map_err(|e| option_error("PACKET_VERSION", e))?;
+        if let Err(e) = set_socket_option(&socket, libc::SOL_PACKET, libc::PACKET_QDISC_BYPASS, 1) {
+            warn!("PACKET_QDISC_BYPASSを有効にできませんでした。qdiscを経由して送信します: {}", e);
+        }
+
+        let request = libc::tpacket_req {
+            tp_block_size: Self::BLOCK_SIZE as libc::c_uint,
+            tp_block_nr: Self::BLOCK_COUNT as libc::c_uint,
+            tp_frame_size: Self::FRAME_SIZE as libc::c_uint,
+            tp_frame_nr: (Self::BLOCK_SIZE / Self::FRAME_SIZE * Self::BLOCK_COUNT) as libc::c_uint,
+        };
+        set_socket_option(&socket, libc::SOL_PACKET, libc::PACKET_TX_RING, request).map_err(|e| option_error("PACKET_TX_RING", e))?;
+
+        let ring_len = Self::BLOCK_SIZE * Self::BLOCK_COUNT;
+        // SAFETY: 送信リングを設定したソケットを、その大きさのままマップする
+        let ring = unsafe { libc::mmap(ptr::null_mut(), ring_len, libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED, socket.as_raw_fd(), 0) };
+        if ring == libc::MAP_FAILED {
+            return Err(PacketReaderError::NetworkError(format!(
+                "送信リングをマップできませんでした: {}",
+                io::Error::last_os_error()
+            )));
+        }
+        let ring = NonNull::new(ring.cast()).ok_or_else(|| PacketReaderError::NetworkError("送信リングのアドレスが不正です".to_string()))?;
+
+        let frame_count = request.tp_frame_nr as usize;
+        info!("{} の送信リングを確保しました: {} フレーム ({} bytes)", interface.name, frame_count, ring_len);
+
+        Ok(Self {
+            socket,
+            ring,
+            ring_len,
+            frame_count,
+            current: 0,
+            pending: 0,
+            raw_ip: RawIpSender::default(),
+            interface: interface.clone(),
+        })
+    }
+
+    fn frame(&self, index: usize) -> *mut u8 {
+        // SAFETY: indexは常にframe_count未満で、リングの範囲内を指す
+        unsafe { self.ring.as_ptr().add(index * Self::FRAME_SIZE) }
+    }
+
+    /// スロットの先頭にあるtp_statusはカーネルと共有するため、アトミックに読み書きする
+    fn status(&self, index: usize) -> &AtomicU32 {
+        // SAFETY: tp_statusはフレームの先頭にある4バイト境界のu32
+        unsafe { AtomicU32::from_ptr(self.frame(index).cast()) }
+    }
+
+    fn queue(&mut self, bytes: &[u8]) -> Result<(), PacketReaderError> {
+        if bytes.len() > Self::FRAME_SIZE - Self::DATA_OFFSET {
+            return Err(PacketReaderError::SendError(format!("送信リングのフレームに収まりません: {} bytes", bytes.len())));
+        }
+
+        // 空きがなければ溜まっている分を送り出してから再確認する
+        if self.status(self.current).load(Ordering::Acquire) != libc::TP_STATUS_AVAILABLE {
+            self.flush()?;
+        }
+        match self.status(self.current).load(Ordering::Acquire) {
+            libc::TP_STATUS_AVAILABLE => {},
+            libc::TP_STATUS_WRONG_FORMAT => {
+                self.status(self.current).store(libc::TP_STATUS_AVAILABLE, Ordering::Release);
+                return Err(PacketReaderError::SendError("カーネルがフレームの形式を受け付けませんでした".to_string()));
+            },
+            _ => return Err(PacketReaderError::SendError("送信リングに空きがありません".to_string())),
+        }
+
+        let frame = self.frame(self.current);
+        // SAFETY: スロットはTP_STATUS_AVAILABLEでカーネルは触っておらず、大きさも確認済み
+        unsafe {
+            ptr::copy_nonoverlapping(bytes.as_ptr(), frame.add(Self::DATA_OFFSET), bytes.len());
+            let header = frame.cast::<libc::tpacket2_hdr>();
+            (*header).tp_len = bytes.len() as u32;
+            (*header).tp_snaplen = bytes.len() as u32;
+        }
+        self.status(self.current).store(libc::TP_STATUS_SEND_REQUEST, Ordering::Release);
+
+        self.current = (self.current + 1) % self.frame_count;
+        self.pending += 1;
+        Ok(())
+    }
+}
+
+impl TransmitBackend for TxRingBackend {
+    fn description(&self) -> String {
+        format!("{} (AF_PACKET TX_RING, {} フレーム)", self.interface.name, self.frame_count)
+    }
+
+    fn source_mac(&self) -> MacAddr {
+        self.interface.mac.unwrap_or_else(MacAddr::zero)
+    }
+
+    fn send(&mut self, frame: &OutboundFrame) -> Result<(), PacketReaderError> {
+        match frame {
+            OutboundFrame::Ethernet(bytes) => self.queue(bytes),
+            OutboundFrame::Ipv4 { destination, packet } => self.raw_ip.send(*destination, packet),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), PacketReaderError> {
+        if self.pending == 0 {
+            return Ok(());
+        }
+
+        // ブロッキングで呼ぶため、戻った時点で書き込んだフレームは全て送信済み
+        // SAFETY: データを渡さずに送信リングの送信だけを要求する
+        let sent = unsafe { libc::send(self.socket.as_raw_fd(), ptr::null(), 0, 0) };
+        let pending = mem::take(&mut self.pending);
+        if sent < 0 {
+            return Err(PacketReaderError::SendError(format!(
+                "送信リングの {} フレームを送信できませんでした: {}",
+                pending,
+                io::Error::last_os_error()
+            )));
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), PacketReaderError> {
+        self.flush()
+    }
+}
+
+impl Drop for TxRingBackend {
+    fn drop(&mut self) {
+        // SAFETY: openでマップした領域をそのまま解放する
+        unsafe {
+            libc::munmap(self.ring.as_ptr().cast(), self.ring_len);
+        }
+    }
+}
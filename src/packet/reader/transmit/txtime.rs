@@ -0,0 +1,144 @@
+use crate::packet::reader::error::PacketReaderError;
+use crate::packet::reader::link_layer::OutboundFrame;
+use crate::packet::reader::transmit::af_packet::{open_packet_socket, set_socket_option, AfPacketBackend};
+use crate::packet::reader::transmit::raw_ip::RawIpSender;
+use crate::packet::reader::transmit::transmit_backend::TransmitBackend;
+use log::{info, warn};
+use pnet::datalink::NetworkInterface;
+use pnet::util::MacAddr;
+use std::io;
+use std::mem;
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::process::Command;
+use std::time::Duration;
+
+/// フレームごとにSO_TXTIMEで送信時刻を付け、ETF qdiscに正確な時刻で送り出させる
+///
+/// ソフトウェアの待機では再現できないマイクロ秒単位の間隔を、カーネルの送信時刻指定で再現する。
+/// 送信時刻は再生開始時のCLOCK_TAIに`LEAD`を足した時刻を基準にするため、待機の遅れが`LEAD`に
+/// 収まっている限り間隔は崩れない。送信時刻を過ぎたフレームはETFに破棄される。
+pub struct TxTimeBackend {
+    socket: OwnedFd,
+    /// 再生開始からの送信時刻0に対応するCLOCK_TAIの時刻 (ナノ秒)
+    base: Option<u64>,
+    raw_ip: RawIpSender,
+    interface: NetworkInterface,
+}
+
+impl TxTimeBackend {
+    /// 送信時刻をフレームを渡す時刻からどれだけ先にするか
+    const LEAD: Duration = Duration::from_millis(5);
+
+    /// ETF qdiscが設定されていればSO_TXTIMEで、なければ警告を出して通常のAF_PACKETで送信する
+    pub fn open_with_fallback(interface: &NetworkInterface) -> Result<Box<dyn TransmitBackend>, PacketReaderError> {
+        match etf_configured(&interface.name) {
+            Some(true) => return Ok(Box::new(Self::open(interface)?)),
+            Some(false) => warn!(
+                "{} にETF qdiscが設定されていないため、送信時刻の指定は使わずにソフトウェアの待機で送信します",
+                interface.name
+            ),
+            None => warn!("tcでqdiscを確認できなかったため、送信時刻の指定は使わずにソフトウェアの待機で送信します"),
+        }
+        warn!(
+            "ETFを使うには次のように設定してください: sudo tc qdisc replace dev {} root etf clockid CLOCK_TAI delta 200000 (マルチキューのNICではmqprio/taprioの子に設定)",
+            interface.name
+        );
+        Ok(Box::new(AfPacketBackend::open(interface)?))
+    }
+
+    fn open(interface: &NetworkInterface) -> Result<Self, PacketReaderError> {
+        // ETFはqdiscのため、PACKET_QDISC_BYPASSは使わない
+        let socket = open_packet_socket(interface)?;
+        let txtime = libc::sock_txtime {
+            clockid: libc::CLOCK_TAI,
+            flags: 0,
+        };
+        set_socket_option(&socket, libc::SOL_SOCKET, libc::SO_TXTIME, txtime).map_err(|e| PacketReaderError::NetworkError(format!("SO_TXTIMEを設定できませんでした: {}", e)))?;
+
+        info!("{} へETFの送信時刻指定 (SO_TXTIME, CLOCK_TAI) で送信します", interface.name);
+        Ok(Self {
+            socket,
+            base: None,
+            raw_ip: RawIpSender::default(),
+            interface: interface.clone(),
+        })
+    }
+
+    fn send_with_launch_time(&self, bytes: &[u8], launch_time: u64) -> Result<(), PacketReaderError> {
+        let mut iovec = libc::iovec {
+            iov_base: bytes.as_ptr() as *mut libc::c_void,
+            iov_len: bytes.len(),
+        };
+        let mut control = [0u8; 64];
+
+        // SAFETY: controlはSCM_TXTIMEの制御メッセージ1つ分より大きく、各ポインタは呼び出しの間有効
+        let sent = unsafe {
+            let control_len = libc::CMSG_SPACE(mem::size_of::<u64>() as u32) as usize;
+            let mut message: libc::msghdr = mem::zeroed();
+            message.msg_iov = &mut iovec;
+            message.msg_iovlen = 1;
+            message.msg_control = control.as_mut_ptr().cast();
+            message.msg_controllen = control_len as _;
+
+            let header = libc::CMSG_FIRSTHDR(&message);
+            (*header).cmsg_level = libc::SOL_SOCKET;
+            (*header).cmsg_type = libc::SCM_TXTIME;
+            (*header).cmsg_len = libc::CMSG_LEN(mem::size_of::<u64>() as u32) as _;
+            libc::CMSG_DATA(header).cast::<u64>().write_unaligned(launch_time);
+
+            libc::sendmsg(self.socket.as_raw_fd(), &message, 0)
+        };
+        if sent < 0 {
+            return Err(PacketReaderError::SendError(io::Error::last_os_error().to_string()));
+        }
+        Ok(())
+    }
+}
+
+impl TransmitBackend for TxTimeBackend {
+    fn description(&self) -> String {
+        format!("{} (AF_PACKET SO_TXTIME / ETF)", self.interface.name)
+    }
+
+    fn source_mac(&self) -> MacAddr {
+        self.interface.mac.unwrap_or_else(MacAddr::zero)
+    }
+
+    fn start(&mut self) -> Result<(), PacketReaderError> {
+        let now = tai_now().map_err(|e| PacketReaderError::NetworkError(format!("CLOCK_TAIを読み取れませんでした: {}", e)))?;
+        self.base = Some(now + Self::LEAD.as_nanos() as u64);
+        Ok(())
+    }
+
+    fn send(&mut self, frame: &OutboundFrame) -> Result<(), PacketReaderError> {
+        self.send_at(frame, Duration::ZERO)
+    }
+
+    fn send_at(&mut self, frame: &OutboundFrame, at: Duration) -> Result<(), PacketReaderError> {
+        match frame {
+            OutboundFrame::Ethernet(bytes) => {
+                let base = self.base.ok_or_else(|| PacketReaderError::SendError("送信時刻の基準が決まっていません".to_string()))?;
+                self.send_with_launch_time(bytes, base + at.as_nanos() as u64)
+            },
+            OutboundFrame::Ipv4 { destination, packet } => self.raw_ip.send(*destination, packet),
+        }
+    }
+}
+
+fn tai_now() -> io::Result<u64> {
+    // SAFETY: timespecは全て0で初期化してよい
+    let mut now: libc::timespec = unsafe { mem::zeroed() };
+    if unsafe { libc::clock_gettime(libc::CLOCK_TAI, &mut now) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(now.tv_sec as u64 * 1_000_000_000 + now.tv_nsec as u64)
+}
+
+/// インターフェースにETF qdiscが設定されているか (tcが使えない場合は`None`)
+fn etf_configured(name: &str) -> Option<bool> {
+    let output = Command::new("tc").args(["qdisc", "show", "dev", name]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).lines().any(|line| line.split_whitespace().nth(1) == Some("etf")))
+}
@@ -1,3 +1,16 @@
+mod packet_columns;
+mod packet_ordering;
 mod packet_repository;
+mod packet_row;
+mod replay_profile_repository;
+mod replay_profile_row;
+mod replay_run_repository;
+mod replay_run_row;
+mod time_partition;
+mod traffic_row;
 
+pub(crate) use packet_columns::PacketColumns;
+pub(crate) use packet_ordering::PacketOrdering;
 pub(crate) use packet_repository::PacketRepository;
+pub(crate) use replay_profile_repository::ReplayProfileRepository;
+pub(crate) use replay_run_repository::ReplayRunRepository;
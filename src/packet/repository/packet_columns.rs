@@ -0,0 +1,127 @@
+use crate::database::{DatabaseError, ExecuteQuery};
+use crate::packet::repository::packet_ordering::PacketOrdering;
+use std::collections::HashMap;
+
+/// 再生に必須のカラム
+const REQUIRED_COLUMNS: [&str; 2] = ["timestamp", "raw_packet"];
+
+const INTEGER_TYPES: &[&str] = &["smallint", "integer", "bigint"];
+const TEXT_TYPES: &[&str] = &["text", "character varying", "character", "name"];
+const INET_TYPES: &[&str] = &["inet"];
+
+/// あれば読み取る追加のカラムと、読み取れる型 (information_schema.columns.data_type)
+const OPTIONAL_COLUMNS: [(&str, &[&str]); 8] = [
+    ("original_length", INTEGER_TYPES),
+    ("source", TEXT_TYPES),
+    ("link_type", INTEGER_TYPES),
+    ("src_ip", INET_TYPES),
+    ("dst_ip", INET_TYPES),
+    ("src_port", INTEGER_TYPES),
+    ("dst_port", INTEGER_TYPES),
+    ("protocol", INTEGER_TYPES),
+];
+
+/// 型が合わないため読み取らない追加のカラム
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnMismatch {
+    pub name: &'static str,
+    pub actual: String,
+    pub expected: &'static [&'static str],
+}
+
+/// packetsテーブルのカラムとその型
+///
+/// 取得するカラムを`SELECT *`に任せず、存在して型の合うものだけを明示的に選ぶ。
+#[derive(Debug, Clone, Default)]
+pub struct PacketColumns {
+    types: HashMap<String, String>,
+}
+
+impl PacketColumns {
+    pub async fn load<D: ExecuteQuery + ?Sized>(db: &D) -> Result<Self, DatabaseError> {
+        let query = "
+            SELECT column_name, data_type
+            FROM information_schema.columns
+            WHERE table_schema = current_schema() AND table_name = 'packets'";
+
+        let rows = db.query(query, &[]).await?;
+        Ok(Self::from_types(rows.iter().map(|row| (row.get("column_name"), row.get("data_type")))))
+    }
+
+    pub fn from_types(types: impl IntoIterator<Item = (String, String)>) -> Self {
+        Self {
+            types: types.into_iter().collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+
+    pub fn data_type(&self, name: &str) -> Option<&str> {
+        self.types.get(name).map(String::as_str)
+    }
+
+    pub fn has_capture_sequence(&self) -> bool {
+        self.types.contains_key(PacketOrdering::SEQUENCE_COLUMN)
+    }
+
+    /// 存在するが型が合わない追加のカラム
+    pub fn mismatches(&self) -> Vec<ColumnMismatch> {
+        OPTIONAL_COLUMNS
+            .iter()
+            .filter_map(|(name, expected)| {
+                let actual = self.data_type(name)?;
+                (!expected.contains(&actual)).then(|| ColumnMismatch {
+                    name,
+                    actual: actual.to_string(),
+                    expected,
+                })
+            })
+            .collect()
+    }
+
+    /// `SELECT`に並べるカラム。必須のカラムと、存在して型の合う追加のカラム
+    pub fn select_list(&self) -> String {
+        let optional = OPTIONAL_COLUMNS.iter().filter(|(name, expected)| self.data_type(name).is_some_and(|actual| expected.contains(&actual))).map(|(name, _)| *name);
+        REQUIRED_COLUMNS.into_iter().chain(optional).collect::<Vec<_>>().join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns(types: &[(&str, &str)]) -> PacketColumns {
+        PacketColumns::from_types(types.iter().map(|(name, data_type)| (name.to_string(), data_type.to_string())))
+    }
+
+    #[test]
+    fn selects_present_optional_columns() {
+        let columns = columns(&[
+            ("timestamp", "timestamp with time zone"),
+            ("raw_packet", "bytea"),
+            ("link_type", "integer"),
+            ("src_ip", "inet"),
+            ("comment", "text"),
+        ]);
+
+        assert_eq!(columns.select_list(), "timestamp, raw_packet, link_type, src_ip");
+        assert!(columns.mismatches().is_empty());
+    }
+
+    #[test]
+    fn skips_optional_columns_with_other_types() {
+        let columns = columns(&[
+            ("timestamp", "timestamp with time zone"),
+            ("raw_packet", "bytea"),
+            ("source", "integer"),
+            ("src_ip", "text"),
+            ("dst_ip", "inet"),
+        ]);
+
+        assert_eq!(columns.select_list(), "timestamp, raw_packet, dst_ip");
+        let skipped: Vec<_> = columns.mismatches().into_iter().map(|mismatch| (mismatch.name, mismatch.actual)).collect();
+        assert_eq!(skipped, [("source", "integer".to_string()), ("src_ip", "text".to_string())]);
+    }
+}
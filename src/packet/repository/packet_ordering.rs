@@ -0,0 +1,23 @@
+/// 同じタイムスタンプのパケットを並べる順序
+///
+/// タイムスタンプが同じパケット (TCPのハンドシェイク等) も毎回同じ順で再生されるよう、2番目の並び順を決める。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketOrdering {
+    /// 取り込み時に挿入順で採番される`capture_seq`カラム
+    CaptureSequence,
+    /// 行の物理的な位置 (VACUUM FULL等でテーブルを書き換えると変わる)
+    ///
+    /// `capture_seq`のないテーブル向けの旧方式で、`database.legacy_row_order`を指定した場合のみ使う。
+    RowLocation,
+}
+
+impl PacketOrdering {
+    pub const SEQUENCE_COLUMN: &'static str = "capture_seq";
+
+    pub fn order_by(self) -> &'static str {
+        match self {
+            Self::CaptureSequence => "timestamp ASC, capture_seq ASC",
+            Self::RowLocation => "timestamp ASC, ctid ASC",
+        }
+    }
+}
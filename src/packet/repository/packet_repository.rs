@@ -1,36 +1,541 @@
-use crate::database::{Database, DatabaseError, ExecuteQuery};
-use chrono::{DateTime, Utc};
+use crate::database::{required_column, BeginTransaction, Database, DatabaseError, ExecuteQuery, FromRow, RowStream};
+use crate::packet::model::{Packet, TopTalker, TrafficBucket};
+use crate::packet::repository::packet_columns::PacketColumns;
+use crate::packet::repository::packet_ordering::PacketOrdering;
+use crate::packet::repository::time_partition::TimePartition;
+use chrono::{DateTime, Duration, Utc};
+use futures::stream::{self, BoxStream};
+use futures::{StreamExt, TryStreamExt};
+use log::{info, warn};
 
-pub struct PacketRepository;
+/// 時刻順に取得したパケットのストリーム
+pub type PacketStream<'a> = BoxStream<'a, Result<Packet, DatabaseError>>;
 
-impl PacketRepository {
-    pub async fn get_packets_in_timerange(start_time: DateTime<Utc>, end_time: DateTime<Utc>) -> Result<Vec<(DateTime<Utc>, Vec<u8>)>, DatabaseError> {
-        let db = Database::get_database();
-        let query = "
-            SELECT timestamp, raw_packet
+/// パケットテーブルへのアクセス
+///
+/// クエリは`ExecuteQuery`越しに実行するため、テストではモックを渡せる。
+pub struct PacketRepository<'a, D: ExecuteQuery + ?Sized = Database> {
+    db: &'a D,
+    legacy_row_order: bool,
+}
+
+impl PacketRepository<'static> {
+    pub fn from_database() -> Self {
+        Self::new(Database::get_database())
+    }
+}
+
+impl<'a, D: ExecuteQuery + ?Sized> PacketRepository<'a, D> {
+    /// チャンクの情報が得られない場合に区切る幅
+    const FALLBACK_PARTITION: Duration = Duration::hours(1);
+
+    pub fn new(db: &'a D) -> Self {
+        Self { db, legacy_row_order: false }
+    }
+
+    /// `capture_seq`カラムがない場合に、行の格納位置の順で並べる旧方式を許可する
+    pub fn with_legacy_row_order(mut self, allowed: bool) -> Self {
+        self.legacy_row_order = allowed;
+        self
+    }
+
+    pub async fn get_packets_in_timerange(&self, start_time: DateTime<Utc>, end_time: DateTime<Utc>) -> Result<Vec<Packet>, DatabaseError> {
+        let (columns, ordering) = self.get_packet_selection().await?;
+        let query = format!(
+            "
+            SELECT {}
             FROM packets
             WHERE timestamp >= $1 AND timestamp <= $2
-            ORDER BY timestamp ASC";
+            ORDER BY {}",
+            columns,
+            ordering.order_by()
+        );
 
-        let rows = db.query(query, &[&start_time, &end_time]).await?;
-        Ok(rows.into_iter().map(|row| (row.get("timestamp"), row.get("raw_packet"))).collect())
+        // 行をすべてバッファリングせず、受け取った行から順に変換する
+        let rows = self.db.query_raw(&query, &[&start_time, &end_time]).await?;
+        rows.map(|row| row.and_then(|row| Packet::from_row(&row))).try_collect().await
     }
 
-    pub async fn get_packet_time_range() -> Result<(DateTime<Utc>, DateTime<Utc>), DatabaseError> {
-        let db = Database::get_database();
+    /// 期間をTimescaleDBのチャンク境界で区切り、`concurrency`本の接続で並列に取得して時刻順に連結する
+    ///
+    /// チャンクの情報が得られない場合 (TimescaleDBでない等) は一定の幅で区切る。
+    /// 全体をバッファリングせず、先頭の区間から順にパケットを返すストリームにする。
+    pub async fn get_packets_partitioned(&self, start_time: DateTime<Utc>, end_time: DateTime<Utc>, concurrency: usize) -> Result<PacketStream<'_>, DatabaseError> {
+        let (columns, ordering) = self.get_packet_selection().await?;
+        let boundaries = match self.get_chunk_boundaries(start_time, end_time).await {
+            Ok(boundaries) if !boundaries.is_empty() => boundaries,
+            Ok(_) => TimePartition::fixed_boundaries(start_time, end_time, Self::FALLBACK_PARTITION),
+            Err(e) => {
+                warn!(
+                    "チャンクの境界を取得できなかったため、{}時間ごとに区切って取得します: {}",
+                    Self::FALLBACK_PARTITION.num_hours(),
+                    e
+                );
+                TimePartition::fixed_boundaries(start_time, end_time, Self::FALLBACK_PARTITION)
+            },
+        };
+        let partitions = TimePartition::split(start_time, end_time, &boundaries);
+        info!("{} 区間に分けて並列に取得します (同時接続数: {})", partitions.len(), concurrency.max(1));
+
+        // bufferedは完了順ではなく区間の順に結果を返すため、各区間の行を順に連結するだけで全体が時刻順になる
+        Ok(stream::iter(partitions)
+            .map(move |partition| self.get_rows_in_partition(columns.clone(), ordering, partition))
+            .buffered(concurrency.max(1))
+            .try_flatten()
+            .map(|row| row.and_then(|row| Packet::from_row(&row)))
+            .boxed())
+    }
+
+    async fn get_rows_in_partition(&self, columns: String, ordering: PacketOrdering, partition: TimePartition) -> Result<RowStream, DatabaseError> {
+        let query = format!(
+            "
+            SELECT {}
+            FROM packets
+            WHERE timestamp >= $1 AND timestamp < $2
+            ORDER BY {}",
+            columns,
+            ordering.order_by()
+        );
+
+        self.db.query_raw(&query, &[&partition.start, &partition.end]).await
+    }
+
+    /// 期間に掛かる`packets`のチャンクの開始・終了時刻
+    async fn get_chunk_boundaries(&self, start_time: DateTime<Utc>, end_time: DateTime<Utc>) -> Result<Vec<DateTime<Utc>>, DatabaseError> {
+        let query = "
+            SELECT range_start, range_end
+            FROM timescaledb_information.chunks
+            WHERE hypertable_name = 'packets' AND range_end > $1 AND range_start <= $2
+            ORDER BY range_start ASC";
+
+        let rows = self.db.query(query, &[&start_time, &end_time]).await?;
+        let mut boundaries = Vec::with_capacity(rows.len() * 2);
+        for row in &rows {
+            boundaries.push(required_column(row, "range_start")?);
+            boundaries.push(required_column(row, "range_end")?);
+        }
+        Ok(boundaries)
+    }
+
+    pub async fn get_packet_time_range(&self) -> Result<(DateTime<Utc>, DateTime<Utc>), DatabaseError> {
         let query = "
             SELECT
                 MIN(timestamp) as min_time,
                 MAX(timestamp) as max_time
             FROM packets";
 
-        let rows = db.query(query, &[]).await?;
-        if let Some(row) = rows.first() {
-            let min_time: DateTime<Utc> = row.get("min_time");
-            let max_time: DateTime<Utc> = row.get("max_time");
-            Ok((min_time, max_time))
-        } else {
-            Err(DatabaseError::QueryExecutionError("パケットが存在しません".to_string()))
+        let row = self.db.query_one(query, &[]).await?;
+        let min_time: Option<DateTime<Utc>> = row.get("min_time");
+        let max_time: Option<DateTime<Utc>> = row.get("max_time");
+        match (min_time, max_time) {
+            (Some(min_time), Some(max_time)) => Ok((min_time, max_time)),
+            _ => Err(DatabaseError::QueryExecutionError("パケットが存在しません".to_string())),
         }
     }
+
+    /// 期間内のパケット数とバイト数を`bucket`幅で集計する (パケットのないバケットは含まれない)
+    pub async fn get_traffic_histogram(&self, start_time: DateTime<Utc>, end_time: DateTime<Utc>, bucket: Duration) -> Result<Vec<TrafficBucket>, DatabaseError> {
+        let query = "
+            SELECT
+                time_bucket(make_interval(secs => $1), timestamp) AS bucket,
+                COUNT(*) AS packets,
+                COALESCE(SUM(octet_length(raw_packet)), 0)::bigint AS bytes
+            FROM packets
+            WHERE timestamp >= $2 AND timestamp <= $3
+            GROUP BY bucket
+            ORDER BY bucket ASC";
+
+        let bucket_seconds = bucket.num_milliseconds() as f64 / 1000.0;
+        let rows = self.db.query(query, &[&bucket_seconds, &start_time, &end_time]).await?;
+        rows.iter().map(TrafficBucket::from_row).collect()
+    }
+
+    /// 期間内で送信バイト数の多い送信元アドレスを返す (`src_ip`カラムが必要)
+    pub async fn get_top_talkers(&self, start_time: DateTime<Utc>, end_time: DateTime<Utc>, limit: i64) -> Result<Vec<TopTalker>, DatabaseError> {
+        let query = "
+            SELECT
+                src_ip AS address,
+                COUNT(*) AS packets,
+                COALESCE(SUM(octet_length(raw_packet)), 0)::bigint AS bytes
+            FROM packets
+            WHERE timestamp >= $1 AND timestamp <= $2 AND src_ip IS NOT NULL
+            GROUP BY src_ip
+            ORDER BY bytes DESC
+            LIMIT $3";
+
+        let rows = self.db.query(query, &[&start_time, &end_time, &limit]).await?;
+        rows.iter().map(TopTalker::from_row).collect()
+    }
+
+    /// テーブルのカラムから、取得するカラムの一覧と同じタイムスタンプ内の並び順を決める
+    ///
+    /// 追加のカラムは存在して型が合う場合のみ取得し、型が合わなければ警告して読み飛ばす。
+    /// 並び順には`capture_seq`カラムを使い、ない場合はエラーにする。
+    /// 旧方式が許可されていれば、警告した上で行の格納位置の順に並べる。
+    async fn get_packet_selection(&self) -> Result<(String, PacketOrdering), DatabaseError> {
+        let columns = PacketColumns::load(self.db).await?;
+        for mismatch in columns.mismatches() {
+            warn!(
+                "packets.{} の型が {} のため読み取りません ({} である必要があります)",
+                mismatch.name,
+                mismatch.actual,
+                mismatch.expected.join(" / ")
+            );
+        }
+
+        if columns.has_capture_sequence() {
+            return Ok((columns.select_list(), PacketOrdering::CaptureSequence));
+        }
+        if !self.legacy_row_order {
+            return Err(DatabaseError::UnsupportedSchema(format!(
+                "packets テーブルに {} カラムがありません (migrate サブコマンドで追加してください)",
+                PacketOrdering::SEQUENCE_COLUMN
+            )));
+        }
+
+        warn!(
+            "packets テーブルに {} カラムがないため、旧方式 (database.legacy_row_order) で同じタイムスタンプのパケットを行の格納位置の順に並べます",
+            PacketOrdering::SEQUENCE_COLUMN
+        );
+        warn!("この順序はVACUUM FULLや圧縮でテーブルが書き換えられると変わり、取り込み順と一致する保証もありません。migrate サブコマンドでカラムを追加してください");
+        Ok((columns.select_list(), PacketOrdering::RowLocation))
+    }
+}
+
+impl<D: BeginTransaction + ?Sized> PacketRepository<'_, D> {
+    /// `capture_seq`カラムと、並び替えに使うインデックスを追加する。カラムを追加した場合は`true`を返す
+    ///
+    /// 既存の行には格納位置の順に採番されるため、旧方式と同じ順序が固定される。
+    /// 以降に取り込み側がカラムを指定せずにINSERTした行には、挿入順に採番される。
+    pub async fn add_capture_sequence(&self) -> Result<bool, DatabaseError> {
+        let transaction = self.db.transaction().await?;
+
+        let added = !PacketColumns::load(transaction.as_ref()).await?.has_capture_sequence();
+        if added {
+            let query = format!("ALTER TABLE packets ADD COLUMN {} bigserial", PacketOrdering::SEQUENCE_COLUMN);
+            transaction.execute(&query, &[]).await?;
+        }
+        // 作成済みであればCREATE INDEX IF NOT EXISTSのNOTICEを出さないよう、先に存在を確認する
+        let index = format!("packets_timestamp_{}_idx", PacketOrdering::SEQUENCE_COLUMN);
+        let exists = transaction.query_one("SELECT to_regclass($1) IS NOT NULL AS exists", &[&index]).await?;
+        if !required_column::<bool>(&exists, "exists")? {
+            let query = format!("CREATE INDEX {} ON packets (timestamp, {})", index, PacketOrdering::SEQUENCE_COLUMN);
+            transaction.execute(&query, &[]).await?;
+        }
+
+        transaction.commit().await?;
+        Ok(added)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{MemoryDatabase, Row};
+    use crate::packet::model::LinkType;
+    use chrono::TimeZone;
+    use std::net::IpAddr;
+    use tokio_postgres::types::Type;
+
+    fn at(second: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, second).unwrap()
+    }
+
+    fn column_rows(types: &[(&str, &str)]) -> Vec<Row> {
+        types.iter().map(|(name, data_type)| Row::from_values(&[("column_name", Type::TEXT, name), ("data_type", Type::TEXT, data_type)])).collect()
+    }
+
+    #[tokio::test]
+    async fn decodes_packet_rows_with_optional_columns() {
+        let columns = column_rows(&[
+            ("timestamp", "timestamp with time zone"),
+            ("raw_packet", "bytea"),
+            ("original_length", "integer"),
+            ("source", "text"),
+            ("link_type", "smallint"),
+            ("src_ip", "inet"),
+            ("dst_ip", "inet"),
+            ("src_port", "integer"),
+            ("dst_port", "integer"),
+            ("protocol", "smallint"),
+            ("capture_seq", "bigint"),
+        ]);
+        let src_ip: IpAddr = "192.0.2.1".parse().unwrap();
+        let dst_ip: IpAddr = "2001:db8::1".parse().unwrap();
+        let packets = vec![
+            Row::from_values(&[
+                ("timestamp", Type::TIMESTAMPTZ, &at(1)),
+                ("raw_packet", Type::BYTEA, &vec![1u8, 2, 3]),
+                ("original_length", Type::INT4, &1514i32),
+                ("source", Type::TEXT, &"eth0"),
+                ("link_type", Type::INT2, &113i16),
+                ("src_ip", Type::INET, &src_ip),
+                ("dst_ip", Type::INET, &dst_ip),
+                ("src_port", Type::INT4, &53i32),
+                ("dst_port", Type::INT4, &70000i32),
+                ("protocol", Type::INT2, &17i16),
+            ]),
+            Row::from_values(&[
+                ("timestamp", Type::TIMESTAMPTZ, &at(2)),
+                ("raw_packet", Type::BYTEA, &vec![4u8]),
+                ("original_length", Type::INT4, &None::<i32>),
+                ("source", Type::TEXT, &None::<String>),
+                ("link_type", Type::INT2, &None::<i16>),
+                ("src_ip", Type::INET, &None::<IpAddr>),
+                ("dst_ip", Type::INET, &None::<IpAddr>),
+                ("src_port", Type::INT4, &None::<i32>),
+                ("dst_port", Type::INT4, &None::<i32>),
+                ("protocol", Type::INT2, &None::<i16>),
+            ]),
+        ];
+        let db = MemoryDatabase::default().with_rows(columns).with_rows(packets);
+
+        let packets = PacketRepository::new(&db).get_packets_in_timerange(at(0), at(10)).await.unwrap();
+
+        assert_eq!(packets.len(), 2);
+        let first = &packets[0];
+        assert_eq!((first.timestamp, first.data.as_slice()), (at(1), [1u8, 2, 3].as_slice()));
+        assert_eq!(first.original_length, 1514);
+        assert!(first.is_truncated());
+        assert_eq!(first.source.as_deref(), Some("eth0"));
+        assert_eq!(first.link_type, LinkType::LinuxSll);
+        assert_eq!((first.metadata.src_ip, first.metadata.dst_ip), (Some(src_ip), Some(dst_ip)));
+        // 範囲外のポート番号は読み飛ばす
+        assert_eq!((first.metadata.src_port, first.metadata.dst_port, first.metadata.protocol), (Some(53), None, Some(17)));
+
+        // NULLの追加カラムは既定値になる
+        let second = &packets[1];
+        assert_eq!((second.timestamp, second.data.as_slice(), second.original_length), (at(2), [4u8].as_slice(), 1));
+        assert_eq!((second.source.as_deref(), second.link_type), (None, LinkType::Ethernet));
+        assert_eq!(second.metadata.src_ip, None);
+
+        let fetch = db.recorded().pop().unwrap();
+        assert_eq!(
+            fetch.query,
+            "SELECT timestamp, raw_packet, original_length, source, link_type, src_ip, dst_ip, src_port, dst_port, protocol \
+             FROM packets WHERE timestamp >= $1 AND timestamp <= $2 ORDER BY timestamp ASC, capture_seq ASC"
+        );
+        assert_eq!(fetch.params, [format!("{:?}", at(0)), format!("{:?}", at(10))]);
+    }
+
+    #[tokio::test]
+    async fn selects_only_required_columns_when_optional_ones_are_unreadable() {
+        let columns = column_rows(&[
+            ("timestamp", "timestamp with time zone"),
+            ("raw_packet", "bytea"),
+            ("source", "integer"),
+            ("comment", "text"),
+            ("capture_seq", "bigint"),
+        ]);
+        let packet = Row::from_values(&[
+            ("timestamp", Type::TIMESTAMPTZ, &at(1)),
+            ("raw_packet", Type::BYTEA, &vec![1u8]),
+        ]);
+        let db = MemoryDatabase::default().with_rows(columns).with_rows(vec![packet]);
+
+        let packets = PacketRepository::new(&db).get_packets_in_timerange(at(0), at(10)).await.unwrap();
+
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].source, None);
+        assert!(db.recorded().pop().unwrap().query.starts_with("SELECT timestamp, raw_packet FROM packets"));
+    }
+
+    fn packet_row(second: u32) -> Row {
+        Row::from_values(&[
+            ("timestamp", Type::TIMESTAMPTZ, &at(second)),
+            ("raw_packet", Type::BYTEA, &vec![second as u8]),
+        ])
+    }
+
+    fn chunk_row(start: u32, end: u32) -> Row {
+        Row::from_values(&[
+            ("range_start", Type::TIMESTAMPTZ, &at(start)),
+            ("range_end", Type::TIMESTAMPTZ, &at(end)),
+        ])
+    }
+
+    #[tokio::test]
+    async fn streams_partitions_in_time_order() {
+        let columns = column_rows(&[
+            ("timestamp", "timestamp with time zone"),
+            ("raw_packet", "bytea"),
+            ("capture_seq", "bigint"),
+        ]);
+        let chunks = vec![chunk_row(0, 10), chunk_row(10, 20)];
+        let db = MemoryDatabase::default().with_rows(columns).with_rows(chunks).with_rows(vec![packet_row(5), packet_row(8)]).with_rows(vec![packet_row(10), packet_row(15)]);
+
+        let repository = PacketRepository::new(&db);
+        let packets: Vec<Packet> = repository.get_packets_partitioned(at(5), at(15), 2).await.unwrap().try_collect().await.unwrap();
+
+        let seconds: Vec<u8> = packets.iter().map(|packet| packet.data[0]).collect();
+        assert_eq!(seconds, [5, 8, 10, 15]);
+
+        // 区間は境界で切れ目なく続き、最後の区間は終了日時を含む
+        let fetches: Vec<_> = db.recorded().into_iter().skip(2).map(|fetch| fetch.params).collect();
+        assert_eq!(
+            fetches,
+            [
+                vec![format!("{:?}", at(5)), format!("{:?}", at(10))],
+                vec![format!("{:?}", at(10)), format!("{:?}", at(15) + Duration::microseconds(1))],
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_fixed_partitions_without_chunks() {
+        let columns = column_rows(&[
+            ("timestamp", "timestamp with time zone"),
+            ("raw_packet", "bytea"),
+            ("capture_seq", "bigint"),
+        ]);
+        let db = MemoryDatabase::default().with_rows(columns).with_rows(Vec::new()).with_rows(vec![packet_row(1)]);
+
+        let repository = PacketRepository::new(&db);
+        let packets: Vec<Packet> = repository.get_packets_partitioned(at(0), at(30), 4).await.unwrap().try_collect().await.unwrap();
+
+        assert_eq!(packets.len(), 1);
+        // 1時間に満たない期間は1区間で取得する
+        assert_eq!(db.recorded().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn rejects_out_of_range_link_type() {
+        let columns = column_rows(&[
+            ("timestamp", "timestamp with time zone"),
+            ("raw_packet", "bytea"),
+            ("link_type", "integer"),
+            ("capture_seq", "bigint"),
+        ]);
+        let packet = Row::from_values(&[
+            ("timestamp", Type::TIMESTAMPTZ, &at(1)),
+            ("raw_packet", Type::BYTEA, &vec![1u8]),
+            ("link_type", Type::INT4, &70000i32),
+        ]);
+        let db = MemoryDatabase::default().with_rows(columns).with_rows(vec![packet]);
+
+        let result = PacketRepository::new(&db).get_packets_in_timerange(at(0), at(10)).await;
+
+        assert!(matches!(result, Err(DatabaseError::RowMappingError(message)) if message.starts_with("link_type")));
+    }
+
+    #[tokio::test]
+    async fn missing_capture_seq_is_an_error() {
+        let columns = column_rows(&[("timestamp", "timestamp with time zone"), ("raw_packet", "bytea")]);
+        let db = MemoryDatabase::default().with_rows(columns);
+
+        let result = PacketRepository::new(&db).get_packets_in_timerange(at(0), at(10)).await;
+
+        assert!(matches!(result, Err(DatabaseError::UnsupportedSchema(_))));
+        assert!(db.recorded().iter().all(|recorded| !recorded.query.contains("FROM packets WHERE")));
+    }
+
+    #[tokio::test]
+    async fn legacy_row_order_falls_back_to_row_location() {
+        let columns = column_rows(&[("timestamp", "timestamp with time zone"), ("raw_packet", "bytea")]);
+        let db = MemoryDatabase::default().with_rows(columns).with_rows(Vec::new()).with_rows(vec![packet_row(1)]);
+
+        let repository = PacketRepository::new(&db).with_legacy_row_order(true);
+        let packets: Vec<Packet> = repository.get_packets_partitioned(at(0), at(10), 1).await.unwrap().try_collect().await.unwrap();
+
+        assert_eq!(packets.len(), 1);
+        let fetch = db.recorded().pop().unwrap();
+        assert_eq!(
+            fetch.query,
+            "SELECT timestamp, raw_packet FROM packets WHERE timestamp >= $1 AND timestamp < $2 ORDER BY timestamp ASC, ctid ASC"
+        );
+    }
+
+    fn index_exists(exists: bool) -> Vec<Row> {
+        vec![Row::from_values(&[("exists", Type::BOOL, &exists)])]
+    }
+
+    #[tokio::test]
+    async fn adds_capture_seq_and_index_in_one_transaction() {
+        let columns = column_rows(&[("timestamp", "timestamp with time zone"), ("raw_packet", "bytea")]);
+        let db = MemoryDatabase::default().with_rows(columns).with_rows(index_exists(false));
+
+        assert!(PacketRepository::new(&db).add_capture_sequence().await.unwrap());
+
+        let queries: Vec<String> = db.recorded().into_iter().map(|recorded| recorded.query).collect();
+        assert_eq!(queries[0], "BEGIN");
+        assert_eq!(queries[2], "ALTER TABLE packets ADD COLUMN capture_seq bigserial");
+        assert_eq!(queries[4], "CREATE INDEX packets_timestamp_capture_seq_idx ON packets (timestamp, capture_seq)");
+        assert_eq!(queries[5], "COMMIT");
+        assert_eq!(queries.len(), 6);
+    }
+
+    #[tokio::test]
+    async fn migration_is_a_no_op_when_already_applied() {
+        let columns = column_rows(&[
+            ("timestamp", "timestamp with time zone"),
+            ("raw_packet", "bytea"),
+            ("capture_seq", "bigint"),
+        ]);
+        let db = MemoryDatabase::default().with_rows(columns).with_rows(index_exists(true));
+
+        assert!(!PacketRepository::new(&db).add_capture_sequence().await.unwrap());
+
+        let queries: Vec<String> = db.recorded().into_iter().map(|recorded| recorded.query).collect();
+        assert!(queries.iter().all(|query| !query.starts_with("ALTER") && !query.starts_with("CREATE")));
+        assert_eq!(queries.last().map(String::as_str), Some("COMMIT"));
+    }
+
+    #[tokio::test]
+    async fn reads_time_range() {
+        let row = Row::from_values(&[
+            ("min_time", Type::TIMESTAMPTZ, &Some(at(1))),
+            ("max_time", Type::TIMESTAMPTZ, &Some(at(9))),
+        ]);
+        let db = MemoryDatabase::default().with_rows(vec![row]);
+
+        assert_eq!(PacketRepository::new(&db).get_packet_time_range().await.unwrap(), (at(1), at(9)));
+    }
+
+    #[tokio::test]
+    async fn empty_table_has_no_time_range() {
+        // 集約関数は0行でもNULLの1行を返す
+        let none: Option<DateTime<Utc>> = None;
+        let row = Row::from_values(&[("min_time", Type::TIMESTAMPTZ, &none), ("max_time", Type::TIMESTAMPTZ, &none)]);
+        let db = MemoryDatabase::default().with_rows(vec![row]);
+
+        assert!(matches!(
+            PacketRepository::new(&db).get_packet_time_range().await,
+            Err(DatabaseError::QueryExecutionError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn reads_traffic_histogram() {
+        let bucket = Row::from_values(&[
+            ("bucket", Type::TIMESTAMPTZ, &at(0)),
+            ("packets", Type::INT8, &3i64),
+            ("bytes", Type::INT8, &180i64),
+        ]);
+        let db = MemoryDatabase::default().with_rows(vec![bucket]);
+
+        let buckets = PacketRepository::new(&db).get_traffic_histogram(at(0), at(10), Duration::milliseconds(1500)).await.unwrap();
+
+        assert_eq!(buckets.len(), 1);
+        assert_eq!((buckets[0].start, buckets[0].packets, buckets[0].bytes), (at(0), 3, 180));
+        let query = db.recorded().pop().unwrap();
+        assert!(query.query.contains("time_bucket(make_interval(secs => $1), timestamp)"));
+        assert_eq!(query.params[0], "1.5");
+    }
+
+    #[tokio::test]
+    async fn reads_top_talkers() {
+        let address: IpAddr = "192.0.2.1".parse().unwrap();
+        let talker = Row::from_values(&[
+            ("address", Type::INET, &address),
+            ("packets", Type::INT8, &2i64),
+            ("bytes", Type::INT8, &120i64),
+        ]);
+        let db = MemoryDatabase::default().with_rows(vec![talker]);
+
+        let talkers = PacketRepository::new(&db).get_top_talkers(at(0), at(10), 5).await.unwrap();
+
+        assert_eq!(talkers.len(), 1);
+        assert_eq!((talkers[0].address, talkers[0].packets, talkers[0].bytes), (address, 2, 120));
+        assert_eq!(db.recorded().pop().unwrap().params[2], "5");
+    }
 }
@@ -0,0 +1,28 @@
+use crate::database::{optional_column, optional_integer, required_column, DatabaseError, FromRow, Row};
+use crate::packet::model::{LinkType, Packet, PacketMetadata};
+
+// 必須カラム以外は結果に含まれていなければ既定値になる
+// 取得するカラムはPacketColumnsで決めるため、カラムを追加する場合はその一覧にも型と合わせて加える
+impl FromRow for Packet {
+    fn from_row(row: &Row) -> Result<Self, DatabaseError> {
+        let mut packet = Packet::new(required_column(row, "timestamp")?, required_column(row, "raw_packet")?);
+
+        if let Some(original_length) = optional_integer(row, "original_length")? {
+            packet.original_length = usize::try_from(original_length).unwrap_or(packet.original_length);
+        }
+        packet.source = optional_column(row, "source")?;
+        if let Some(link_type) = optional_integer(row, "link_type")? {
+            let link_type = u16::try_from(link_type).map_err(|_| DatabaseError::RowMappingError(format!("link_type: 範囲外の値です ({})", link_type)))?;
+            packet.link_type = LinkType::from_linktype(link_type);
+        }
+        packet.metadata = PacketMetadata {
+            src_ip: optional_column(row, "src_ip")?,
+            dst_ip: optional_column(row, "dst_ip")?,
+            src_port: optional_integer(row, "src_port")?.and_then(|port| u16::try_from(port).ok()),
+            dst_port: optional_integer(row, "dst_port")?.and_then(|port| u16::try_from(port).ok()),
+            protocol: optional_integer(row, "protocol")?.and_then(|protocol| u8::try_from(protocol).ok()),
+        };
+
+        Ok(packet)
+    }
+}
@@ -0,0 +1,153 @@
+use crate::database::{required_column, Database, DatabaseError, ExecuteQuery, FromRow};
+use crate::packet::model::ReplayProfile;
+
+/// 再生のプロファイル (`replay_profiles`テーブル) へのアクセス
+///
+/// テーブルはこのツールが所有するため、存在しなければ作成する。
+pub struct ReplayProfileRepository<'a, D: ExecuteQuery + ?Sized = Database> {
+    db: &'a D,
+}
+
+impl ReplayProfileRepository<'static> {
+    pub fn from_database() -> Self {
+        Self::new(Database::get_database())
+    }
+}
+
+impl<'a, D: ExecuteQuery + ?Sized> ReplayProfileRepository<'a, D> {
+    pub fn new(db: &'a D) -> Self {
+        Self { db }
+    }
+
+    pub async fn ensure_table(&self) -> Result<(), DatabaseError> {
+        // 作成済みであればCREATE TABLE IF NOT EXISTSのNOTICEを出さないよう、先に存在を確認する
+        let exists = self.db.query_one("SELECT to_regclass('replay_profiles') IS NOT NULL AS exists", &[]).await?;
+        if required_column(&exists, "exists")? {
+            return Ok(());
+        }
+
+        let query = "
+            CREATE TABLE IF NOT EXISTS replay_profiles (
+                name TEXT PRIMARY KEY,
+                description TEXT,
+                arguments TEXT[] NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+                updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+            )";
+
+        self.db.execute(query, &[]).await?;
+        Ok(())
+    }
+
+    pub async fn list_profiles(&self) -> Result<Vec<ReplayProfile>, DatabaseError> {
+        let query = "
+            SELECT name, description, arguments, updated_at
+            FROM replay_profiles
+            ORDER BY name ASC";
+
+        let rows = self.db.query(query, &[]).await?;
+        rows.iter().map(ReplayProfile::from_row).collect()
+    }
+
+    pub async fn get_profile(&self, name: &str) -> Result<Option<ReplayProfile>, DatabaseError> {
+        let query = "
+            SELECT name, description, arguments, updated_at
+            FROM replay_profiles
+            WHERE name = $1";
+
+        self.db.query_opt(query, &[&name]).await?.as_ref().map(ReplayProfile::from_row).transpose()
+    }
+
+    /// 同じ名前のプロファイルがあれば作成せずに`false`を返す
+    pub async fn insert_profile(&self, name: &str, description: Option<&str>, arguments: &[String]) -> Result<bool, DatabaseError> {
+        let query = "
+            INSERT INTO replay_profiles (name, description, arguments)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (name) DO NOTHING";
+
+        Ok(self.db.execute(query, &[&name, &description, &arguments]).await? > 0)
+    }
+
+    /// 同じ名前のプロファイルがあれば置き換える
+    pub async fn upsert_profile(&self, name: &str, description: Option<&str>, arguments: &[String]) -> Result<(), DatabaseError> {
+        let query = "
+            INSERT INTO replay_profiles (name, description, arguments)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (name) DO UPDATE SET description = EXCLUDED.description, arguments = EXCLUDED.arguments, updated_at = now()";
+
+        self.db.execute(query, &[&name, &description, &arguments]).await?;
+        Ok(())
+    }
+
+    /// 削除した場合は`true`
+    pub async fn delete_profile(&self, name: &str) -> Result<bool, DatabaseError> {
+        let query = "
+            DELETE FROM replay_profiles
+            WHERE name = $1";
+
+        Ok(self.db.execute(query, &[&name]).await? > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{MemoryDatabase, Row};
+    use tokio_postgres::types::Type;
+
+    fn arguments() -> Vec<String> {
+        vec!["--speed".to_string(), "2".to_string()]
+    }
+
+    #[tokio::test]
+    async fn creates_table_only_when_missing() {
+        let exists = |exists: bool| vec![Row::from_values(&[("exists", Type::BOOL, &exists)])];
+        let db = MemoryDatabase::default().with_rows(exists(true)).with_rows(exists(false));
+        let repository = ReplayProfileRepository::new(&db);
+
+        repository.ensure_table().await.unwrap();
+        assert_eq!(db.recorded().len(), 1);
+        repository.ensure_table().await.unwrap();
+        assert!(db.recorded()[2].query.starts_with("CREATE TABLE IF NOT EXISTS replay_profiles"));
+    }
+
+    #[tokio::test]
+    async fn insert_reports_existing_profile() {
+        let db = MemoryDatabase::default().with_affected_rows(0);
+        let inserted = ReplayProfileRepository::new(&db).insert_profile("lab", None, &arguments()).await.unwrap();
+
+        assert!(!inserted);
+        let recorded = db.recorded();
+        assert_eq!(recorded.len(), 1);
+        assert!(recorded[0].query.starts_with("INSERT INTO replay_profiles"));
+        assert!(recorded[0].query.ends_with("ON CONFLICT (name) DO NOTHING"));
+        assert_eq!(recorded[0].params, ["\"lab\"", "None", "[\"--speed\", \"2\"]"]);
+    }
+
+    #[tokio::test]
+    async fn insert_and_delete_report_affected_rows() {
+        let db = MemoryDatabase::default().with_affected_rows(1);
+        let repository = ReplayProfileRepository::new(&db);
+
+        assert!(repository.insert_profile("lab", Some("検証用"), &arguments()).await.unwrap());
+        assert!(repository.delete_profile("lab").await.unwrap());
+        assert_eq!(db.recorded()[1].query, "DELETE FROM replay_profiles WHERE name = $1");
+    }
+
+    #[tokio::test]
+    async fn reads_profile_and_reports_missing_one() {
+        let profile = Row::from_values(&[
+            ("name", Type::TEXT, &"lab"),
+            ("description", Type::TEXT, &None::<String>),
+            ("arguments", Type::TEXT_ARRAY, &arguments()),
+            ("updated_at", Type::TIMESTAMPTZ, &chrono::Utc::now()),
+        ]);
+        let db = MemoryDatabase::default().with_rows(vec![profile]);
+        let repository = ReplayProfileRepository::new(&db);
+
+        let profile = repository.get_profile("lab").await.unwrap().unwrap();
+        assert_eq!((profile.name.as_str(), profile.description, profile.arguments), ("lab", None, arguments()));
+        assert!(repository.get_profile("lab").await.unwrap().is_none());
+        assert!(!repository.delete_profile("lab").await.unwrap());
+    }
+}
@@ -0,0 +1,13 @@
+use crate::database::{optional_column, required_column, DatabaseError, FromRow, Row};
+use crate::packet::model::ReplayProfile;
+
+impl FromRow for ReplayProfile {
+    fn from_row(row: &Row) -> Result<Self, DatabaseError> {
+        Ok(Self {
+            name: required_column(row, "name")?,
+            description: optional_column(row, "description")?,
+            arguments: required_column(row, "arguments")?,
+            updated_at: required_column(row, "updated_at")?,
+        })
+    }
+}
@@ -0,0 +1,206 @@
+use crate::database::{required_column, Database, DatabaseError, ExecuteQuery, FromRow};
+use crate::packet::model::{ReplayRun, ReplayRunParameters, ReplayRunStatistics, ReplayRunStatus};
+
+/// 再生の履歴 (`replay_runs`テーブル) へのアクセス
+///
+/// テーブルはこのツールが所有するため、存在しなければ作成する。
+pub struct ReplayRunRepository<'a, D: ExecuteQuery + ?Sized = Database> {
+    db: &'a D,
+}
+
+impl ReplayRunRepository<'static> {
+    pub fn from_database() -> Self {
+        Self::new(Database::get_database())
+    }
+}
+
+impl<'a, D: ExecuteQuery + ?Sized> ReplayRunRepository<'a, D> {
+    pub fn new(db: &'a D) -> Self {
+        Self { db }
+    }
+
+    pub async fn ensure_table(&self) -> Result<(), DatabaseError> {
+        // 作成済みであればCREATE TABLE IF NOT EXISTSのNOTICEを出さないよう、先に存在を確認する
+        let exists = self.db.query_one("SELECT to_regclass('replay_runs') IS NOT NULL AS exists", &[]).await?;
+        if required_column(&exists, "exists")? {
+            return Ok(());
+        }
+
+        let query = "
+            CREATE TABLE IF NOT EXISTS replay_runs (
+                id BIGSERIAL PRIMARY KEY,
+                started_at TIMESTAMPTZ NOT NULL,
+                finished_at TIMESTAMPTZ,
+                range_start TIMESTAMPTZ NOT NULL,
+                range_end TIMESTAMPTZ NOT NULL,
+                backend TEXT NOT NULL,
+                destination TEXT,
+                filters TEXT NOT NULL,
+                speed TEXT NOT NULL,
+                host TEXT NOT NULL,
+                operator TEXT NOT NULL,
+                status TEXT NOT NULL,
+                packets_total BIGINT,
+                packets_sent BIGINT,
+                bytes_sent BIGINT,
+                errors BIGINT,
+                error_message TEXT
+            )";
+
+        self.db.execute(query, &[]).await?;
+        Ok(())
+    }
+
+    /// 実行中として記録し、採番したIDを返す
+    pub async fn insert_run(&self, parameters: &ReplayRunParameters, host: &str, operator: &str) -> Result<i64, DatabaseError> {
+        let query = "
+            INSERT INTO replay_runs (started_at, range_start, range_end, backend, destination, filters, speed, host, operator, status)
+            VALUES (now(), $1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING id";
+
+        let row = self
+            .db
+            .query_one(
+                query,
+                &[
+                    &parameters.range_start,
+                    &parameters.range_end,
+                    &parameters.backend,
+                    &parameters.destination,
+                    &parameters.filters,
+                    &parameters.speed,
+                    &host,
+                    &operator,
+                    &ReplayRunStatus::Running.as_str(),
+                ],
+            )
+            .await?;
+        required_column(&row, "id")
+    }
+
+    pub async fn finish_run(&self, id: i64, status: ReplayRunStatus, statistics: &ReplayRunStatistics, error_message: Option<&str>) -> Result<(), DatabaseError> {
+        let query = "
+            UPDATE replay_runs
+            SET finished_at = now(), status = $2, packets_total = $3, packets_sent = $4, bytes_sent = $5, errors = $6, error_message = $7
+            WHERE id = $1";
+
+        self.db
+            .execute(
+                query,
+                &[
+                    &id,
+                    &status.as_str(),
+                    &statistics.packets_total,
+                    &statistics.packets_sent,
+                    &statistics.bytes_sent,
+                    &statistics.errors,
+                    &error_message,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// 新しい順に`limit`件
+    pub async fn list_runs(&self, limit: i64) -> Result<Vec<ReplayRun>, DatabaseError> {
+        let query = "
+            SELECT *
+            FROM replay_runs
+            ORDER BY id DESC
+            LIMIT $1";
+
+        let rows = self.db.query(query, &[&limit]).await?;
+        rows.iter().map(ReplayRun::from_row).collect()
+    }
+
+    pub async fn get_run(&self, id: i64) -> Result<Option<ReplayRun>, DatabaseError> {
+        let query = "
+            SELECT *
+            FROM replay_runs
+            WHERE id = $1";
+
+        self.db.query_opt(query, &[&id]).await?.as_ref().map(ReplayRun::from_row).transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{MemoryDatabase, Row};
+    use chrono::{DateTime, TimeZone, Utc};
+    use tokio_postgres::types::Type;
+
+    fn at(minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 1, 0, minute, 0).unwrap()
+    }
+
+    fn parameters() -> ReplayRunParameters {
+        ReplayRunParameters {
+            range_start: at(0),
+            range_end: at(10),
+            backend: "pnet".to_string(),
+            destination: Some("eth0".to_string()),
+            filters: "なし".to_string(),
+            speed: "x1".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn records_start_and_finish_of_a_run() {
+        let db = MemoryDatabase::default().with_rows(vec![Row::from_values(&[("id", Type::INT8, &7i64)])]);
+        let repository = ReplayRunRepository::new(&db);
+
+        let id = repository.insert_run(&parameters(), "host1", "alice").await.unwrap();
+        assert_eq!(id, 7);
+        let statistics = ReplayRunStatistics {
+            packets_total: 10,
+            packets_sent: 9,
+            bytes_sent: 900,
+            errors: 1,
+        };
+        repository.finish_run(id, ReplayRunStatus::Failed, &statistics, Some("送信エラー")).await.unwrap();
+
+        let recorded = db.recorded();
+        assert!(recorded[0].query.starts_with("INSERT INTO replay_runs"));
+        assert_eq!(recorded[0].params[6..], ["\"host1\"", "\"alice\"", "\"running\""]);
+        assert!(recorded[1].query.starts_with("UPDATE replay_runs"));
+        assert_eq!(recorded[1].params, ["7", "\"failed\"", "10", "9", "900", "1", "Some(\"送信エラー\")"]);
+    }
+
+    #[tokio::test]
+    async fn decodes_unfinished_run_without_statistics() {
+        let row = Row::from_values(&[
+            ("id", Type::INT8, &3i64),
+            ("started_at", Type::TIMESTAMPTZ, &at(1)),
+            ("finished_at", Type::TIMESTAMPTZ, &None::<DateTime<Utc>>),
+            ("range_start", Type::TIMESTAMPTZ, &at(0)),
+            ("range_end", Type::TIMESTAMPTZ, &at(10)),
+            ("backend", Type::TEXT, &"dry_run"),
+            ("destination", Type::TEXT, &None::<String>),
+            ("filters", Type::TEXT, &"なし"),
+            ("speed", Type::TEXT, &"x2"),
+            ("host", Type::TEXT, &"host1"),
+            ("operator", Type::TEXT, &"alice"),
+            ("status", Type::TEXT, &"running"),
+            ("packets_total", Type::INT8, &None::<i64>),
+            ("packets_sent", Type::INT8, &None::<i64>),
+            ("bytes_sent", Type::INT8, &None::<i64>),
+            ("errors", Type::INT8, &None::<i64>),
+            ("error_message", Type::TEXT, &None::<String>),
+        ]);
+        let db = MemoryDatabase::default().with_rows(vec![row]);
+
+        let run = ReplayRunRepository::new(&db).get_run(3).await.unwrap().unwrap();
+
+        assert_eq!((run.id, run.status, run.finished_at), (3, ReplayRunStatus::Running, None));
+        assert_eq!((run.parameters.backend.as_str(), run.parameters.destination), ("dry_run", None));
+        assert!(run.statistics.is_none());
+        assert_eq!(db.recorded()[0].params, ["3"]);
+    }
+
+    #[tokio::test]
+    async fn missing_run_is_none() {
+        let db = MemoryDatabase::default();
+        assert!(ReplayRunRepository::new(&db).get_run(1).await.unwrap().is_none());
+    }
+}
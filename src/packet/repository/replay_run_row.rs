@@ -0,0 +1,41 @@
+use crate::database::{optional_column, required_column, DatabaseError, FromRow, Row};
+use crate::packet::model::{ReplayRun, ReplayRunParameters, ReplayRunStatistics};
+
+impl FromRow for ReplayRun {
+    fn from_row(row: &Row) -> Result<Self, DatabaseError> {
+        let status: String = required_column(row, "status")?;
+        let statistics = match (
+            optional_column(row, "packets_total")?,
+            optional_column(row, "packets_sent")?,
+            optional_column(row, "bytes_sent")?,
+            optional_column(row, "errors")?,
+        ) {
+            (Some(packets_total), Some(packets_sent), Some(bytes_sent), Some(errors)) => Some(ReplayRunStatistics {
+                packets_total,
+                packets_sent,
+                bytes_sent,
+                errors,
+            }),
+            _ => None,
+        };
+
+        Ok(Self {
+            id: required_column(row, "id")?,
+            started_at: required_column(row, "started_at")?,
+            finished_at: optional_column(row, "finished_at")?,
+            parameters: ReplayRunParameters {
+                range_start: required_column(row, "range_start")?,
+                range_end: required_column(row, "range_end")?,
+                backend: required_column(row, "backend")?,
+                destination: optional_column(row, "destination")?,
+                filters: required_column(row, "filters")?,
+                speed: required_column(row, "speed")?,
+            },
+            host: required_column(row, "host")?,
+            operator: required_column(row, "operator")?,
+            status: status.parse().map_err(DatabaseError::RowMappingError)?,
+            statistics,
+            error_message: optional_column(row, "error_message")?,
+        })
+    }
+}
@@ -0,0 +1,107 @@
+use chrono::{DateTime, Duration, Utc};
+
+/// 並列に取得する1区間 (`start`以上`end`未満)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimePartition {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl TimePartition {
+    /// `start`以上`end`以下の期間を、`boundaries`のうち期間内にある時刻で区切る
+    ///
+    /// 区間は重ならず、時刻順に隙間なく並ぶため、各区間を時刻順に取得して連結すれば全体も時刻順になる。
+    pub fn split(start: DateTime<Utc>, end: DateTime<Utc>, boundaries: &[DateTime<Utc>]) -> Vec<Self> {
+        // 終了日時を含めるため、最後の区間の上限はtimestamptzの分解能 (1マイクロ秒) だけ後ろにする
+        let end = end + Duration::microseconds(1);
+
+        let mut points: Vec<DateTime<Utc>> = boundaries.iter().copied().filter(|point| *point > start && *point < end).collect();
+        points.sort();
+        points.dedup();
+
+        let mut partitions = Vec::with_capacity(points.len() + 1);
+        let mut from = start;
+        for point in points.into_iter().chain(std::iter::once(end)) {
+            partitions.push(Self { start: from, end: point });
+            from = point;
+        }
+        partitions
+    }
+
+    /// `start`から`interval`ごとの区切りの時刻 (チャンクの情報が得られない場合に使う)
+    pub fn fixed_boundaries(start: DateTime<Utc>, end: DateTime<Utc>, interval: Duration) -> Vec<DateTime<Utc>> {
+        let mut boundaries = Vec::new();
+        let mut point = start + interval;
+        while point <= end {
+            boundaries.push(point);
+            point += interval;
+        }
+        boundaries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(minute: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap() + Duration::minutes(minute)
+    }
+
+    fn assert_contiguous(partitions: &[TimePartition], start: DateTime<Utc>, end: DateTime<Utc>) {
+        assert_eq!(partitions.first().map(|partition| partition.start), Some(start));
+        assert_eq!(partitions.last().map(|partition| partition.end), Some(end + Duration::microseconds(1)));
+        for pair in partitions.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+        assert!(partitions.iter().all(|partition| partition.start < partition.end));
+    }
+
+    #[test]
+    fn splits_at_boundaries_inside_range() {
+        // 範囲外・重複・順不同の境界は無視または整理される
+        let boundaries = [at(120), at(60), at(0), at(60), at(-60), at(300)];
+        let partitions = TimePartition::split(at(30), at(150), &boundaries);
+
+        assert_eq!(partitions.iter().map(|partition| partition.start).collect::<Vec<_>>(), [at(30), at(60), at(120)]);
+        assert_contiguous(&partitions, at(30), at(150));
+    }
+
+    #[test]
+    fn boundary_at_end_keeps_end_in_last_partition() {
+        let partitions = TimePartition::split(at(0), at(60), &[at(60)]);
+        assert_eq!(partitions.len(), 2);
+        assert_eq!(partitions[1].start, at(60));
+        assert_contiguous(&partitions, at(0), at(60));
+    }
+
+    #[test]
+    fn single_instant_range_is_one_partition() {
+        let partitions = TimePartition::split(at(10), at(10), &[at(10)]);
+        assert_eq!(
+            partitions,
+            [TimePartition {
+                start: at(10),
+                end: at(10) + Duration::microseconds(1),
+            }]
+        );
+    }
+
+    #[test]
+    fn fixed_boundaries_leave_partial_last_partition() {
+        let boundaries = TimePartition::fixed_boundaries(at(0), at(150), Duration::hours(1));
+        assert_eq!(boundaries, [at(60), at(120)]);
+
+        let partitions = TimePartition::split(at(0), at(150), &boundaries);
+        assert_eq!(partitions.len(), 3);
+        assert_eq!(partitions[2].end - partitions[2].start, Duration::minutes(30) + Duration::microseconds(1));
+        assert_contiguous(&partitions, at(0), at(150));
+    }
+
+    #[test]
+    fn fixed_boundaries_for_empty_or_exact_ranges() {
+        assert!(TimePartition::fixed_boundaries(at(10), at(10), Duration::hours(1)).is_empty());
+        assert_eq!(TimePartition::fixed_boundaries(at(0), at(120), Duration::hours(1)), [at(60), at(120)]);
+    }
+}
@@ -0,0 +1,22 @@
+use crate::database::{required_column, DatabaseError, FromRow, Row};
+use crate::packet::model::{TopTalker, TrafficBucket};
+
+impl FromRow for TrafficBucket {
+    fn from_row(row: &Row) -> Result<Self, DatabaseError> {
+        Ok(Self {
+            start: required_column(row, "bucket")?,
+            packets: required_column(row, "packets")?,
+            bytes: required_column(row, "bytes")?,
+        })
+    }
+}
+
+impl FromRow for TopTalker {
+    fn from_row(row: &Row) -> Result<Self, DatabaseError> {
+        Ok(Self {
+            address: required_column(row, "address")?,
+            packets: required_column(row, "packets")?,
+            bytes: required_column(row, "bytes")?,
+        })
+    }
+}
@@ -0,0 +1,42 @@
+/// 16ビット単位の1の補数和 (桁上がりは畳み込まない)
+pub fn ones_complement_sum(data: &[u8]) -> u32 {
+    data.chunks(2).fold(0u32, |sum, chunk| {
+        let word = match chunk {
+            [high, low] => u16::from_be_bytes([*high, *low]),
+            [high] => u16::from_be_bytes([*high, 0]),
+            _ => 0,
+        };
+        sum.wrapping_add(u32::from(word))
+    })
+}
+
+pub fn fold(mut sum: u32) -> u16 {
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    sum as u16
+}
+
+/// RFC 1071のインターネットチェックサム
+pub fn internet_checksum(data: &[u8]) -> u16 {
+    !fold(ones_complement_sum(data))
+}
+
+/// IPv4ヘッダのチェックサムを再計算して書き込む
+pub fn update_ipv4_header_checksum(header: &mut [u8]) {
+    header[10] = 0;
+    header[11] = 0;
+    let checksum = internet_checksum(header);
+    header[10..12].copy_from_slice(&checksum.to_be_bytes());
+}
+
+/// TCP/UDP/ICMPv6の疑似ヘッダの和 (IPv4/IPv6共通)
+pub fn pseudo_header_sum(addresses: &[u8], protocol: u8, length: u32) -> u32 {
+    ones_complement_sum(addresses).wrapping_add(u32::from(protocol)).wrapping_add(length >> 16).wrapping_add(length & 0xffff)
+}
+
+/// 和が`old_sum`から`new_sum`に変わったときのチェックサムの差分更新 (RFC 1624)
+pub fn adjust_checksum(checksum: u16, old_sum: u32, new_sum: u32) -> u16 {
+    let sum = u32::from(!checksum) + u32::from(fold(new_sum)) + u32::from(!fold(old_sum));
+    !fold(sum)
+}
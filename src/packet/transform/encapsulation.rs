@@ -0,0 +1,445 @@
+use crate::packet::transform::checksum::update_ipv4_header_checksum;
+use crate::packet::transform::error::TransformError;
+use crate::packet::transform::ethernet::{
+    bytes, l3_offset, l4_offset, read_u16, ETHERNET_HEADER_LEN, ETHERTYPE_IPV4, ETHERTYPE_IPV6, ETHERTYPE_MPLS, ETHERTYPE_MPLS_MULTICAST, ETHERTYPE_TEB, IP_PROTOCOL_GRE,
+    IP_PROTOCOL_UDP,
+};
+use crate::packet::transform::pipeline::FrameTransform;
+use crate::packet::transform::spec::Spec;
+use pnet::util::MacAddr;
+use std::net::Ipv4Addr;
+use std::str::FromStr;
+
+const VXLAN_PORT: u16 = 4789;
+const VXLAN_HEADER_LEN: usize = 8;
+const UDP_HEADER_LEN: usize = 8;
+const IPV4_HEADER_LEN: usize = 20;
+const MPLS_LABEL_LEN: usize = 4;
+const DEFAULT_TTL: u8 = 64;
+const DEFAULT_VXLAN_SOURCE_PORT: u16 = 49152;
+
+/// 外すトンネルの種類 (`vxlan` / `gre` / `mpls`)
+///
+/// 該当するカプセル化がされていないフレームはそのまま通す。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decapsulation {
+    Vxlan,
+    Gre,
+    Mpls,
+}
+
+impl FromStr for Decapsulation {
+    type Err = TransformError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "vxlan" => Ok(Self::Vxlan),
+            "gre" => Ok(Self::Gre),
+            "mpls" => Ok(Self::Mpls),
+            other => Err(TransformError::InvalidSpec(format!("不明なカプセル化の種類です: {}", other))),
+        }
+    }
+}
+
+impl FrameTransform for Decapsulation {
+    fn apply(&self, frame: &mut Vec<u8>) -> Result<(), TransformError> {
+        match self {
+            Self::Vxlan => decapsulate_vxlan(frame),
+            Self::Gre => decapsulate_gre(frame),
+            Self::Mpls => decapsulate_mpls(frame),
+        }
+    }
+}
+
+/// 追加するトンネル
+///
+/// - `vxlan:vni=<VNI>,src=<IPv4>,dst=<IPv4>[,sport=<PORT>][,ttl=<TTL>][,src_mac=<MAC>][,dst_mac=<MAC>]`
+/// - `gre:src=<IPv4>,dst=<IPv4>[,key=<KEY>][,ttl=<TTL>][,src_mac=<MAC>][,dst_mac=<MAC>]`
+/// - `mpls:label=<LABEL>[,tc=<TC>][,ttl=<TTL>]`
+///
+/// 外側のMACを省略した場合は元のフレームのMACを引き継ぐ。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Encapsulation {
+    Vxlan {
+        vni: u32,
+        outer: OuterIpv4,
+        source_port: u16,
+    },
+    Gre {
+        key: Option<u32>,
+        outer: OuterIpv4,
+    },
+    Mpls {
+        label: u32,
+        tc: u8,
+        ttl: u8,
+    },
+}
+
+/// VXLAN/GREの外側のヘッダ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OuterIpv4 {
+    pub src_ip: Ipv4Addr,
+    pub dst_ip: Ipv4Addr,
+    pub ttl: u8,
+    pub src_mac: Option<MacAddr>,
+    pub dst_mac: Option<MacAddr>,
+}
+
+impl OuterIpv4 {
+    fn from_spec(spec: &Spec) -> Result<Self, TransformError> {
+        Ok(Self {
+            src_ip: spec.required("src")?,
+            dst_ip: spec.required("dst")?,
+            ttl: spec.optional("ttl")?.unwrap_or(DEFAULT_TTL),
+            src_mac: spec.optional("src_mac")?,
+            dst_mac: spec.optional("dst_mac")?,
+        })
+    }
+
+    /// 外側のEthernet/IPv4ヘッダを付けて`inner`をカプセル化する
+    fn wrap(&self, inner_frame: &[u8], protocol: u8, l4_header: &[u8]) -> Result<Vec<u8>, TransformError> {
+        let inner_header = bytes(inner_frame, 0, ETHERNET_HEADER_LEN)?;
+        let dst_mac = self.dst_mac.map(|mac| mac.octets()).unwrap_or_else(|| inner_header[0..6].try_into().unwrap_or([0; 6]));
+        let src_mac = self.src_mac.map(|mac| mac.octets()).unwrap_or_else(|| inner_header[6..12].try_into().unwrap_or([0; 6]));
+
+        let total_len = IPV4_HEADER_LEN + l4_header.len() + inner_frame.len();
+        let total_len = u16::try_from(total_len).map_err(|_| TransformError::Unsupported(format!("カプセル化後のサイズが大きすぎます: {} bytes", total_len)))?;
+
+        let mut ip_header = [0u8; IPV4_HEADER_LEN];
+        ip_header[0] = 0x45;
+        ip_header[2..4].copy_from_slice(&total_len.to_be_bytes());
+        ip_header[8] = self.ttl;
+        ip_header[9] = protocol;
+        ip_header[12..16].copy_from_slice(&self.src_ip.octets());
+        ip_header[16..20].copy_from_slice(&self.dst_ip.octets());
+        update_ipv4_header_checksum(&mut ip_header);
+
+        let mut frame = Vec::with_capacity(ETHERNET_HEADER_LEN + usize::from(total_len));
+        frame.extend_from_slice(&dst_mac);
+        frame.extend_from_slice(&src_mac);
+        frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        frame.extend_from_slice(&ip_header);
+        frame.extend_from_slice(l4_header);
+        frame.extend_from_slice(inner_frame);
+        Ok(frame)
+    }
+}
+
+impl FromStr for Encapsulation {
+    type Err = TransformError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let spec = Spec::parse(value)?;
+        match spec.kind {
+            "vxlan" => {
+                let vni: u32 = spec.required("vni")?;
+                if vni > 0x00ff_ffff {
+                    return Err(TransformError::InvalidSpec(format!("VNIは24ビットで指定してください: {}", vni)));
+                }
+                Ok(Self::Vxlan {
+                    vni,
+                    outer: OuterIpv4::from_spec(&spec)?,
+                    source_port: spec.optional("sport")?.unwrap_or(DEFAULT_VXLAN_SOURCE_PORT),
+                })
+            },
+            "gre" => Ok(Self::Gre {
+                key: spec.optional("key")?,
+                outer: OuterIpv4::from_spec(&spec)?,
+            }),
+            "mpls" => {
+                let label: u32 = spec.required("label")?;
+                let tc: u8 = spec.optional("tc")?.unwrap_or(0);
+                if label > 0x000f_ffff || tc > 7 {
+                    return Err(TransformError::InvalidSpec("MPLSのラベルは20ビット、TCは3ビットで指定してください".to_string()));
+                }
+                Ok(Self::Mpls {
+                    label,
+                    tc,
+                    ttl: spec.optional("ttl")?.unwrap_or(DEFAULT_TTL),
+                })
+            },
+            other => Err(TransformError::InvalidSpec(format!("不明なカプセル化の種類です: {}", other))),
+        }
+    }
+}
+
+impl FrameTransform for Encapsulation {
+    fn apply(&self, frame: &mut Vec<u8>) -> Result<(), TransformError> {
+        match self {
+            Self::Vxlan { vni, outer, source_port } => {
+                let udp_len = UDP_HEADER_LEN + VXLAN_HEADER_LEN + frame.len();
+                let udp_len = u16::try_from(udp_len).map_err(|_| TransformError::Unsupported(format!("カプセル化後のサイズが大きすぎます: {} bytes", udp_len)))?;
+
+                // UDPチェックサムはIPv4では0 (未使用) としてよい
+                let mut header = [0u8; UDP_HEADER_LEN + VXLAN_HEADER_LEN];
+                header[0..2].copy_from_slice(&source_port.to_be_bytes());
+                header[2..4].copy_from_slice(&VXLAN_PORT.to_be_bytes());
+                header[4..6].copy_from_slice(&udp_len.to_be_bytes());
+                header[8] = 0x08;
+                header[12..15].copy_from_slice(&vni.to_be_bytes()[1..4]);
+
+                *frame = outer.wrap(frame, IP_PROTOCOL_UDP, &header)?;
+            },
+            Self::Gre { key, outer } => {
+                let mut header = Vec::with_capacity(8);
+                header.extend_from_slice(&(if key.is_some() { 0x2000u16 } else { 0 }).to_be_bytes());
+                header.extend_from_slice(&ETHERTYPE_TEB.to_be_bytes());
+                if let Some(key) = key {
+                    header.extend_from_slice(&key.to_be_bytes());
+                }
+
+                *frame = outer.wrap(frame, IP_PROTOCOL_GRE, &header)?;
+            },
+            Self::Mpls { label, tc, ttl } => {
+                let (ethertype, l3) = l3_offset(frame)?;
+                // 既にMPLSであればスタックの先頭に積む
+                let bottom_of_stack = match ethertype {
+                    ETHERTYPE_MPLS | ETHERTYPE_MPLS_MULTICAST => 0,
+                    ETHERTYPE_IPV4 | ETHERTYPE_IPV6 => 1,
+                    other => return Err(TransformError::Unsupported(format!("MPLSでカプセル化できないEtherTypeです: {:#06x}", other))),
+                };
+
+                let entry = (label << 12) | (u32::from(*tc) << 9) | (bottom_of_stack << 8) | u32::from(*ttl);
+                frame.splice(l3..l3, entry.to_be_bytes());
+                frame[l3 - 2..l3].copy_from_slice(&ETHERTYPE_MPLS.to_be_bytes());
+            },
+        }
+
+        Ok(())
+    }
+}
+
+fn decapsulate_vxlan(frame: &mut Vec<u8>) -> Result<(), TransformError> {
+    let (ethertype, l3) = l3_offset(frame)?;
+    let Some((IP_PROTOCOL_UDP, l4)) = l4_offset(frame, ethertype, l3)? else {
+        return Ok(());
+    };
+    if read_u16(frame, l4 + 2)? != VXLAN_PORT {
+        return Ok(());
+    }
+
+    let inner = l4 + UDP_HEADER_LEN + VXLAN_HEADER_LEN;
+    bytes(frame, inner, ETHERNET_HEADER_LEN)?;
+    frame.drain(..inner);
+    Ok(())
+}
+
+fn decapsulate_gre(frame: &mut Vec<u8>) -> Result<(), TransformError> {
+    let (ethertype, l3) = l3_offset(frame)?;
+    let Some((IP_PROTOCOL_GRE, gre)) = l4_offset(frame, ethertype, l3)? else {
+        return Ok(());
+    };
+
+    let flags = read_u16(frame, gre)?;
+    let protocol = read_u16(frame, gre + 2)?;
+    // チェックサム(C)、キー(K)、シーケンス番号(S)の各フィールドは存在する場合のみ4バイトずつ続く
+    let header_len = 4 + [0x8000, 0x2000, 0x1000].iter().filter(|bit| flags & **bit != 0).count() * 4;
+    let inner = gre + header_len;
+
+    match protocol {
+        ETHERTYPE_TEB => {
+            bytes(frame, inner, ETHERNET_HEADER_LEN)?;
+            frame.drain(..inner);
+        },
+        ETHERTYPE_IPV4 | ETHERTYPE_IPV6 => {
+            // 切り詰められたキャプチャでは内側のIPヘッダまで揃っていないことがある
+            bytes(frame, inner, if protocol == ETHERTYPE_IPV4 { 20 } else { 40 })?;
+            // 外側のL2ヘッダを残し、EtherTypeだけ内側のプロトコルに差し替える
+            frame.drain(l3..inner);
+            frame[l3 - 2..l3].copy_from_slice(&protocol.to_be_bytes());
+        },
+        other => return Err(TransformError::Unsupported(format!("GREのペイロード種別に未対応です: {:#06x}", other))),
+    }
+    Ok(())
+}
+
+fn decapsulate_mpls(frame: &mut Vec<u8>) -> Result<(), TransformError> {
+    let (ethertype, l3) = l3_offset(frame)?;
+    if !matches!(ethertype, ETHERTYPE_MPLS | ETHERTYPE_MPLS_MULTICAST) {
+        return Ok(());
+    }
+
+    let mut offset = l3;
+    loop {
+        let entry = bytes(frame, offset, MPLS_LABEL_LEN)?;
+        offset += MPLS_LABEL_LEN;
+        if entry[2] & 0x01 != 0 {
+            break;
+        }
+    }
+
+    match bytes(frame, offset, 1)?[0] >> 4 {
+        4 | 6 => {
+            let inner_ethertype = if frame[offset] >> 4 == 4 { ETHERTYPE_IPV4 } else { ETHERTYPE_IPV6 };
+            frame.drain(l3..offset);
+            frame[l3 - 2..l3].copy_from_slice(&inner_ethertype.to_be_bytes());
+        },
+        // 先頭ニブルが0ならコントロールワード付きのEthernet疑似回線
+        0 => {
+            let inner = offset + 4;
+            bytes(frame, inner, ETHERNET_HEADER_LEN)?;
+            frame.drain(..inner);
+        },
+        other => return Err(TransformError::Unsupported(format!("MPLS内のペイロードを判別できません (先頭ニブル: {})", other))),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ethernet + IPv4 (GRE) + キー付きGREヘッダ + 内側IPv4ヘッダ
+    fn gre_frame() -> Vec<u8> {
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        let mut outer = [0u8; 20];
+        outer[0] = 0x45;
+        outer[9] = IP_PROTOCOL_GRE;
+        frame.extend_from_slice(&outer);
+        frame.extend_from_slice(&[0x20, 0x00]);
+        frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0, 1]);
+        let mut inner = [0u8; 20];
+        inner[0] = 0x45;
+        frame.extend_from_slice(&inner);
+        frame
+    }
+
+    const DST: [u8; 6] = [0x02, 0, 0, 0, 0, 0x01];
+    const SRC: [u8; 6] = [0x02, 0, 0, 0, 0, 0x02];
+
+    /// 内側のEthernet + IPv4 (UDP) フレーム
+    fn inner_frame() -> Vec<u8> {
+        let mut frame = [DST, SRC].concat();
+        frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        let mut ip = [0u8; 20];
+        ip[0] = 0x45;
+        ip[9] = IP_PROTOCOL_UDP;
+        frame.extend_from_slice(&ip);
+        frame.extend_from_slice(&[0x30, 0x39, 0x00, 0x35, 0x00, 0x0c, 0x00, 0x00, 0xde, 0xad, 0xbe, 0xef]);
+        frame
+    }
+
+    fn encapsulate(spec: &str, mut frame: Vec<u8>) -> Vec<u8> {
+        spec.parse::<Encapsulation>().unwrap().apply(&mut frame).unwrap();
+        frame
+    }
+
+    #[test]
+    fn vxlan_round_trip() {
+        let inner = inner_frame();
+        let mut frame = encapsulate("vxlan:vni=5000,src=192.0.2.1,dst=192.0.2.2,sport=50000,dst_mac=02:00:00:00:00:ff", inner.clone());
+
+        assert_eq!(frame.len(), 14 + 20 + 8 + 8 + inner.len());
+        assert_eq!(frame[0..6], [0x02, 0, 0, 0, 0, 0xff]);
+        assert_eq!(frame[6..12], SRC);
+        assert_eq!(frame[14 + 9], IP_PROTOCOL_UDP);
+        assert_eq!(frame[14 + 12..14 + 20], [192, 0, 2, 1, 192, 0, 2, 2]);
+        assert_eq!(crate::packet::transform::checksum::internet_checksum(&frame[14..34]), 0);
+        assert_eq!(read_u16(&frame, 34).unwrap(), 50000);
+        assert_eq!(read_u16(&frame, 36).unwrap(), VXLAN_PORT);
+        assert_eq!(usize::from(read_u16(&frame, 38).unwrap()), 16 + inner.len());
+        assert_eq!(frame[42..50], [0x08, 0, 0, 0, 0x00, 0x13, 0x88, 0]);
+
+        Decapsulation::Vxlan.apply(&mut frame).unwrap();
+        assert_eq!(frame, inner);
+    }
+
+    #[test]
+    fn gre_round_trip_with_key() {
+        let inner = inner_frame();
+        let mut frame = encapsulate("gre:src=192.0.2.1,dst=192.0.2.2,key=7,ttl=10", inner.clone());
+
+        assert_eq!(frame[14 + 8], 10);
+        assert_eq!(frame[14 + 9], IP_PROTOCOL_GRE);
+        assert_eq!(frame[34..42], [0x20, 0x00, 0x65, 0x58, 0, 0, 0, 7]);
+
+        Decapsulation::Gre.apply(&mut frame).unwrap();
+        assert_eq!(frame, inner);
+    }
+
+    #[test]
+    fn mpls_round_trip_and_stacking() {
+        let inner = inner_frame();
+        let mut frame = encapsulate("mpls:label=100,tc=1,ttl=32", inner.clone());
+        assert_eq!(read_u16(&frame, 12).unwrap(), ETHERTYPE_MPLS);
+        // ラベル100、TC 1、スタックの底、TTL 32
+        assert_eq!(frame[14..18], [0x00, 0x06, 0x43, 0x20]);
+
+        // 既にMPLSのフレームには底ではないエントリとして積む
+        let mut stacked = encapsulate("mpls:label=200", frame.clone());
+        assert_eq!(stacked[14..18], [0x00, 0x0c, 0x80, 0x40]);
+        assert_eq!(stacked[18..22], frame[14..18]);
+
+        Decapsulation::Mpls.apply(&mut stacked).unwrap();
+        assert_eq!(stacked, inner);
+        Decapsulation::Mpls.apply(&mut frame).unwrap();
+        assert_eq!(frame, inner);
+    }
+
+    #[test]
+    fn mpls_pseudowire_with_control_word_is_stripped() {
+        let inner = inner_frame();
+        let mut frame = [DST, SRC].concat();
+        frame.extend_from_slice(&ETHERTYPE_MPLS.to_be_bytes());
+        frame.extend_from_slice(&[0x00, 0x06, 0x41, 0x40]);
+        frame.extend_from_slice(&[0, 0, 0, 0]);
+        frame.extend_from_slice(&inner);
+
+        Decapsulation::Mpls.apply(&mut frame).unwrap();
+        assert_eq!(frame, inner);
+    }
+
+    #[test]
+    fn truncated_mpls_stack_is_rejected() {
+        let mut frame = [DST, SRC].concat();
+        frame.extend_from_slice(&ETHERTYPE_MPLS.to_be_bytes());
+        // スタックの底を示すエントリがないまま途切れている
+        frame.extend_from_slice(&[0x00, 0x06, 0x40, 0x40]);
+        assert!(matches!(Decapsulation::Mpls.apply(&mut frame), Err(TransformError::MalformedFrame(_))));
+    }
+
+    #[test]
+    fn decapsulation_leaves_other_frames_untouched() {
+        for decapsulation in [Decapsulation::Vxlan, Decapsulation::Gre, Decapsulation::Mpls] {
+            let mut frame = inner_frame();
+            decapsulation.apply(&mut frame).unwrap();
+            assert_eq!(frame, inner_frame(), "{:?}", decapsulation);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_encapsulation_specs() {
+        for spec in [
+            "vxlan:vni=16777216,src=192.0.2.1,dst=192.0.2.2",
+            "vxlan:vni=1,src=192.0.2.1",
+            "gre:src=192.0.2.1,dst=bad",
+            "mpls:label=1048576",
+            "mpls:label=1,tc=8",
+            "ipip:src=192.0.2.1",
+        ] {
+            assert!(matches!(spec.parse::<Encapsulation>(), Err(TransformError::InvalidSpec(_))), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn gre_with_inner_ipv4_is_stripped() {
+        let mut frame = gre_frame();
+        Decapsulation::Gre.apply(&mut frame).unwrap();
+        assert_eq!(frame.len(), 14 + 20);
+        assert_eq!(frame[12..14], ETHERTYPE_IPV4.to_be_bytes());
+        assert_eq!(frame[14], 0x45);
+    }
+
+    #[test]
+    fn truncated_gre_is_rejected() {
+        // 内側のIPヘッダが欠けている、またはGREヘッダの途中で切れている
+        for len in [14 + 20 + 8 + 10, 14 + 20 + 8, 14 + 20 + 6] {
+            let mut frame = gre_frame();
+            frame.truncate(len);
+            assert!(matches!(Decapsulation::Gre.apply(&mut frame), Err(TransformError::MalformedFrame(_))), "{} bytes", len);
+        }
+    }
+}
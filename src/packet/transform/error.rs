@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum TransformError {
+    #[error("フレームの解析に失敗しました: {0}")]
+    MalformedFrame(String),
+
+    #[error("未対応のフレームです: {0}")]
+    Unsupported(String),
+
+    #[error("変換指定の解析に失敗しました: {0}")]
+    InvalidSpec(String),
+}
@@ -0,0 +1,107 @@
+use crate::packet::transform::error::TransformError;
+
+pub const ETHERNET_HEADER_LEN: usize = 14;
+pub const ETHERTYPE_OFFSET: usize = 12;
+pub const VLAN_TAG_LEN: usize = 4;
+
+pub const ETHERTYPE_IPV4: u16 = 0x0800;
+pub const ETHERTYPE_IPV6: u16 = 0x86dd;
+pub const ETHERTYPE_VLAN: u16 = 0x8100;
+pub const ETHERTYPE_QINQ: u16 = 0x88a8;
+pub const ETHERTYPE_QINQ_LEGACY: u16 = 0x9100;
+pub const ETHERTYPE_MPLS: u16 = 0x8847;
+pub const ETHERTYPE_MPLS_MULTICAST: u16 = 0x8848;
+pub const ETHERTYPE_TEB: u16 = 0x6558;
+
+pub const IP_PROTOCOL_TCP: u8 = 6;
+pub const IP_PROTOCOL_UDP: u8 = 17;
+pub const IP_PROTOCOL_GRE: u8 = 47;
+
+pub fn is_vlan_tpid(ethertype: u16) -> bool {
+    matches!(ethertype, ETHERTYPE_VLAN | ETHERTYPE_QINQ | ETHERTYPE_QINQ_LEGACY)
+}
+
+/// VLANタグをすべて読み飛ばした位置にあるEtherTypeフィールドのオフセット
+pub fn ethertype_offset(frame: &[u8]) -> Result<usize, TransformError> {
+    let mut offset = ETHERTYPE_OFFSET;
+    while is_vlan_tpid(read_u16(frame, offset)?) {
+        offset += VLAN_TAG_LEN;
+    }
+    Ok(offset)
+}
+
+/// 最内側のEtherTypeとL3ヘッダの開始位置
+pub fn l3_offset(frame: &[u8]) -> Result<(u16, usize), TransformError> {
+    let offset = ethertype_offset(frame)?;
+    Ok((read_u16(frame, offset)?, offset + 2))
+}
+
+pub fn read_u16(data: &[u8], offset: usize) -> Result<u16, TransformError> {
+    let bytes = bytes(data, offset, 2)?;
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+pub fn bytes(data: &[u8], offset: usize, len: usize) -> Result<&[u8], TransformError> {
+    data.get(offset..offset + len).ok_or_else(|| TransformError::MalformedFrame(format!("フレーム長が不足しています: {} bytes", data.len())))
+}
+
+/// IPv4/IPv6ヘッダを読み、L4のプロトコル番号と開始位置を返す (IPv6の拡張ヘッダは辿らない)
+pub fn l4_offset(frame: &[u8], ethertype: u16, l3: usize) -> Result<Option<(u8, usize)>, TransformError> {
+    match ethertype {
+        ETHERTYPE_IPV4 => {
+            let header = bytes(frame, l3, 20)?;
+            let ihl = usize::from(header[0] & 0x0f) * 4;
+            if ihl < 20 {
+                return Err(TransformError::MalformedFrame(format!("IPv4ヘッダ長が不正です: {} bytes", ihl)));
+            }
+            bytes(frame, l3, ihl)?;
+            Ok(Some((header[9], l3 + ihl)))
+        },
+        ETHERTYPE_IPV6 => {
+            let header = bytes(frame, l3, 40)?;
+            Ok(Some((header[6], l3 + 40)))
+        },
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ipv4_frame(version_ihl: u8) -> Vec<u8> {
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        let mut header = [0u8; 24];
+        header[0] = version_ihl;
+        header[9] = IP_PROTOCOL_UDP;
+        frame.extend_from_slice(&header);
+        frame
+    }
+
+    #[test]
+    fn l4_offset_follows_ipv4_options() {
+        assert_eq!(l4_offset(&ipv4_frame(0x45), ETHERTYPE_IPV4, 14).unwrap(), Some((IP_PROTOCOL_UDP, 34)));
+        assert_eq!(l4_offset(&ipv4_frame(0x46), ETHERTYPE_IPV4, 14).unwrap(), Some((IP_PROTOCOL_UDP, 38)));
+    }
+
+    #[test]
+    fn l4_offset_rejects_invalid_ihl() {
+        // IHLが5未満、またはオプションの途中でキャプチャが切れている
+        for version_ihl in [0x40, 0x44, 0x47] {
+            assert!(
+                matches!(l4_offset(&ipv4_frame(version_ihl), ETHERTYPE_IPV4, 14), Err(TransformError::MalformedFrame(_))),
+                "{:#04x}",
+                version_ihl
+            );
+        }
+    }
+
+    #[test]
+    fn l3_offset_skips_stacked_vlan_tags() {
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&[0x88, 0xa8, 0x00, 0x64, 0x81, 0x00, 0x00, 0x0a]);
+        frame.extend_from_slice(&ETHERTYPE_IPV6.to_be_bytes());
+        assert_eq!(l3_offset(&frame).unwrap(), (ETHERTYPE_IPV6, 22));
+    }
+}
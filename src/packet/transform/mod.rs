@@ -0,0 +1,14 @@
+mod checksum;
+mod encapsulation;
+mod error;
+mod ethernet;
+mod pipeline;
+mod sanitize;
+mod spec;
+mod vlan;
+
+pub use encapsulation::{Decapsulation, Encapsulation};
+pub use error::TransformError;
+pub use pipeline::TransformPipeline;
+pub use sanitize::{AnonymizationKey, PayloadPolicy, Sanitizer};
+pub use vlan::VlanOperation;
@@ -0,0 +1,59 @@
+use crate::packet::transform::error::TransformError;
+use crate::packet::transform::ethernet::{ETHERNET_HEADER_LEN, ETHERTYPE_IPV4};
+
+/// 送信前のEthernetフレームに対する1段の変換
+pub trait FrameTransform: Send + Sync {
+    fn apply(&self, frame: &mut Vec<u8>) -> Result<(), TransformError>;
+
+    /// 顧客情報を取り除く変換か。L2ヘッダのないIPパケットにも適用し、元のアドレスをログに出さない
+    fn sanitizes(&self) -> bool {
+        false
+    }
+}
+
+/// 登録順にフレームへ変換を適用する
+#[derive(Default)]
+pub struct TransformPipeline {
+    stages: Vec<Box<dyn FrameTransform>>,
+}
+
+impl TransformPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, stage: impl FrameTransform + 'static) {
+        self.stages.push(Box::new(stage));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    pub fn apply(&self, frame: &mut Vec<u8>) -> Result<(), TransformError> {
+        self.stages.iter().try_for_each(|stage| stage.apply(frame))
+    }
+
+    pub fn sanitizes(&self) -> bool {
+        self.stages.iter().any(|stage| stage.sanitizes())
+    }
+
+    /// L3のまま送るIPv4パケットにサニタイズのみを適用する
+    ///
+    /// VLANやトンネルの変換はL2ヘッダがないと意味を持たないため適用しない。
+    pub fn apply_ipv4(&self, packet: &mut Vec<u8>) -> Result<(), TransformError> {
+        if !self.sanitizes() {
+            return Ok(());
+        }
+
+        // 変換はEthernetフレームを前提にしているため、仮のL2ヘッダを付けて適用し、後で取り除く
+        let mut frame = Vec::with_capacity(ETHERNET_HEADER_LEN + packet.len());
+        frame.extend_from_slice(&[0u8; 12]);
+        frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        frame.extend_from_slice(packet);
+        self.stages.iter().filter(|stage| stage.sanitizes()).try_for_each(|stage| stage.apply(&mut frame))?;
+
+        *packet = frame.split_off(ETHERNET_HEADER_LEN);
+        Ok(())
+    }
+}
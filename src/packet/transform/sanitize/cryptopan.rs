@@ -0,0 +1,176 @@
+use crate::packet::transform::error::TransformError;
+use aes::cipher::{BlockEncrypt, KeyInit};
+use aes::Aes128;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+const BLOCK_LEN: usize = 16;
+/// 匿名化結果を覚えておくアドレスの上限。超えたら一度捨てて作り直す
+const CACHE_CAPACITY: usize = 65536;
+
+/// Crypto-PAnの鍵 (前半16バイトがAES鍵、後半16バイトがパッドの元)
+#[derive(Clone)]
+pub struct AnonymizationKey([u8; 32]);
+
+impl FromStr for AnonymizationKey {
+    type Err = TransformError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if value.len() != 64 || !value.is_ascii() {
+            return Err(TransformError::InvalidSpec("匿名化の鍵は64桁の16進数で指定してください".to_string()));
+        }
+
+        let mut key = [0u8; 32];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&value[i * 2..i * 2 + 2], 16).map_err(|_| TransformError::InvalidSpec("匿名化の鍵に16進数以外の文字が含まれています".to_string()))?;
+        }
+        Ok(Self(key))
+    }
+}
+
+// 鍵がログに出ないようにする
+impl std::fmt::Debug for AnonymizationKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AnonymizationKey(***)")
+    }
+}
+
+/// プレフィックスを保存するIPアドレスの匿名化 (Crypto-PAn)
+///
+/// 元のアドレスが上位nビットを共有していれば、匿名化後も上位nビットを共有する。
+pub struct CryptoPan {
+    cipher: Aes128,
+    pad: [u8; BLOCK_LEN],
+    cache: Mutex<HashMap<IpAddr, IpAddr>>,
+}
+
+impl CryptoPan {
+    pub fn new(key: &AnonymizationKey) -> Self {
+        let cipher = Aes128::new(key.0[..BLOCK_LEN].into());
+        let mut pad = [0u8; BLOCK_LEN];
+        pad.copy_from_slice(&key.0[BLOCK_LEN..]);
+        cipher.encrypt_block((&mut pad).into());
+
+        Self {
+            cipher,
+            pad,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn anonymize(&self, address: IpAddr) -> IpAddr {
+        if let Some(anonymized) = self.cache.lock().ok().and_then(|cache| cache.get(&address).copied()) {
+            return anonymized;
+        }
+
+        let anonymized = match address {
+            IpAddr::V4(v4) => IpAddr::V4(Ipv4Addr::from(self.anonymize_bits(u128::from(u32::from(v4)) << 96, 32) as u32)),
+            IpAddr::V6(v6) => IpAddr::V6(Ipv6Addr::from(self.anonymize_bits(u128::from(v6), 128))),
+        };
+        if let Ok(mut cache) = self.cache.lock() {
+            // 送信元が散らばるキャプチャでも際限なく増えないようにする
+            if cache.len() >= CACHE_CAPACITY {
+                cache.clear();
+            }
+            cache.insert(address, anonymized);
+        }
+        anonymized
+    }
+
+    /// 上位`width`ビットを匿名化する (`original`は上位詰めで渡す)
+    fn anonymize_bits(&self, original: u128, width: u32) -> u128 {
+        let pad = u128::from_be_bytes(self.pad);
+        let mut result = 0u128;
+
+        for position in 0..width {
+            // 元のアドレスの上位positionビットとパッドの残りを連結して暗号化する
+            let mask = if position == 0 { 0 } else { u128::MAX << (128 - position) };
+            let mut block = ((original & mask) | (pad & !mask)).to_be_bytes();
+            self.cipher.encrypt_block((&mut block).into());
+            result |= u128::from(block[0] >> 7) << (127 - position);
+        }
+
+        let anonymized = result ^ original;
+        anonymized >> (128 - width)
+    }
+
+    /// 鍵から決まる擬似乱数ブロック (MACアドレスのスクランブルに使う)
+    pub fn keyed_block(&self, input: &[u8]) -> [u8; BLOCK_LEN] {
+        let mut block = [0u8; BLOCK_LEN];
+        for (dst, (src, pad)) in block.iter_mut().zip(input.iter().chain(std::iter::repeat(&0)).zip(self.pad.iter())) {
+            *dst = src ^ pad;
+        }
+        self.cipher.encrypt_block((&mut block).into());
+        block
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cryptopan() -> CryptoPan {
+        CryptoPan::new(&"0123456789abcdef".repeat(4).parse().unwrap())
+    }
+
+    /// 上位から何ビット一致しているか
+    fn common_prefix(a: IpAddr, b: IpAddr) -> u32 {
+        match (a, b) {
+            (IpAddr::V4(a), IpAddr::V4(b)) => (u32::from(a) ^ u32::from(b)).leading_zeros(),
+            (IpAddr::V6(a), IpAddr::V6(b)) => (u128::from(a) ^ u128::from(b)).leading_zeros(),
+            _ => panic!("アドレスファミリが異なります"),
+        }
+    }
+
+    #[test]
+    fn preserves_shared_prefix_length() {
+        let cryptopan = cryptopan();
+        let pairs = [
+            ("192.0.2.1", "192.0.2.200"),
+            ("10.1.0.1", "10.1.255.1"),
+            ("10.0.0.1", "172.16.0.1"),
+            ("198.51.100.7", "198.51.100.6"),
+            ("2001:db8::1", "2001:db8::ffff"),
+            ("2001:db8:1::1", "2001:db8:2::1"),
+        ];
+
+        for (a, b) in pairs {
+            let (a, b): (IpAddr, IpAddr) = (a.parse().unwrap(), b.parse().unwrap());
+            let (anonymized_a, anonymized_b) = (cryptopan.anonymize(a), cryptopan.anonymize(b));
+            assert_ne!(anonymized_a, a);
+            assert_eq!(common_prefix(anonymized_a, anonymized_b), common_prefix(a, b), "{} / {}", a, b);
+        }
+    }
+
+    #[test]
+    fn same_key_gives_same_mapping() {
+        let address: IpAddr = "203.0.113.9".parse().unwrap();
+        assert_eq!(cryptopan().anonymize(address), cryptopan().anonymize(address));
+
+        let other = CryptoPan::new(&"fedcba9876543210".repeat(4).parse().unwrap());
+        assert_ne!(other.anonymize(address), cryptopan().anonymize(address));
+    }
+
+    #[test]
+    fn cache_is_bounded() {
+        let cryptopan = cryptopan();
+        let first = cryptopan.anonymize(IpAddr::V4(Ipv4Addr::from(0u32)));
+        for address in 0..CACHE_CAPACITY as u32 + 10 {
+            cryptopan.anonymize(IpAddr::V4(Ipv4Addr::from(address)));
+        }
+
+        assert!(cryptopan.cache.lock().unwrap().len() <= CACHE_CAPACITY);
+        // 捨てた後に計算し直しても同じ結果になる
+        assert_eq!(cryptopan.anonymize(IpAddr::V4(Ipv4Addr::from(0u32))), first);
+    }
+
+    #[test]
+    fn rejects_malformed_keys() {
+        for key in ["abcd".to_string(), "zz".repeat(32), "0".repeat(65)] {
+            assert!(key.parse::<AnonymizationKey>().is_err(), "{}", key);
+        }
+    }
+}
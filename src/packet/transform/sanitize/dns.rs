@@ -0,0 +1,167 @@
+use crate::packet::transform::sanitize::cryptopan::CryptoPan;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+const HEADER_LEN: usize = 12;
+const TYPE_A: u16 = 1;
+const TYPE_NS: u16 = 2;
+const TYPE_CNAME: u16 = 5;
+const TYPE_SOA: u16 = 6;
+const TYPE_PTR: u16 = 12;
+const TYPE_MX: u16 = 15;
+const TYPE_TXT: u16 = 16;
+const TYPE_AAAA: u16 = 28;
+
+/// DNSメッセージ中のドメイン名とTXTを長さを保ったまま伏せ、A/AAAAのアドレスを匿名化する
+///
+/// 解析できない箇所に達した時点でそれ以降はそのまま残す。
+pub fn scrub_dns(message: &mut [u8], cryptopan: Option<&CryptoPan>) {
+    let _ = scrub_message(message, cryptopan);
+}
+
+fn scrub_message(message: &mut [u8], cryptopan: Option<&CryptoPan>) -> Option<()> {
+    let count = |offset: usize| message.get(offset..offset + 2).map(|b| u16::from_be_bytes([b[0], b[1]]));
+    let questions = count(4)?;
+    let records = u32::from(count(6)?) + u32::from(count(8)?) + u32::from(count(10)?);
+
+    let mut offset = HEADER_LEN;
+    for _ in 0..questions {
+        offset = scrub_name(message, offset)? + 4;
+    }
+
+    for _ in 0..records {
+        offset = scrub_name(message, offset)?;
+        let fixed = message.get(offset..offset + 10)?;
+        let record_type = u16::from_be_bytes([fixed[0], fixed[1]]);
+        let length = usize::from(u16::from_be_bytes([fixed[8], fixed[9]]));
+        let rdata = offset + 10;
+        let end = rdata + length;
+        message.get(rdata..end)?;
+
+        match record_type {
+            TYPE_A if length == 4 => {
+                if let Some(cryptopan) = cryptopan {
+                    let octets: [u8; 4] = message[rdata..end].try_into().ok()?;
+                    if let IpAddr::V4(anonymized) = cryptopan.anonymize(IpAddr::V4(Ipv4Addr::from(octets))) {
+                        message[rdata..end].copy_from_slice(&anonymized.octets());
+                    }
+                }
+            },
+            TYPE_AAAA if length == 16 => {
+                if let Some(cryptopan) = cryptopan {
+                    let octets: [u8; 16] = message[rdata..end].try_into().ok()?;
+                    if let IpAddr::V6(anonymized) = cryptopan.anonymize(IpAddr::V6(Ipv6Addr::from(octets))) {
+                        message[rdata..end].copy_from_slice(&anonymized.octets());
+                    }
+                }
+            },
+            TYPE_NS | TYPE_CNAME | TYPE_PTR => {
+                scrub_name(message, rdata)?;
+            },
+            TYPE_MX => {
+                scrub_name(message, rdata + 2)?;
+            },
+            TYPE_SOA => {
+                let next = scrub_name(message, rdata)?;
+                scrub_name(message, next)?;
+            },
+            TYPE_TXT => {
+                // 各文字列の長さバイトは残して中身だけ伏せる
+                let mut position = rdata;
+                while position < end {
+                    let length = usize::from(message[position]);
+                    let text = message.get_mut(position + 1..(position + 1 + length).min(end))?;
+                    text.fill(b'x');
+                    position += 1 + length;
+                }
+            },
+            _ => {},
+        }
+
+        offset = end;
+    }
+
+    Some(())
+}
+
+/// ラベルの中身を`x`で埋め、名前の直後のオフセットを返す
+///
+/// 圧縮ポインタの参照先はメッセージを先頭から辿る過程で伏せられる。
+fn scrub_name(message: &mut [u8], mut offset: usize) -> Option<usize> {
+    loop {
+        let length = *message.get(offset)?;
+        match length {
+            0 => return Some(offset + 1),
+            length if length & 0xc0 == 0xc0 => return Some(offset + 2),
+            length => {
+                let label = message.get_mut(offset + 1..offset + 1 + usize::from(length))?;
+                label.fill(b'x');
+                offset += 1 + usize::from(length);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `www.example.com`のAレコードを1件返す応答 (回答の名前は質問への圧縮ポインタ)
+    fn response() -> Vec<u8> {
+        let mut message = vec![0x12, 0x34, 0x81, 0x80, 0, 1, 0, 1, 0, 0, 0, 0];
+        message.extend_from_slice(b"\x03www\x07example\x03com\x00");
+        message.extend_from_slice(&[0, 1, 0, 1]);
+        message.extend_from_slice(&[0xc0, 0x0c, 0, 1, 0, 1, 0, 0, 0x0e, 0x10, 0, 4, 192, 0, 2, 10]);
+        message
+    }
+
+    fn cryptopan() -> CryptoPan {
+        CryptoPan::new(&"0123456789abcdef".repeat(4).parse().unwrap())
+    }
+
+    #[test]
+    fn scrubs_names_and_anonymizes_addresses() {
+        let original = response();
+        let mut message = original.clone();
+        scrub_dns(&mut message, Some(&cryptopan()));
+
+        assert_eq!(message.len(), original.len());
+        assert_eq!(message[..12], original[..12]);
+        assert_eq!(&message[12..29], b"\x03xxx\x07xxxxxxx\x03xxx\x00");
+        // 圧縮ポインタとレコードの固定部分は残る
+        assert_eq!(message[33..45], original[33..45]);
+        assert_ne!(message[45..49], [192, 0, 2, 10]);
+        assert_eq!(
+            message[45..49],
+            match cryptopan().anonymize(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 10))) {
+                IpAddr::V4(anonymized) => anonymized.octets(),
+                IpAddr::V6(_) => unreachable!(),
+            }
+        );
+    }
+
+    #[test]
+    fn keeps_addresses_without_key() {
+        let mut message = response();
+        scrub_dns(&mut message, None);
+        assert_eq!(message[45..49], [192, 0, 2, 10]);
+    }
+
+    #[test]
+    fn scrubs_txt_strings_but_keeps_lengths() {
+        let mut message = vec![0, 0, 0x81, 0x80, 0, 0, 0, 1, 0, 0, 0, 0, 0];
+        message.extend_from_slice(&[0, 16, 0, 1, 0, 0, 0, 0, 0, 8]);
+        message.extend_from_slice(b"\x03abc\x03def");
+        scrub_dns(&mut message, None);
+        assert_eq!(&message[23..], b"\x03xxx\x03xxx");
+    }
+
+    #[test]
+    fn stops_at_truncated_message() {
+        let original = response();
+        let mut message = original[..20].to_vec();
+        scrub_dns(&mut message, Some(&cryptopan()));
+        // 読めたラベルまでは伏せ、途中で切れていてもパニックしない
+        assert_eq!(&message[12..17], b"\x03xxx\x07");
+        assert_eq!(message.len(), 20);
+    }
+}
@@ -0,0 +1,114 @@
+const METHODS: [&[u8]; 9] = [
+    b"GET ",
+    b"POST ",
+    b"PUT ",
+    b"HEAD ",
+    b"DELETE ",
+    b"OPTIONS ",
+    b"PATCH ",
+    b"CONNECT ",
+    b"TRACE ",
+];
+
+/// 値を伏せるヘッダ (小文字で比較する)
+const SENSITIVE_HEADERS: [&[u8]; 9] = [
+    b"host",
+    b"cookie",
+    b"set-cookie",
+    b"authorization",
+    b"proxy-authorization",
+    b"referer",
+    b"x-forwarded-for",
+    b"x-real-ip",
+    b"forwarded",
+];
+
+/// HTTP/1.xのリクエストURIのクエリ文字列と機微なヘッダの値を、長さを保ったまま伏せる
+///
+/// TCPのシーケンス番号がずれないよう、バイト数は変えない。
+pub fn scrub_http(payload: &mut [u8]) {
+    let is_request = METHODS.iter().any(|method| payload.starts_with(method));
+    if !is_request && !payload.starts_with(b"HTTP/1.") {
+        return;
+    }
+
+    let header_end = find(payload, b"\r\n\r\n").unwrap_or(payload.len());
+    let mut line_start = 0;
+    let mut first_line = true;
+
+    while line_start < header_end {
+        let line_end = find(&payload[line_start..header_end], b"\r\n").map(|pos| line_start + pos).unwrap_or(header_end);
+        let line = &mut payload[line_start..line_end];
+
+        if first_line {
+            if is_request {
+                scrub_query(line);
+            }
+            first_line = false;
+        } else if let Some(colon) = line.iter().position(|b| *b == b':') {
+            let name = line[..colon].to_ascii_lowercase();
+            if SENSITIVE_HEADERS.iter().any(|header| name.trim_ascii() == *header) {
+                let value_start = colon + 1 + line[colon + 1..].iter().take_while(|b| **b == b' ').count();
+                line[value_start..].fill(b'x');
+            }
+        }
+
+        line_start = line_end + 2;
+    }
+}
+
+/// リクエスト行のURIのうち`?`以降を伏せる
+fn scrub_query(request_line: &mut [u8]) {
+    let Some(uri_start) = request_line.iter().position(|b| *b == b' ').map(|pos| pos + 1) else {
+        return;
+    };
+    let uri_end = request_line[uri_start..].iter().position(|b| *b == b' ').map(|pos| uri_start + pos).unwrap_or(request_line.len());
+
+    if let Some(query) = request_line[uri_start..uri_end].iter().position(|b| *b == b'?') {
+        request_line[uri_start + query + 1..uri_end].fill(b'x');
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scrubbed(payload: &[u8]) -> Vec<u8> {
+        let mut payload = payload.to_vec();
+        scrub_http(&mut payload);
+        payload
+    }
+
+    #[test]
+    fn scrubs_query_and_sensitive_request_headers() {
+        let request = b"GET /search?q=secret&id=42 HTTP/1.1\r\nHost: intra.example\r\nAccept: */*\r\nCookie: session=abc\r\n\r\nbody?x=1";
+        let expected = b"GET /search?xxxxxxxxxxxxxx HTTP/1.1\r\nHost: xxxxxxxxxxxxx\r\nAccept: */*\r\nCookie: xxxxxxxxxxx\r\n\r\nbody?x=1";
+        assert_eq!(scrubbed(request), expected);
+    }
+
+    #[test]
+    fn scrubs_set_cookie_in_responses() {
+        let response = b"HTTP/1.1 200 OK\r\nset-cookie: token=1\r\nContent-Length: 0\r\n\r\n";
+        let expected = b"HTTP/1.1 200 OK\r\nset-cookie: xxxxxxx\r\nContent-Length: 0\r\n\r\n";
+        assert_eq!(scrubbed(response), expected);
+    }
+
+    #[test]
+    fn leaves_non_http_payload_untouched() {
+        let payload = b"\x16\x03\x01 Host: tls handshake";
+        assert_eq!(scrubbed(payload), payload);
+    }
+
+    #[test]
+    fn handles_headers_cut_off_by_capture() {
+        // キャプチャがヘッダの途中で切れていても長さを保って伏せる
+        assert_eq!(
+            scrubbed(b"POST /login?user=a HTTP/1.1\r\nAuthorization: Bas"),
+            b"POST /login?xxxxxx HTTP/1.1\r\nAuthorization: xxx"
+        );
+    }
+}
@@ -0,0 +1,7 @@
+mod cryptopan;
+mod dns;
+mod http;
+mod sanitizer;
+
+pub use cryptopan::AnonymizationKey;
+pub use sanitizer::{PayloadPolicy, Sanitizer};
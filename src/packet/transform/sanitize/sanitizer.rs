@@ -0,0 +1,436 @@
+use crate::packet::transform::checksum::{adjust_checksum, fold, ones_complement_sum, pseudo_header_sum, update_ipv4_header_checksum};
+use crate::packet::transform::error::TransformError;
+use crate::packet::transform::ethernet::{bytes, l3_offset, read_u16, ETHERTYPE_IPV4, ETHERTYPE_IPV6, IP_PROTOCOL_TCP, IP_PROTOCOL_UDP};
+use crate::packet::transform::pipeline::FrameTransform;
+use crate::packet::transform::sanitize::cryptopan::{AnonymizationKey, CryptoPan};
+use crate::packet::transform::sanitize::dns::scrub_dns;
+use crate::packet::transform::sanitize::http::scrub_http;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+const ETHERTYPE_ARP: u16 = 0x0806;
+const IP_PROTOCOL_ICMP: u8 = 1;
+const IP_PROTOCOL_ICMPV6: u8 = 58;
+const DNS_PORT: u16 = 53;
+const HTTP_PORTS: [u16; 4] = [80, 3128, 8000, 8080];
+const ICMP_HEADER_LEN: usize = 8;
+
+/// L4ヘッダ以降のペイロードの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PayloadPolicy {
+    #[default]
+    Keep,
+    /// L4ヘッダの直後で切り詰め、長さフィールドを合わせる
+    Truncate,
+    /// 長さを保ったまま0で埋める
+    Zero,
+}
+
+impl FromStr for PayloadPolicy {
+    type Err = TransformError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "keep" => Ok(Self::Keep),
+            "truncate" => Ok(Self::Truncate),
+            "zero" => Ok(Self::Zero),
+            other => Err(TransformError::InvalidSpec(format!("不明なペイロードの扱いです: {}", other))),
+        }
+    }
+}
+
+/// 共有用にフレームから顧客情報を取り除く変換
+///
+/// 書き換え後はIPv4ヘッダとTCP/UDP/ICMPのチェックサムを再計算する。キャプチャが切り詰められている、
+/// またはフラグメント化されている場合は書き換えた範囲の差分だけチェックサムを更新する。
+pub struct Sanitizer {
+    cryptopan: Option<CryptoPan>,
+    scramble_mac: bool,
+    payload: PayloadPolicy,
+    scrub_application: bool,
+}
+
+/// IPパケット内のL4セグメントの位置
+struct Segment {
+    protocol: u8,
+    start: usize,
+    header_len: usize,
+    checksum_offset: usize,
+    uses_pseudo_header: bool,
+}
+
+impl Sanitizer {
+    pub fn new(key: Option<&AnonymizationKey>, scramble_mac: bool, payload: PayloadPolicy, scrub_application: bool) -> Result<Self, TransformError> {
+        if scramble_mac && key.is_none() {
+            return Err(TransformError::InvalidSpec("MACアドレスのスクランブルには匿名化の鍵が必要です".to_string()));
+        }
+
+        Ok(Self {
+            cryptopan: key.map(CryptoPan::new),
+            scramble_mac,
+            payload,
+            scrub_application,
+        })
+    }
+
+    /// 何も書き換えない設定か
+    pub fn is_noop(&self) -> bool {
+        self.cryptopan.is_none() && self.payload == PayloadPolicy::Keep && !self.scrub_application
+    }
+
+    fn scramble(&self, mac: &mut [u8]) {
+        let Some(cryptopan) = &self.cryptopan else {
+            return;
+        };
+        // ブロードキャスト/マルチキャストはそのまま残す
+        if !self.scramble_mac || mac[0] & 0x01 != 0 {
+            return;
+        }
+
+        let block = cryptopan.keyed_block(mac);
+        mac.copy_from_slice(&block[..6]);
+        // ユニキャストかつローカル管理アドレスにする
+        mac[0] = (mac[0] & 0xfc) | 0x02;
+    }
+
+    fn anonymize_address(&self, address: &mut [u8]) {
+        let Some(cryptopan) = &self.cryptopan else {
+            return;
+        };
+
+        match address.len() {
+            4 => {
+                let octets: [u8; 4] = address.try_into().unwrap_or_default();
+                if let IpAddr::V4(anonymized) = cryptopan.anonymize(IpAddr::V4(Ipv4Addr::from(octets))) {
+                    address.copy_from_slice(&anonymized.octets());
+                }
+            },
+            16 => {
+                let octets: [u8; 16] = address.try_into().unwrap_or_default();
+                if let IpAddr::V6(anonymized) = cryptopan.anonymize(IpAddr::V6(Ipv6Addr::from(octets))) {
+                    address.copy_from_slice(&anonymized.octets());
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn sanitize_arp(&self, frame: &mut [u8], arp: usize) -> Result<(), TransformError> {
+        let header = bytes(frame, arp, 28)?;
+        // Ethernet/IPv4のARPのみ対象にする
+        if read_u16(header, 0)? != 1 || read_u16(header, 2)? != ETHERTYPE_IPV4 || header[4] != 6 || header[5] != 4 {
+            return Ok(());
+        }
+
+        self.scramble(&mut frame[arp + 8..arp + 14]);
+        self.anonymize_address(&mut frame[arp + 14..arp + 18]);
+        self.scramble(&mut frame[arp + 18..arp + 24]);
+        self.anonymize_address(&mut frame[arp + 24..arp + 28]);
+        Ok(())
+    }
+
+    fn sanitize_ip(&self, frame: &mut Vec<u8>, ethertype: u16, l3: usize) -> Result<(), TransformError> {
+        let is_v4 = ethertype == ETHERTYPE_IPV4;
+        let (header_len, declared_end, protocol, first_fragment, unfragmented, addresses) = if is_v4 {
+            let header = bytes(frame, l3, 20)?;
+            let header_len = usize::from(header[0] & 0x0f) * 4;
+            let fragment = read_u16(header, 6)?;
+            let first_fragment = fragment & 0x1fff == 0;
+            (
+                header_len,
+                l3 + usize::from(read_u16(header, 2)?),
+                header[9],
+                first_fragment,
+                first_fragment && fragment & 0x2000 == 0,
+                (l3 + 12, 4),
+            )
+        } else {
+            let header = bytes(frame, l3, 40)?;
+            // 拡張ヘッダは辿らないため、次ヘッダがL4でなければペイロード扱いになる
+            (40, l3 + 40 + usize::from(read_u16(header, 4)?), header[6], true, true, (l3 + 8, 16))
+        };
+        if header_len < 20 {
+            return Err(TransformError::MalformedFrame(format!("IPv4のヘッダ長が不正です: {} bytes", header_len)));
+        }
+        bytes(frame, l3, header_len)?;
+
+        let (address_start, address_len) = addresses;
+        let address_range = address_start..address_start + address_len * 2;
+        let segment_start = l3 + header_len;
+        let segment = if first_fragment { Self::segment(frame, protocol, segment_start, is_v4)? } else { None };
+
+        // TSO/LROのキャプチャでは全長が0などヘッダより短い値になっているため、その場合はキャプチャした長さを使う
+        let headers_end = segment.as_ref().map(|segment| segment.start + segment.header_len).unwrap_or(segment_start);
+        let declared_end = if declared_end < headers_end { frame.len() } else { declared_end };
+        let old_address_sum = ones_complement_sum(&frame[address_range.clone()]);
+        let old_segment_sum = segment.as_ref().map(|segment| Self::segment_sum(frame, segment, declared_end));
+
+        self.anonymize_address(&mut frame[address_start..address_start + address_len]);
+        self.anonymize_address(&mut frame[address_start + address_len..address_start + address_len * 2]);
+
+        let payload_start = segment.as_ref().map(|segment| segment.start + segment.header_len).unwrap_or(segment_start);
+        let captured_end = declared_end.min(frame.len());
+
+        if let Some(segment) = &segment {
+            self.sanitize_application(frame, segment, payload_start, captured_end, is_v4);
+        }
+
+        let mut declared_end = declared_end;
+        match self.payload {
+            PayloadPolicy::Keep => {},
+            PayloadPolicy::Zero => {
+                if payload_start < captured_end {
+                    frame[payload_start..captured_end].fill(0);
+                }
+            },
+            PayloadPolicy::Truncate => {
+                frame.truncate(payload_start.min(frame.len()));
+                declared_end = frame.len();
+                let length = if is_v4 { declared_end - l3 } else { declared_end - segment_start };
+                let length = u16::try_from(length).map_err(|_| TransformError::MalformedFrame("IPパケット長が不正です".to_string()))?;
+                frame[l3 + if is_v4 { 2 } else { 4 }..][..2].copy_from_slice(&length.to_be_bytes());
+                if let Some(segment) = segment.as_ref().filter(|segment| segment.protocol == IP_PROTOCOL_UDP) {
+                    frame[segment.start + 4..segment.start + 6].copy_from_slice(&(segment.header_len as u16).to_be_bytes());
+                }
+            },
+        }
+
+        if is_v4 {
+            update_ipv4_header_checksum(&mut frame[l3..l3 + header_len]);
+        }
+
+        if let (Some(segment), Some(old_segment_sum)) = (segment, old_segment_sum) {
+            let checksum_at = segment.start + segment.checksum_offset;
+            let old_checksum = read_u16(frame, checksum_at)?;
+            // IPv4のUDPでチェックサム0は「未計算」を意味するためそのまま残す
+            if segment.protocol == IP_PROTOCOL_UDP && is_v4 && old_checksum == 0 {
+                return Ok(());
+            }
+
+            let segment_len = (declared_end - segment.start) as u32;
+            let address_sum = |frame: &[u8]| {
+                if segment.uses_pseudo_header {
+                    ones_complement_sum(&frame[address_range.clone()])
+                } else {
+                    0
+                }
+            };
+            let pseudo_sum = |frame: &[u8]| {
+                if segment.uses_pseudo_header {
+                    pseudo_header_sum(&frame[address_range.clone()], segment.protocol, segment_len)
+                } else {
+                    0
+                }
+            };
+
+            let checksum = if unfragmented && declared_end <= frame.len() {
+                let sum = pseudo_sum(frame).wrapping_add(Self::segment_sum(frame, &segment, declared_end));
+                !fold(sum)
+            } else {
+                let old_sum = if segment.uses_pseudo_header { old_address_sum } else { 0 }.wrapping_add(old_segment_sum);
+                let new_sum = address_sum(frame).wrapping_add(Self::segment_sum(frame, &segment, declared_end));
+                adjust_checksum(old_checksum, old_sum, new_sum)
+            };
+            let checksum = if checksum == 0 && segment.protocol == IP_PROTOCOL_UDP { 0xffff } else { checksum };
+            frame[checksum_at..checksum_at + 2].copy_from_slice(&checksum.to_be_bytes());
+        }
+
+        Ok(())
+    }
+
+    /// L4ヘッダが完全にキャプチャされている場合のみ位置を返す
+    fn segment(frame: &[u8], protocol: u8, start: usize, is_v4: bool) -> Result<Option<Segment>, TransformError> {
+        let (header_len, checksum_offset, uses_pseudo_header) = match protocol {
+            IP_PROTOCOL_TCP => {
+                let Some(data_offset) = frame.get(start + 12).map(|byte| byte >> 4) else {
+                    return Ok(None);
+                };
+                if data_offset < 5 {
+                    return Err(TransformError::MalformedFrame(format!("TCPのデータオフセットが不正です: {}", data_offset)));
+                }
+                (usize::from(data_offset) * 4, 16, true)
+            },
+            IP_PROTOCOL_UDP => (8, 6, true),
+            IP_PROTOCOL_ICMP if is_v4 => (ICMP_HEADER_LEN, 2, false),
+            IP_PROTOCOL_ICMPV6 if !is_v4 => (ICMP_HEADER_LEN, 2, true),
+            _ => return Ok(None),
+        };
+        // チェックサムの位置はヘッダ長の内側にあるが、切り出す前に範囲を確かめておく
+        if start + header_len.max(checksum_offset + 2) > frame.len() {
+            return Ok(None);
+        }
+
+        Ok(Some(Segment {
+            protocol,
+            start,
+            header_len,
+            checksum_offset,
+            uses_pseudo_header,
+        }))
+    }
+
+    /// チェックサムフィールドを除いた、キャプチャされている範囲のセグメントの和
+    fn segment_sum(frame: &[u8], segment: &Segment, declared_end: usize) -> u32 {
+        let end = declared_end.min(frame.len());
+        let checksum_at = segment.start + segment.checksum_offset;
+        ones_complement_sum(&frame[segment.start..checksum_at]).wrapping_add(ones_complement_sum(&frame[(checksum_at + 2).min(end)..end]))
+    }
+
+    fn sanitize_application(&self, frame: &mut [u8], segment: &Segment, payload_start: usize, captured_end: usize, is_v4: bool) {
+        if payload_start >= captured_end {
+            return;
+        }
+
+        match segment.protocol {
+            IP_PROTOCOL_TCP | IP_PROTOCOL_UDP if self.scrub_application => {
+                let src_port = u16::from_be_bytes([frame[segment.start], frame[segment.start + 1]]);
+                let dst_port = u16::from_be_bytes([frame[segment.start + 2], frame[segment.start + 3]]);
+                let payload = &mut frame[payload_start..captured_end];
+
+                if src_port == DNS_PORT || dst_port == DNS_PORT {
+                    // DNS over TCPは先頭2バイトがメッセージ長
+                    let message = if segment.protocol == IP_PROTOCOL_TCP {
+                        payload.get_mut(2..).unwrap_or_default()
+                    } else {
+                        payload
+                    };
+                    scrub_dns(message, self.cryptopan.as_ref());
+                } else if segment.protocol == IP_PROTOCOL_TCP && (HTTP_PORTS.contains(&src_port) || HTTP_PORTS.contains(&dst_port)) {
+                    scrub_http(payload);
+                }
+            },
+            // ICMPエラーに含まれる元パケットのIPヘッダもアドレスを匿名化する
+            IP_PROTOCOL_ICMP if matches!(frame[segment.start], 3 | 4 | 5 | 11 | 12) => {
+                if let Some(inner) = frame.get(payload_start..captured_end).filter(|inner| inner.len() >= 20 && inner[0] >> 4 == 4) {
+                    let inner_header_len = usize::from(inner[0] & 0x0f) * 4;
+                    self.anonymize_address(&mut frame[payload_start + 12..payload_start + 16]);
+                    self.anonymize_address(&mut frame[payload_start + 16..payload_start + 20]);
+                    if payload_start + inner_header_len <= captured_end {
+                        update_ipv4_header_checksum(&mut frame[payload_start..payload_start + inner_header_len]);
+                    }
+                }
+            },
+            IP_PROTOCOL_ICMPV6
+                if !is_v4 && (1..=4).contains(&frame[segment.start]) && frame.get(payload_start..captured_end).is_some_and(|inner| inner.len() >= 40 && inner[0] >> 4 == 6) =>
+            {
+                self.anonymize_address(&mut frame[payload_start + 8..payload_start + 24]);
+                self.anonymize_address(&mut frame[payload_start + 24..payload_start + 40]);
+            },
+            _ => {},
+        }
+    }
+}
+
+impl FrameTransform for Sanitizer {
+    fn apply(&self, frame: &mut Vec<u8>) -> Result<(), TransformError> {
+        if self.scramble_mac {
+            bytes(frame, 0, 12)?;
+            self.scramble(&mut frame[0..6]);
+            self.scramble(&mut frame[6..12]);
+        }
+
+        let (ethertype, l3) = l3_offset(frame)?;
+        match ethertype {
+            ETHERTYPE_IPV4 | ETHERTYPE_IPV6 => self.sanitize_ip(frame, ethertype, l3),
+            ETHERTYPE_ARP => self.sanitize_arp(frame, l3),
+            _ => Ok(()),
+        }
+    }
+
+    fn sanitizes(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::transform::checksum::internet_checksum;
+
+    const PAYLOAD: &[u8] = b"customer payload";
+
+    /// Ethernet + IPv4 + UDP のフレーム。IPv4の全長には`total_length`をそのまま書き込む
+    fn udp_frame(total_length: u16) -> Vec<u8> {
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        frame.extend_from_slice(&[0x45, 0, 0, 0, 0, 1, 0, 0, 64, IP_PROTOCOL_UDP, 0, 0, 10, 1, 2, 3, 10, 4, 5, 6]);
+        frame[16..18].copy_from_slice(&total_length.to_be_bytes());
+        update_ipv4_header_checksum(&mut frame[14..34]);
+        frame.extend_from_slice(&[0x9c, 0x40, 0x27, 0x0f]);
+        frame.extend_from_slice(&((8 + PAYLOAD.len()) as u16).to_be_bytes());
+        frame.extend_from_slice(&[0x12, 0x34]);
+        frame.extend_from_slice(PAYLOAD);
+        frame
+    }
+
+    /// Ethernet + IPv4 + TCP (オプションなし) のフレーム
+    fn tcp_frame(data_offset: u8) -> Vec<u8> {
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        frame.extend_from_slice(&[0x45, 0, 0, 40, 0, 1, 0, 0, 64, IP_PROTOCOL_TCP, 0, 0, 10, 1, 2, 3, 10, 4, 5, 6]);
+        update_ipv4_header_checksum(&mut frame[14..34]);
+        let mut tcp = [0u8; 20];
+        tcp[12] = data_offset << 4;
+        frame.extend_from_slice(&tcp);
+        frame
+    }
+
+    fn sanitizer() -> Sanitizer {
+        let key: AnonymizationKey = "ab".repeat(32).parse().unwrap();
+        Sanitizer::new(Some(&key), false, PayloadPolicy::Zero, true).unwrap()
+    }
+
+    /// キャプチャした範囲のUDPセグメントに対してチェックサムが正しいか
+    fn udp_checksum_is_valid(frame: &[u8]) -> bool {
+        let segment = &frame[34..];
+        let sum = pseudo_header_sum(&frame[26..34], IP_PROTOCOL_UDP, segment.len() as u32).wrapping_add(ones_complement_sum(segment));
+        fold(sum) == 0xffff
+    }
+
+    #[test]
+    fn zero_total_length_uses_captured_length() {
+        let mut frame = udp_frame(0);
+        sanitizer().apply(&mut frame).unwrap();
+
+        assert_ne!(&frame[26..34], &[10, 1, 2, 3, 10, 4, 5, 6]);
+        assert!(frame[42..].iter().all(|&byte| byte == 0));
+        assert_eq!(internet_checksum(&frame[14..34]), 0);
+        assert!(udp_checksum_is_valid(&frame));
+    }
+
+    #[test]
+    fn total_length_shorter_than_header_uses_captured_length() {
+        let mut frame = udp_frame(12);
+        sanitizer().apply(&mut frame).unwrap();
+
+        assert_ne!(&frame[26..34], &[10, 1, 2, 3, 10, 4, 5, 6]);
+        assert!(frame[42..].iter().all(|&byte| byte == 0));
+        assert!(udp_checksum_is_valid(&frame));
+    }
+
+    #[test]
+    fn header_length_below_minimum_is_rejected() {
+        let mut frame = udp_frame(0);
+        frame[14] = 0x44;
+        assert!(matches!(sanitizer().apply(&mut frame), Err(TransformError::MalformedFrame(_))));
+    }
+
+    #[test]
+    fn tcp_data_offset_below_minimum_is_rejected() {
+        for data_offset in [0, 4] {
+            let mut frame = tcp_frame(data_offset);
+            assert!(matches!(sanitizer().apply(&mut frame), Err(TransformError::MalformedFrame(_))), "{}", data_offset);
+        }
+    }
+
+    #[test]
+    fn truncated_l4_header_keeps_checksum_untouched() {
+        // TCPのチェックサムの途中、UDPのチェックサムの手前でキャプチャが切れている
+        for (mut frame, len) in [(tcp_frame(5), 34 + 17), (udp_frame(0), 34 + 6)] {
+            frame.truncate(len);
+            sanitizer().apply(&mut frame).unwrap();
+            assert_ne!(&frame[26..34], &[10, 1, 2, 3, 10, 4, 5, 6]);
+            assert_eq!(frame.len(), len);
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use crate::packet::transform::error::TransformError;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// `kind:key=value,key=value`形式の変換指定
+pub struct Spec<'a> {
+    pub kind: &'a str,
+    params: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> Spec<'a> {
+    pub fn parse(value: &'a str) -> Result<Self, TransformError> {
+        let (kind, rest) = value.split_once(':').unwrap_or((value, ""));
+        let mut params = HashMap::new();
+        for pair in rest.split(',').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').ok_or_else(|| TransformError::InvalidSpec(format!("key=value形式ではありません: {}", pair)))?;
+            params.insert(key.trim(), value.trim());
+        }
+
+        Ok(Self { kind: kind.trim(), params })
+    }
+
+    pub fn required<T: FromStr>(&self, key: &str) -> Result<T, TransformError> {
+        self.optional(key)?.ok_or_else(|| TransformError::InvalidSpec(format!("{}: {}の指定が必要です", self.kind, key)))
+    }
+
+    pub fn optional<T: FromStr>(&self, key: &str) -> Result<Option<T>, TransformError> {
+        self.params.get(key).map(|value| value.parse::<T>().map_err(|_| TransformError::InvalidSpec(format!("{}: {}の値が不正です: {}", self.kind, key, value)))).transpose()
+    }
+
+    /// `0x`接頭辞の16進数も受け付ける
+    pub fn optional_u16(&self, key: &str) -> Result<Option<u16>, TransformError> {
+        match self.params.get(key) {
+            Some(value) => {
+                let parsed = match value.strip_prefix("0x") {
+                    Some(hex) => u16::from_str_radix(hex, 16),
+                    None => value.parse::<u16>(),
+                };
+                parsed.map(Some).map_err(|_| TransformError::InvalidSpec(format!("{}: {}の値が不正です: {}", self.kind, key, value)))
+            },
+            None => Ok(None),
+        }
+    }
+}
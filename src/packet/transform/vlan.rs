@@ -0,0 +1,182 @@
+use crate::packet::transform::error::TransformError;
+use crate::packet::transform::ethernet::{bytes, is_vlan_tpid, read_u16, ETHERTYPE_OFFSET, ETHERTYPE_VLAN, VLAN_TAG_LEN};
+use crate::packet::transform::pipeline::FrameTransform;
+use crate::packet::transform::spec::Spec;
+use std::str::FromStr;
+
+const MAX_VID: u16 = 0x0fff;
+const MAX_PCP: u8 = 7;
+
+/// 最外側のVLANタグに対する操作
+///
+/// - `pop`: 最外側のタグを外す (タグがなければ何もしない)
+/// - `push:vid=<VID>[,pcp=<PCP>][,tpid=<TPID>]`: 最外側にタグを追加する (QinQは`tpid=0x88a8`)
+/// - `rewrite:vid=<VID>[,pcp=<PCP>]`: 最外側のタグのVID/PCPを書き換える (タグがなければ何もしない)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VlanOperation {
+    Pop,
+    Push { tpid: u16, vid: u16, pcp: u8 },
+    Rewrite { vid: u16, pcp: Option<u8> },
+}
+
+impl FromStr for VlanOperation {
+    type Err = TransformError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let spec = Spec::parse(value)?;
+        let operation = match spec.kind {
+            "pop" => Self::Pop,
+            "push" => Self::Push {
+                tpid: spec.optional_u16("tpid")?.unwrap_or(ETHERTYPE_VLAN),
+                vid: spec.required("vid")?,
+                pcp: spec.optional("pcp")?.unwrap_or(0),
+            },
+            "rewrite" => Self::Rewrite {
+                vid: spec.required("vid")?,
+                pcp: spec.optional("pcp")?,
+            },
+            other => return Err(TransformError::InvalidSpec(format!("不明なVLAN操作です: {}", other))),
+        };
+
+        operation.validate()?;
+        Ok(operation)
+    }
+}
+
+impl VlanOperation {
+    fn validate(&self) -> Result<(), TransformError> {
+        let (vid, pcp) = match *self {
+            Self::Pop => return Ok(()),
+            Self::Push { tpid, vid, pcp } => {
+                if !is_vlan_tpid(tpid) {
+                    return Err(TransformError::InvalidSpec(format!("VLANのTPIDではありません: {:#06x}", tpid)));
+                }
+                (vid, Some(pcp))
+            },
+            Self::Rewrite { vid, pcp } => (vid, pcp),
+        };
+
+        if vid > MAX_VID {
+            return Err(TransformError::InvalidSpec(format!("VIDは0-{}の範囲で指定してください: {}", MAX_VID, vid)));
+        }
+        if pcp.is_some_and(|pcp| pcp > MAX_PCP) {
+            return Err(TransformError::InvalidSpec(format!("PCPは0-{}の範囲で指定してください", MAX_PCP)));
+        }
+        Ok(())
+    }
+}
+
+impl FrameTransform for VlanOperation {
+    fn apply(&self, frame: &mut Vec<u8>) -> Result<(), TransformError> {
+        let tagged = is_vlan_tpid(read_u16(frame, ETHERTYPE_OFFSET)?);
+
+        match *self {
+            Self::Pop => {
+                if tagged {
+                    // タグの後ろに続くEtherTypeまで揃っていなければ外せない
+                    bytes(frame, ETHERTYPE_OFFSET, VLAN_TAG_LEN + 2)?;
+                    frame.drain(ETHERTYPE_OFFSET..ETHERTYPE_OFFSET + VLAN_TAG_LEN);
+                }
+            },
+            Self::Push { tpid, vid, pcp } => {
+                let tci = (u16::from(pcp) << 13) | vid;
+                let mut tag = [0u8; VLAN_TAG_LEN];
+                tag[..2].copy_from_slice(&tpid.to_be_bytes());
+                tag[2..].copy_from_slice(&tci.to_be_bytes());
+                frame.splice(ETHERTYPE_OFFSET..ETHERTYPE_OFFSET, tag);
+            },
+            Self::Rewrite { vid, pcp } => {
+                if tagged {
+                    let tci_offset = ETHERTYPE_OFFSET + 2;
+                    let tci = read_u16(frame, tci_offset)?;
+                    let pcp = pcp.map(u16::from).unwrap_or(tci >> 13);
+                    let tci = (pcp << 13) | (tci & 0x1000) | vid;
+                    frame[tci_offset..tci_offset + 2].copy_from_slice(&tci.to_be_bytes());
+                }
+            },
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet::transform::ethernet::{ETHERTYPE_IPV4, ETHERTYPE_QINQ};
+
+    const DST: [u8; 6] = [0x02, 0, 0, 0, 0, 0x01];
+    const SRC: [u8; 6] = [0x02, 0, 0, 0, 0, 0x02];
+
+    /// 指定したタグ (TPID, TCI) を外側から順に積んだIPv4フレーム
+    fn frame(tags: &[(u16, u16)]) -> Vec<u8> {
+        let mut frame = [DST, SRC].concat();
+        for (tpid, tci) in tags {
+            frame.extend_from_slice(&tpid.to_be_bytes());
+            frame.extend_from_slice(&tci.to_be_bytes());
+        }
+        frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        frame.extend_from_slice(&[0x45, 0, 0, 20]);
+        frame
+    }
+
+    fn apply(operation: &str, mut frame: Vec<u8>) -> Result<Vec<u8>, TransformError> {
+        operation.parse::<VlanOperation>()?.apply(&mut frame)?;
+        Ok(frame)
+    }
+
+    #[test]
+    fn push_adds_outermost_tag() {
+        assert_eq!(apply("push:vid=100,pcp=5", frame(&[])).unwrap(), frame(&[(ETHERTYPE_VLAN, 0xa064)]));
+    }
+
+    #[test]
+    fn push_with_qinq_tpid_stacks_on_existing_tag() {
+        let pushed = apply("push:vid=200,tpid=0x88a8", frame(&[(ETHERTYPE_VLAN, 100)])).unwrap();
+        assert_eq!(pushed, frame(&[(ETHERTYPE_QINQ, 200), (ETHERTYPE_VLAN, 100)]));
+    }
+
+    #[test]
+    fn pop_removes_only_outermost_tag() {
+        assert_eq!(
+            apply("pop", frame(&[(ETHERTYPE_QINQ, 200), (ETHERTYPE_VLAN, 100)])).unwrap(),
+            frame(&[(ETHERTYPE_VLAN, 100)])
+        );
+        assert_eq!(apply("pop", frame(&[(ETHERTYPE_VLAN, 100)])).unwrap(), frame(&[]));
+        assert_eq!(apply("pop", frame(&[])).unwrap(), frame(&[]));
+    }
+
+    #[test]
+    fn rewrite_changes_outermost_vid_and_keeps_dei() {
+        // PCPを省略すると元のPCPを残す
+        let rewritten = apply("rewrite:vid=300", frame(&[(ETHERTYPE_QINQ, 0x7064), (ETHERTYPE_VLAN, 100)])).unwrap();
+        assert_eq!(rewritten, frame(&[(ETHERTYPE_QINQ, 0x712c), (ETHERTYPE_VLAN, 100)]));
+
+        let rewritten = apply("rewrite:vid=1,pcp=2", frame(&[(ETHERTYPE_VLAN, 0xf064)])).unwrap();
+        assert_eq!(rewritten, frame(&[(ETHERTYPE_VLAN, 0x5001)]));
+
+        assert_eq!(apply("rewrite:vid=1", frame(&[])).unwrap(), frame(&[]));
+    }
+
+    #[test]
+    fn truncated_tagged_frame_is_rejected() {
+        let mut truncated = frame(&[(ETHERTYPE_VLAN, 100)]);
+        truncated.truncate(ETHERTYPE_OFFSET + VLAN_TAG_LEN);
+        assert!(matches!(apply("pop", truncated), Err(TransformError::MalformedFrame(_))));
+        assert!(matches!(apply("push:vid=1", DST.to_vec()), Err(TransformError::MalformedFrame(_))));
+    }
+
+    #[test]
+    fn rejects_out_of_range_values() {
+        for spec in [
+            "push:vid=4096",
+            "push:vid=1,pcp=8",
+            "push:vid=1,tpid=0x0800",
+            "rewrite:vid=1,pcp=8",
+            "push",
+            "swap:vid=1",
+        ] {
+            assert!(matches!(spec.parse::<VlanOperation>(), Err(TransformError::InvalidSpec(_))), "{}", spec);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use crate::config::DatabaseConfig;
+use crate::database::{Database, DatabaseError, ExecuteQuery};
+use crate::packet::repository::{PacketColumns, PacketOrdering};
+use crate::preflight::preflight_report::Finding;
+
+/// 再生に必須のカラムと、その型 (information_schema.columns.data_type)
+const REQUIRED_COLUMNS: [(&str, &str); 2] = [("timestamp", "timestamp with time zone"), ("raw_packet", "bytea")];
+
+/// データベースへ接続し、パケットテーブルが再生に使える形かを確認する
+///
+/// 接続に成功した場合は接続プールが初期化されたままになる。
+pub async fn check_database(config: &DatabaseConfig) -> Vec<Finding> {
+    let password = match config.password() {
+        Ok(password) => password,
+        Err(e) => {
+            return vec![Finding::error(
+                "データベース",
+                e.to_string(),
+                Some("config show で database.password* の設定とその設定元を確認してください".to_string()),
+            )]
+        },
+    };
+    if let Err(e) = Database::connect(&config.host, config.port, &config.user, password.as_ref(), &config.database).await {
+        return vec![Finding::error(
+            "データベース",
+            format!("{}:{} に接続できません: {}", config.host, config.port, e),
+            Some(format!(
+                "pg_isready -h {} -p {} でサーバーが起動しているかを確認してください\n\
+                 config show で database.* の設定とその設定元を確認してください",
+                config.host, config.port
+            )),
+        )];
+    }
+
+    match check_schema(Database::get_database(), config.legacy_row_order).await {
+        Ok(findings) => findings,
+        Err(e) => vec![Finding::error(
+            "データベース",
+            format!("スキーマを確認できませんでした: {}", e),
+            None,
+        )],
+    }
+}
+
+async fn check_schema<D: ExecuteQuery + ?Sized>(db: &D, legacy_row_order: bool) -> Result<Vec<Finding>, DatabaseError> {
+    let mut findings = Vec::new();
+
+    let columns = PacketColumns::load(db).await?;
+
+    if columns.is_empty() {
+        findings.push(Finding::error(
+            "データベース",
+            "packets テーブルがありません",
+            Some("database.name (TIMESCALE_DB_DATABASE) が取り込み側と同じデータベースを指しているかを確認してください".to_string()),
+        ));
+    } else {
+        for (name, expected) in REQUIRED_COLUMNS {
+            match columns.data_type(name) {
+                None => findings.push(Finding::error("データベース", format!("packets テーブルに {} カラムがありません", name), None)),
+                Some(actual) if actual != expected => findings.push(Finding::error(
+                    "データベース",
+                    format!("packets.{} の型が {} です ({} である必要があります)", name, actual, expected),
+                    Some(format!("ALTER TABLE packets ALTER COLUMN {} TYPE {};", name, expected)),
+                )),
+                Some(_) => {},
+            }
+        }
+        for mismatch in columns.mismatches() {
+            findings.push(Finding::warning(
+                "データベース",
+                format!(
+                    "packets.{} の型が {} のため読み取りません ({} である必要があります)",
+                    mismatch.name,
+                    mismatch.actual,
+                    mismatch.expected.join(" / ")
+                ),
+                None,
+            ));
+        }
+        if !columns.has_capture_sequence() {
+            let hint = "packet-flow-cli migrate で追加してください (既存の行には格納位置の順に採番されます)\n\
+                        取り込み側はこのカラムを指定せずにINSERTすれば挿入順に採番されます";
+            if legacy_row_order {
+                findings.push(Finding::warning(
+                    "データベース",
+                    format!(
+                        "packets テーブルに {} カラムがないため、旧方式 (database.legacy_row_order) で同じタイムスタンプのパケットを行の格納位置の順に並べます",
+                        PacketOrdering::SEQUENCE_COLUMN
+                    ),
+                    Some(hint.to_string()),
+                ));
+            } else {
+                findings.push(Finding::error(
+                    "データベース",
+                    format!(
+                        "packets テーブルに {} カラムがないため、同じタイムスタンプのパケットの順序が決まりません",
+                        PacketOrdering::SEQUENCE_COLUMN
+                    ),
+                    Some(format!(
+                        "{}\n移行できない場合は database.legacy_row_order = true で行の格納位置の順に並べられます (非推奨)",
+                        hint
+                    )),
+                ));
+            }
+        }
+    }
+
+    let extension = db.query_opt("SELECT extversion FROM pg_extension WHERE extname = 'timescaledb'", &[]).await?;
+    if extension.is_none() {
+        findings.push(Finding::warning(
+            "データベース",
+            "timescaledb 拡張が有効ではありません (availability やタイムラインの集計が使えません)",
+            Some("CREATE EXTENSION IF NOT EXISTS timescaledb;".to_string()),
+        ));
+    } else if !columns.is_empty() {
+        let hypertable = db
+            .query_opt(
+                "SELECT 1 FROM timescaledb_information.hypertables WHERE hypertable_schema = current_schema() AND hypertable_name = 'packets'",
+                &[],
+            )
+            .await?;
+        if hypertable.is_none() {
+            findings.push(Finding::warning(
+                "データベース",
+                "packets がハイパーテーブルではないため、期間を指定した取得が遅くなります",
+                Some("SELECT create_hypertable('packets', 'timestamp', migrate_data => true);".to_string()),
+            ));
+        }
+    }
+
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{MemoryDatabase, Row};
+    use crate::preflight::preflight_report::Severity;
+    use tokio_postgres::types::Type;
+
+    fn column_rows(types: &[(&str, &str)]) -> Vec<Row> {
+        types.iter().map(|(name, data_type)| Row::from_values(&[("column_name", Type::TEXT, name), ("data_type", Type::TEXT, data_type)])).collect()
+    }
+
+    fn messages(findings: &[Finding]) -> Vec<(Severity, &str)> {
+        findings.iter().map(|finding| (finding.severity, finding.message.as_str())).collect()
+    }
+
+    #[tokio::test]
+    async fn reports_missing_table_and_extension() {
+        let db = MemoryDatabase::default();
+
+        let findings = check_schema(&db, false).await.unwrap();
+        assert_eq!(
+            messages(&findings),
+            [
+                (Severity::Error, "packets テーブルがありません"),
+                (Severity::Warning, "timescaledb 拡張が有効ではありません (availability やタイムラインの集計が使えません)"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_column_problems_and_plain_table() {
+        let columns = column_rows(&[("timestamp", "timestamp without time zone"), ("source", "integer")]);
+        let extension = vec![Row::from_values(&[("extversion", Type::TEXT, &"2.14.0")])];
+        let db = MemoryDatabase::default().with_rows(columns).with_rows(extension);
+
+        let findings = check_schema(&db, false).await.unwrap();
+        assert_eq!(
+            messages(&findings),
+            [
+                (
+                    Severity::Error,
+                    "packets.timestamp の型が timestamp without time zone です (timestamp with time zone である必要があります)"
+                ),
+                (Severity::Error, "packets テーブルに raw_packet カラムがありません"),
+                (
+                    Severity::Warning,
+                    "packets.source の型が integer のため読み取りません (text / character varying / character / name である必要があります)"
+                ),
+                (
+                    Severity::Error,
+                    "packets テーブルに capture_seq カラムがないため、同じタイムスタンプのパケットの順序が決まりません"
+                ),
+                (Severity::Warning, "packets がハイパーテーブルではないため、期間を指定した取得が遅くなります"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn accepts_expected_schema() {
+        let columns = column_rows(&[
+            ("timestamp", "timestamp with time zone"),
+            ("raw_packet", "bytea"),
+            ("src_ip", "inet"),
+            ("capture_seq", "bigint"),
+        ]);
+        let extension = vec![Row::from_values(&[("extversion", Type::TEXT, &"2.14.0")])];
+        let hypertable = vec![Row::from_values(&[("?column?", Type::INT4, &1i32)])];
+        let db = MemoryDatabase::default().with_rows(columns).with_rows(extension).with_rows(hypertable);
+
+        assert!(check_schema(&db, false).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn legacy_row_order_downgrades_missing_capture_seq_to_warning() {
+        let columns = column_rows(&[("timestamp", "timestamp with time zone"), ("raw_packet", "bytea")]);
+        let extension = vec![Row::from_values(&[("extversion", Type::TEXT, &"2.14.0")])];
+        let hypertable = vec![Row::from_values(&[("?column?", Type::INT4, &1i32)])];
+        let db = MemoryDatabase::default().with_rows(columns).with_rows(extension).with_rows(hypertable);
+
+        let findings = check_schema(&db, true).await.unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Warning);
+        assert!(findings[0].message.contains("旧方式 (database.legacy_row_order)"));
+        assert!(findings[0].hint.as_deref().is_some_and(|hint| hint.starts_with("packet-flow-cli migrate")));
+    }
+}
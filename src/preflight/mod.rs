@@ -0,0 +1,6 @@
+mod database_check;
+mod network_check;
+mod preflight_report;
+
+pub use network_check::NetworkRequirement;
+pub use preflight_report::PreflightReport;
@@ -0,0 +1,83 @@
+use crate::interface::{describe_interface, has_cap_net_raw, is_usable, InterfaceSelector};
+use crate::preflight::preflight_report::Finding;
+use pnet::datalink;
+use std::env;
+
+/// 再生に必要なネットワーク側の条件
+#[derive(Debug, Clone)]
+pub struct NetworkRequirement {
+    /// 送信先が決まっている場合はそのインターフェース。対話的に選ぶ場合は`None`
+    pub selector: Option<InterfaceSelector>,
+    /// 一覧に停止中等のインターフェースも表示するか
+    pub show_all: bool,
+}
+
+/// 生パケットの送信に必要なCAP_NET_RAWがあるかを確認する
+pub fn check_capability() -> Option<Finding> {
+    // /proc が読めない環境では判定しない
+    if has_cap_net_raw()? {
+        return None;
+    }
+
+    let executable = env::current_exe().map(|path| path.display().to_string()).unwrap_or_else(|_| "target/release/packet-flow-cli".to_string());
+    Some(Finding::error(
+        "権限",
+        "生パケットの送信に必要なCAP_NET_RAWがありません",
+        Some(format!("sudo setcap cap_net_raw,cap_net_admin=eip {}\n(または sudo で実行してください)", executable)),
+    ))
+}
+
+/// 送信先のインターフェースが存在し、送信できる状態かを確認する
+pub fn check_interface(requirement: &NetworkRequirement) -> Vec<Finding> {
+    let interfaces = datalink::interfaces();
+    if interfaces.is_empty() {
+        return vec![Finding::error(
+            "インターフェース",
+            "ネットワークインターフェースが1つも見つかりません",
+            None,
+        )];
+    }
+
+    let usable_names = || interfaces.iter().filter(|interface| is_usable(interface)).map(|interface| interface.name.as_str()).collect::<Vec<_>>().join(", ");
+
+    let Some(selector) = &requirement.selector else {
+        // 対話的に選ぶ場合は候補が残るかだけを確認する
+        if !requirement.show_all && !interfaces.iter().any(is_usable) {
+            return vec![Finding::error(
+                "インターフェース",
+                "起動中でアドレスを持つインターフェースがありません",
+                Some("--all-interfaces で全てのインターフェースを表示するか、ip link set <名前> up で起動してください".to_string()),
+            )];
+        }
+        return Vec::new();
+    };
+
+    let Some(interface) = interfaces.iter().find(|interface| selector.matches(interface)) else {
+        let usable = usable_names();
+        return vec![Finding::error(
+            "インターフェース",
+            format!("指定されたインターフェースが見つかりません: {}", selector),
+            Some(if usable.is_empty() {
+                "ip link でインターフェースの一覧を確認してください".to_string()
+            } else {
+                format!("使用できるインターフェース: {}", usable)
+            }),
+        )];
+    };
+
+    let mut findings = Vec::new();
+    if !interface.is_up() {
+        findings.push(Finding::error(
+            "インターフェース",
+            format!("{} は停止しています", describe_interface(interface)),
+            Some(format!("sudo ip link set {} up", interface.name)),
+        ));
+    } else if !interface.is_running() {
+        findings.push(Finding::warning(
+            "インターフェース",
+            format!("{} はリンクが確立していません", describe_interface(interface)),
+            Some("ケーブルや対向側 (veth・ブリッジ等) の状態を確認してください".to_string()),
+        ));
+    }
+    findings
+}
@@ -0,0 +1,104 @@
+use crate::config::AppConfig;
+use crate::preflight::database_check::check_database;
+use crate::preflight::network_check::{check_capability, check_interface, NetworkRequirement};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// 実行はできるが一部の機能が使えない、または結果が期待と異なる可能性がある
+    Warning,
+    /// このままでは実行できない
+    Error,
+}
+
+/// 事前チェックで見つかった1件の問題
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    /// 問題のあった対象 (権限 / インターフェース / データベース 等)
+    pub subject: &'static str,
+    pub message: String,
+    /// 対処方法 (実行すべきコマンド等)
+    pub hint: Option<String>,
+}
+
+impl Finding {
+    pub fn error(subject: &'static str, message: impl Into<String>, hint: Option<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            subject,
+            message: message.into(),
+            hint,
+        }
+    }
+
+    pub fn warning(subject: &'static str, message: impl Into<String>, hint: Option<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            subject,
+            message: message.into(),
+            hint,
+        }
+    }
+}
+
+/// 起動時の事前チェックの結果
+///
+/// 時間のかかるクエリや送信を始める前に、権限・インターフェース・データベースの状態をまとめて確認する。
+/// 最初の問題で止めずに全ての項目を確認し、見つかった問題を対処方法と合わせて一度に報告する。
+#[derive(Debug, Default)]
+pub struct PreflightReport {
+    findings: Vec<Finding>,
+}
+
+impl PreflightReport {
+    /// 事前チェックを実行する
+    ///
+    /// データベースのチェックでは接続プールの初期化も行うため、以降はそのまま`Database`を使える。
+    pub async fn run(config: &AppConfig, network: Option<&NetworkRequirement>) -> Self {
+        let mut findings = Vec::new();
+
+        if let Some(network) = network {
+            findings.extend(check_capability());
+            findings.extend(check_interface(network));
+        }
+        findings.extend(check_database(&config.database).await);
+
+        Self { findings }
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.findings.iter().any(|finding| finding.severity == Severity::Error)
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.findings.iter().filter(|finding| finding.severity == Severity::Error).count()
+    }
+
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+        let _ = writeln!(output, "事前チェックで {} 件の問題が見つかりました:", self.findings.len());
+
+        // エラーを先に表示する
+        let ordered =
+            self.findings.iter().filter(|finding| finding.severity == Severity::Error).chain(self.findings.iter().filter(|finding| finding.severity == Severity::Warning));
+        for finding in ordered {
+            let label = match finding.severity {
+                Severity::Error => "エラー",
+                Severity::Warning => "警告",
+            };
+            let _ = writeln!(output, "  [{}] {}: {}", label, finding.subject, finding.message);
+            if let Some(hint) = &finding.hint {
+                for (index, line) in hint.lines().enumerate() {
+                    let _ = writeln!(output, "      {} {}", if index == 0 { "対処:" } else { "     " }, line);
+                }
+            }
+        }
+
+        output.trim_end().to_string()
+    }
+}
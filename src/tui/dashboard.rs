@@ -0,0 +1,131 @@
+use crate::packet::reader::{PacketReaderError, ReplayMonitor, ReplaySnapshot};
+use crate::tui::error::TuiError;
+use crate::tui::terminal_session::TerminalSession;
+use ratatui::crossterm::event::KeyCode;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Gauge, List, ListItem, Paragraph, Sparkline};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+
+/// 画面の更新間隔
+const TICK: Duration = Duration::from_millis(200);
+/// 送信レートのグラフに残すサンプル数
+const RATE_HISTORY: usize = 300;
+
+/// 再生タスクの進捗を表示し、完了後はキー入力で閉じる
+///
+/// 再生中にqを押すと再生タスクを中断する。
+pub async fn run_dashboard(session: &mut TerminalSession, monitor: Arc<ReplayMonitor>, mut replay: JoinHandle<Result<(), PacketReaderError>>) -> Result<(), TuiError> {
+    let started = Instant::now();
+    let mut rates: VecDeque<u64> = VecDeque::with_capacity(RATE_HISTORY);
+    let mut previous = (Instant::now(), monitor.snapshot());
+    let mut outcome: Option<Result<(), TuiError>> = None;
+    let mut elapsed = Duration::ZERO;
+
+    loop {
+        let now = Instant::now();
+        let snapshot = monitor.snapshot();
+        let interval = now.duration_since(previous.0).as_secs_f64();
+        if interval > 0.0 && outcome.is_none() {
+            let rate = (snapshot.sent - previous.1.sent) as f64 / interval;
+            if rates.len() == RATE_HISTORY {
+                rates.pop_front();
+            }
+            rates.push_back(rate.round() as u64);
+            elapsed = started.elapsed();
+        }
+        previous = (now, snapshot);
+
+        if outcome.is_none() && replay.is_finished() {
+            outcome = Some(match (&mut replay).await {
+                Ok(Ok(())) => Ok(()),
+                Ok(Err(e)) => Err(TuiError::ReplayError(e.to_string())),
+                Err(e) => Err(TuiError::ReplayError(e.to_string())),
+            });
+        }
+
+        let errors = monitor.recent_errors();
+        session.terminal().draw(|frame| draw(frame, &snapshot, &rates, &errors, elapsed, outcome.as_ref())).map_err(|e| TuiError::TerminalError(e.to_string()))?;
+
+        if let Some(KeyCode::Esc | KeyCode::Char('q')) = session.next_key(Duration::ZERO)? {
+            return match outcome {
+                Some(outcome) => outcome,
+                None => {
+                    replay.abort();
+                    Err(TuiError::Cancelled)
+                },
+            };
+        }
+
+        sleep(TICK).await;
+    }
+}
+
+fn draw(frame: &mut ratatui::Frame, snapshot: &ReplaySnapshot, rates: &VecDeque<u64>, errors: &[String], elapsed: Duration, outcome: Option<&Result<(), TuiError>>) {
+    let [gauge_area, stats_area, rate_area, error_area, help_area] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(4),
+        Constraint::Min(6),
+        Constraint::Length(8),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let processed = snapshot.sent + snapshot.errors;
+    let ratio = if snapshot.total == 0 {
+        0.0
+    } else {
+        (processed as f64 / snapshot.total as f64).min(1.0)
+    };
+    let (title, color) = match outcome {
+        None => (" 再生中 ", Color::Cyan),
+        Some(Ok(())) => (" 再生完了 ", Color::Green),
+        Some(Err(_)) => (" 再生失敗 ", Color::Red),
+    };
+    let gauge = Gauge::default().block(Block::default().borders(Borders::ALL).title(title)).gauge_style(Style::default().fg(color)).ratio(ratio).label(format!(
+        "{} / {} ({:.1}%)",
+        processed,
+        snapshot.total,
+        ratio * 100.0
+    ));
+    frame.render_widget(gauge, gauge_area);
+
+    let seconds = elapsed.as_secs_f64();
+    let average_mbps = if seconds > 0.0 { snapshot.bytes as f64 * 8.0 / seconds / 1_000_000.0 } else { 0.0 };
+    let stats = format!(
+        "経過 {:.1}s  送信 {} pkts / {} bytes  エラー {}  現在 {} pps  平均 {:.2} Mbps",
+        seconds,
+        snapshot.sent,
+        snapshot.bytes,
+        snapshot.errors,
+        rates.back().copied().unwrap_or(0),
+        average_mbps
+    );
+    let queue = format!(
+        "送信キュー {} / {}  満杯で待機 {} 回",
+        snapshot.queue_depth, snapshot.queue_capacity, snapshot.queue_full_waits
+    );
+    let stats = vec![Line::from(stats), Line::from(queue)];
+    frame.render_widget(Paragraph::new(stats).block(Block::default().borders(Borders::ALL).title(" 統計 ")), stats_area);
+
+    // 新しいサンプルが右端に来るよう、表示幅に収まる分だけ渡す
+    let visible = usize::from(rate_area.width.saturating_sub(2));
+    let samples: Vec<u64> = rates.iter().skip(rates.len().saturating_sub(visible)).copied().collect();
+    let sparkline = Sparkline::default().block(Block::default().borders(Borders::ALL).title(" 送信レート (pps) ")).data(&samples).style(Style::default().fg(Color::Yellow));
+    frame.render_widget(sparkline, rate_area);
+
+    let mut items: Vec<ListItem> = Vec::new();
+    if let Some(Err(e)) = outcome {
+        items.push(ListItem::new(Line::styled(e.to_string(), Style::default().fg(Color::Red))));
+    }
+    items.extend(errors.iter().map(|error| ListItem::new(error.as_str())));
+    frame.render_widget(List::new(items).block(Block::default().borders(Borders::ALL).title(" エラー (新しい順) ")), error_area);
+
+    let help = if outcome.is_some() { "q: 終了" } else { "q: 中断" };
+    frame.render_widget(Paragraph::new(help), help_area);
+}
@@ -0,0 +1,19 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum TuiError {
+    #[error("端末の操作に失敗しました: {0}")]
+    TerminalError(String),
+
+    #[error("操作がキャンセルされました")]
+    Cancelled,
+
+    #[error("利用可能なネットワークインターフェースがありません")]
+    NoAvailableNetworkInterface,
+
+    #[error("データの取得に失敗しました: {0}")]
+    FetchError(String),
+
+    #[error("パケット再生に失敗しました: {0}")]
+    ReplayError(String),
+}
@@ -0,0 +1,109 @@
+use crate::interface::{interface_mtu, is_usable, validate_interface};
+use crate::tui::error::TuiError;
+use crate::tui::terminal_session::TerminalSession;
+use pnet::datalink::{self, NetworkInterface};
+use ratatui::crossterm::event::KeyCode;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState};
+use std::time::Duration;
+
+/// インターフェースの一覧から送信先を選ばせる
+///
+/// 既定では再生先として使えるものだけを表示し、aキーで全て表示に切り替える。
+pub fn pick_interface(session: &mut TerminalSession) -> Result<NetworkInterface, TuiError> {
+    let interfaces = datalink::interfaces();
+    if interfaces.is_empty() {
+        return Err(TuiError::NoAvailableNetworkInterface);
+    }
+
+    let mut show_all = !interfaces.iter().any(is_usable);
+    let mut state = TableState::default().with_selected(Some(0));
+    let mut status = String::new();
+
+    loop {
+        let candidates: Vec<&NetworkInterface> = interfaces.iter().filter(|interface| show_all || is_usable(interface)).collect();
+        let rows: Vec<Row> = candidates.iter().map(|interface| interface_row(interface)).collect();
+        let title = if show_all {
+            " 送信先のインターフェース (全て) "
+        } else {
+            " 送信先のインターフェース (使用可能なもの) "
+        };
+
+        session
+            .terminal()
+            .draw(|frame| {
+                let [table_area, status_area, help_area] = Layout::vertical([Constraint::Min(3), Constraint::Length(1), Constraint::Length(1)]).areas(frame.area());
+
+                let table = Table::new(
+                    rows,
+                    [
+                        Constraint::Length(4),
+                        Constraint::Length(16),
+                        Constraint::Length(14),
+                        Constraint::Length(17),
+                        Constraint::Length(6),
+                        Constraint::Min(20),
+                    ],
+                )
+                .header(Row::new(["番号", "名前", "状態", "MAC", "MTU", "アドレス"]).style(Style::default().add_modifier(Modifier::BOLD)))
+                .block(Block::default().borders(Borders::ALL).title(title))
+                .row_highlight_style(Style::default().bg(Color::Blue).fg(Color::White))
+                .highlight_symbol("> ");
+                frame.render_stateful_widget(table, table_area, &mut state);
+                frame.render_widget(Paragraph::new(status.as_str()).style(Style::default().fg(Color::Red)), status_area);
+                frame.render_widget(Paragraph::new("↑/↓: 選択  a: 全て表示/使用可能なもののみ  Enter: 決定  q: キャンセル"), help_area);
+            })
+            .map_err(|e| TuiError::TerminalError(e.to_string()))?;
+
+        match session.next_key(Duration::from_millis(250))? {
+            Some(KeyCode::Up | KeyCode::Char('k')) => state.select_previous(),
+            Some(KeyCode::Down | KeyCode::Char('j')) => {
+                let next = state.selected().map_or(0, |selected| (selected + 1).min(candidates.len().saturating_sub(1)));
+                state.select(Some(next));
+            },
+            Some(KeyCode::Char('a')) => {
+                show_all = !show_all;
+                state.select(Some(0));
+                status.clear();
+            },
+            Some(KeyCode::Enter) => {
+                if let Some(interface) = state.selected().and_then(|selected| candidates.get(selected)) {
+                    // 送信できない状態であれば選び直させる
+                    match validate_interface(interface) {
+                        Ok(()) => return Ok((*interface).clone()),
+                        Err(e) => status = e.to_string(),
+                    }
+                }
+            },
+            Some(KeyCode::Esc | KeyCode::Char('q')) => return Err(TuiError::Cancelled),
+            _ => {},
+        }
+    }
+}
+
+fn interface_row(interface: &NetworkInterface) -> Row<'static> {
+    let mut state = vec![if interface.is_up() { "UP" } else { "DOWN" }];
+    if interface.is_running() {
+        state.push("RUNNING");
+    }
+    if interface.is_loopback() {
+        state.push("LOOPBACK");
+    }
+
+    let state_style = if interface.is_up() && interface.is_running() {
+        Style::default().fg(Color::Green)
+    } else {
+        Style::default().fg(Color::Red)
+    };
+    let addresses = interface.ips.iter().map(|ip| ip.to_string()).collect::<Vec<_>>().join(", ");
+
+    Row::new(vec![
+        Cell::from(interface.index.to_string()),
+        Cell::from(interface.name.clone()),
+        Cell::from(state.join(",")).style(state_style),
+        Cell::from(interface.mac.map(|mac| mac.to_string()).unwrap_or_else(|| "-".to_string())),
+        Cell::from(interface_mtu(&interface.name).map(|mtu| mtu.to_string()).unwrap_or_else(|| "-".to_string())),
+        Cell::from(addresses),
+    ])
+}
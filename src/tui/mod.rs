@@ -0,0 +1,11 @@
+mod dashboard;
+mod error;
+mod interface_picker;
+mod range_picker;
+mod terminal_session;
+
+pub use dashboard::run_dashboard;
+pub use error::TuiError;
+pub use interface_picker::pick_interface;
+pub use range_picker::pick_range;
+pub use terminal_session::TerminalSession;
@@ -0,0 +1,234 @@
+use crate::config::Timezone;
+use crate::packet::availability::AvailabilityReport;
+use crate::packet::model::TrafficBucket;
+use crate::packet::repository::PacketRepository;
+use crate::tui::error::TuiError;
+use crate::tui::terminal_session::TerminalSession;
+use chrono::{DateTime, Duration, Utc};
+use ratatui::crossterm::event::KeyCode;
+use ratatui::crossterm::terminal;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph, Sparkline};
+use std::time::Duration as StdDuration;
+
+/// 一度に移動するバケット数 (PageUp/PageDown)
+const PAGE_STEP: usize = 10;
+
+/// パケット密度のタイムライン上で開始/終了を選ばせる
+pub async fn pick_range(session: &mut TerminalSession, timezone: Timezone) -> Result<(DateTime<Utc>, DateTime<Utc>), TuiError> {
+    let repository = PacketRepository::from_database();
+    let (min_time, max_time) = repository.get_packet_time_range().await.map_err(|e| TuiError::FetchError(e.to_string()))?;
+
+    // 端末の幅に1バケット1列で収まる程度の幅で集計する
+    let columns = terminal::size().map(|(columns, _)| i64::from(columns).saturating_sub(2)).unwrap_or(80);
+    let bucket_width = AvailabilityReport::bucket_width_for(max_time - min_time, columns);
+    let buckets = repository.get_traffic_histogram(min_time, max_time, bucket_width).await.map_err(|e| TuiError::FetchError(e.to_string()))?;
+
+    let mut timeline = Timeline::new(min_time, max_time, bucket_width, &buckets);
+    loop {
+        session.terminal().draw(|frame| timeline.draw(frame, timezone)).map_err(|e| TuiError::TerminalError(e.to_string()))?;
+
+        match session.next_key(StdDuration::from_millis(250))? {
+            Some(KeyCode::Left | KeyCode::Char('h')) => timeline.move_cursor(-1),
+            Some(KeyCode::Right | KeyCode::Char('l')) => timeline.move_cursor(1),
+            Some(KeyCode::PageUp) => timeline.move_cursor(-(PAGE_STEP as isize)),
+            Some(KeyCode::PageDown) => timeline.move_cursor(PAGE_STEP as isize),
+            Some(KeyCode::Home) => timeline.cursor = 0,
+            Some(KeyCode::End) => timeline.cursor = timeline.packets.len() - 1,
+            Some(KeyCode::Char('s')) => timeline.start = Some(timeline.cursor),
+            Some(KeyCode::Char('e')) => timeline.end = Some(timeline.cursor),
+            Some(KeyCode::Enter) => {
+                if let Some(range) = timeline.selected_range() {
+                    return Ok(range);
+                }
+            },
+            Some(KeyCode::Esc | KeyCode::Char('q')) => return Err(TuiError::Cancelled),
+            _ => {},
+        }
+    }
+}
+
+/// 空のバケットも含めて等間隔に並べたパケット数
+struct Timeline {
+    min_time: DateTime<Utc>,
+    max_time: DateTime<Utc>,
+    origin: DateTime<Utc>,
+    bucket_width: Duration,
+    packets: Vec<u64>,
+    bytes: Vec<u64>,
+    cursor: usize,
+    /// 表示している先頭のバケット
+    offset: usize,
+    start: Option<usize>,
+    end: Option<usize>,
+}
+
+impl Timeline {
+    fn new(min_time: DateTime<Utc>, max_time: DateTime<Utc>, bucket_width: Duration, buckets: &[TrafficBucket]) -> Self {
+        // time_bucketは幅の倍数の時刻に揃えるため、最初のバケットを起点にする
+        let origin = buckets.first().map_or(min_time, |bucket| bucket.start);
+        let len = ((max_time - origin).num_milliseconds() / bucket_width.num_milliseconds()).max(0) as usize + 1;
+        let mut packets = vec![0; len];
+        let mut bytes = vec![0; len];
+        for bucket in buckets {
+            let idx = ((bucket.start - origin).num_milliseconds() / bucket_width.num_milliseconds()) as usize;
+            if idx < len {
+                packets[idx] = bucket.packets.max(0) as u64;
+                bytes[idx] = bucket.bytes.max(0) as u64;
+            }
+        }
+
+        Self {
+            min_time,
+            max_time,
+            origin,
+            bucket_width,
+            packets,
+            bytes,
+            cursor: 0,
+            offset: 0,
+            start: None,
+            end: None,
+        }
+    }
+
+    fn move_cursor(&mut self, delta: isize) {
+        self.cursor = self.cursor.saturating_add_signed(delta).min(self.packets.len() - 1);
+    }
+
+    fn bucket_start(&self, idx: usize) -> DateTime<Utc> {
+        self.origin + self.bucket_width * idx as i32
+    }
+
+    /// 開始バケットの先頭から終了バケットの末尾まで (逆順に選ばれていれば入れ替える)
+    fn selected_range(&self) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let (start, end) = (self.start?, self.end?);
+        let (first, last) = (start.min(end), start.max(end));
+        Some((
+            self.bucket_start(first).max(self.min_time),
+            (self.bucket_start(last) + self.bucket_width).min(self.max_time),
+        ))
+    }
+
+    fn is_selected(&self, idx: usize) -> bool {
+        match (self.start, self.end) {
+            (Some(start), Some(end)) => (start.min(end)..=start.max(end)).contains(&idx),
+            (Some(mark), None) | (None, Some(mark)) => idx == mark,
+            (None, None) => false,
+        }
+    }
+
+    fn draw(&mut self, frame: &mut ratatui::Frame, timezone: Timezone) {
+        let format_time = |datetime: DateTime<Utc>| timezone.localize(datetime).format("%Y-%m-%d %H:%M:%S %:z").to_string();
+        let [header_area, chart_area, marker_area, info_area, help_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(5),
+            Constraint::Length(1),
+            Constraint::Length(4),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        // カーソルが見える範囲にスクロールする
+        let visible = usize::from(chart_area.width.saturating_sub(2)).max(1);
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if self.cursor >= self.offset + visible {
+            self.offset = self.cursor + 1 - visible;
+        }
+        let window = self.offset..(self.offset + visible).min(self.packets.len());
+
+        frame.render_widget(
+            Paragraph::new(format!("データの範囲: {} 〜 {}", format_time(self.min_time), format_time(self.max_time))),
+            header_area,
+        );
+
+        let sparkline = Sparkline::default()
+            .block(Block::default().borders(Borders::ALL).title(format!(" パケット数 (1列 = {}s) ", self.bucket_width.num_seconds())))
+            .data(&self.packets[window.clone()])
+            .style(Style::default().fg(Color::Cyan));
+        frame.render_widget(sparkline, chart_area);
+
+        let markers: Vec<Span> = std::iter::once(Span::raw(" "))
+            .chain(window.clone().map(|idx| {
+                if idx == self.cursor {
+                    Span::styled("▲", Style::default().fg(Color::Yellow))
+                } else if self.is_selected(idx) {
+                    Span::styled("━", Style::default().fg(Color::Green))
+                } else {
+                    Span::raw(" ")
+                }
+            }))
+            .collect();
+        frame.render_widget(Paragraph::new(Line::from(markers)), marker_area);
+
+        let cursor_start = self.bucket_start(self.cursor);
+        let selection = match self.selected_range() {
+            Some((start, end)) => format!("選択中: {} 〜 {}", format_time(start), format_time(end)),
+            None => format!(
+                "選択中: 開始 {} / 終了 {}",
+                self.start.map(|idx| format_time(self.bucket_start(idx))).unwrap_or_else(|| "未指定".to_string()),
+                self.end.map(|idx| format_time(self.bucket_start(idx) + self.bucket_width)).unwrap_or_else(|| "未指定".to_string())
+            ),
+        };
+        let info = vec![
+            Line::from(format!("カーソル: {} 〜 {}", format_time(cursor_start), format_time(cursor_start + self.bucket_width))),
+            Line::from(format!("  {} pkts / {} bytes", self.packets[self.cursor], self.bytes[self.cursor])),
+            Line::from(selection),
+        ];
+        frame.render_widget(Paragraph::new(info), info_area);
+        frame.render_widget(Paragraph::new("←/→: 移動  PgUp/PgDn: 10列移動  s: 開始  e: 終了  Enter: 決定  q: キャンセル"), help_area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(second: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap() + Duration::seconds(second)
+    }
+
+    fn bucket(second: i64, packets: i64) -> TrafficBucket {
+        TrafficBucket {
+            start: at(second),
+            packets,
+            bytes: packets * 10,
+        }
+    }
+
+    #[test]
+    fn fills_empty_buckets_between_data() {
+        let timeline = Timeline::new(at(3), at(58), Duration::seconds(10), &[bucket(0, 2), bucket(30, 5)]);
+        assert_eq!(timeline.packets, [2, 0, 0, 5, 0, 0]);
+        assert_eq!(timeline.bytes, [20, 0, 0, 50, 0, 0]);
+    }
+
+    #[test]
+    fn selection_is_clamped_to_data_range_and_order_independent() {
+        let mut timeline = Timeline::new(at(3), at(58), Duration::seconds(10), &[bucket(0, 2)]);
+        assert_eq!(timeline.selected_range(), None);
+
+        timeline.start = Some(5);
+        timeline.end = Some(0);
+        assert_eq!(timeline.selected_range(), Some((at(3), at(58))));
+        assert!(timeline.is_selected(2));
+
+        timeline.start = Some(1);
+        timeline.end = Some(2);
+        assert_eq!(timeline.selected_range(), Some((at(10), at(30))));
+        assert!(!timeline.is_selected(3));
+    }
+
+    #[test]
+    fn cursor_stays_within_timeline() {
+        let mut timeline = Timeline::new(at(0), at(50), Duration::seconds(10), &[]);
+        timeline.move_cursor(-1);
+        assert_eq!(timeline.cursor, 0);
+        timeline.move_cursor(PAGE_STEP as isize);
+        assert_eq!(timeline.cursor, timeline.packets.len() - 1);
+    }
+}
@@ -0,0 +1,51 @@
+use crate::tui::error::TuiError;
+use log::LevelFilter;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::DefaultTerminal;
+use std::time::Duration;
+
+/// 代替画面とrawモードを有効にしている間の端末
+///
+/// 標準出力へのログは画面を崩すため、セッション中はログの出力を止め、終了時に元のレベルへ戻す。
+pub struct TerminalSession {
+    terminal: DefaultTerminal,
+    log_level: LevelFilter,
+}
+
+impl TerminalSession {
+    pub fn start() -> Result<Self, TuiError> {
+        let terminal = ratatui::try_init().map_err(|e| TuiError::TerminalError(e.to_string()))?;
+        let log_level = log::max_level();
+        log::set_max_level(LevelFilter::Off);
+        Ok(Self { terminal, log_level })
+    }
+
+    pub fn terminal(&mut self) -> &mut DefaultTerminal {
+        &mut self.terminal
+    }
+
+    /// `timeout`以内に押されたキーを返す (Ctrl+CはEscとして扱う)
+    pub fn next_key(&self, timeout: Duration) -> Result<Option<KeyCode>, TuiError> {
+        if !event::poll(timeout).map_err(|e| TuiError::TerminalError(e.to_string()))? {
+            return Ok(None);
+        }
+
+        match event::read().map_err(|e| TuiError::TerminalError(e.to_string()))? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => {
+                if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+                    Ok(Some(KeyCode::Esc))
+                } else {
+                    Ok(Some(key.code))
+                }
+            },
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Drop for TerminalSession {
+    fn drop(&mut self) {
+        let _ = ratatui::try_restore();
+        log::set_max_level(self.log_level);
+    }
+}
@@ -0,0 +1,50 @@
+use chrono::Duration;
+use std::fmt::Write;
+
+/// バイト数を1024単位の読みやすい表記にする
+pub fn format_bytes(bytes: i64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// 期間を`1h30m`のような表記にする (1秒未満はミリ秒)
+pub fn format_duration(duration: Duration) -> String {
+    let total = duration.num_seconds();
+    let (days, hours, minutes, seconds) = (total / 86400, total % 86400 / 3600, total % 3600 / 60, total % 60);
+
+    let mut out = String::new();
+    for (value, unit) in [(days, "d"), (hours, "h"), (minutes, "m"), (seconds, "s")] {
+        if value > 0 {
+            let _ = write!(out, "{}{}", value, unit);
+        }
+    }
+    if out.is_empty() {
+        out = format!("{}ms", duration.num_milliseconds());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_bytes_and_durations() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_duration(Duration::seconds(90061)), "1d1h1m1s");
+        assert_eq!(format_duration(Duration::milliseconds(250)), "250ms");
+    }
+}
@@ -1 +1,3 @@
+pub mod format;
 pub mod measure_time;
+pub mod secret;
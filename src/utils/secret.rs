@@ -0,0 +1,35 @@
+use std::fmt;
+use zeroize::Zeroizing;
+
+/// パスワード等の秘密の文字列
+///
+/// 破棄時にメモリを0で上書きし、`Debug`/`Display`では中身の代わりに`MASK`を出す。
+#[derive(Clone)]
+pub struct SecretString(Zeroizing<String>);
+
+impl SecretString {
+    pub const MASK: &'static str = "********";
+
+    /// 秘密の値そのもの。接続時など値が必要な箇所でのみ使う
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(value: String) -> Self {
+        Self(Zeroizing::new(value))
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretString({})", Self::MASK)
+    }
+}
+
+impl fmt::Display for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(Self::MASK)
+    }
+}